@@ -1 +1,256 @@
+use std::collections::HashMap;
 
+use reqwest::{Client, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::datatypes::{
+    DependencyType, GameLoader, LockDependency, LockMod, ModSources, ProjectType, SideSupport,
+};
+
+/*
+    CurseForge's API needs a key (from https://console.curseforge.com), which is read from CURSEFORGE_API_KEY
+    and sent in the 'x-api-key' header of every request.
+
+    CurseForge only gives the sha1 and md5 of files, so the sha512 of a CurseForge lockfile entry is left empty
+    until the file is downloaded (see DownloadTask).
+*/
+
+const CURSEFORGE_API_BASE: &str = "https://api.curseforge.com";
+const MINECRAFT_GAME_ID: u32 = 432;
+
+pub fn curseforge_api_key() -> Option<String> {
+    std::env::var("CURSEFORGE_API_KEY")
+        .ok()
+        .filter(|key| !key.is_empty())
+}
+
+#[derive(Deserialize)]
+struct Data<T> {
+    data: T,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseForgeFile {
+    pub id: u64,
+    pub mod_id: u64,
+    pub display_name: String,
+    pub file_name: String,
+    pub file_date: String,
+    pub file_length: u64,
+    pub download_url: Option<String>, // None if the author doesn't allow downloads from other apps.
+    #[serde(default)]
+    pub hashes: Vec<CurseForgeHash>,
+    #[serde(default)]
+    pub game_versions: Vec<String>, // Minecraft versions, loaders ('Fabric', 'Forge', etc.) and sides ('Client', 'Server').
+    #[serde(default)]
+    pub dependencies: Vec<CurseForgeDependency>,
+    #[serde(default)]
+    pub file_fingerprint: u32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CurseForgeHash {
+    pub value: String,
+    pub algo: u32, // 1 = sha1, 2 = md5
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseForgeDependency {
+    pub mod_id: u64,
+    pub relation_type: u32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseForgeMod {
+    pub id: u64,
+    pub name: String,
+    pub class_id: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FingerprintMatches {
+    exact_matches: Vec<FingerprintMatch>,
+}
+
+#[derive(Deserialize)]
+struct FingerprintMatch {
+    file: CurseForgeFile,
+}
+
+impl CurseForgeMod {
+    // Project type from the class of the project (its category at the top level).
+    pub fn project_type(&self) -> ProjectType {
+        match self.class_id {
+            Some(12) => ProjectType::ResourcePack,
+            Some(6552) => ProjectType::Shader,
+            Some(6945) => ProjectType::Datapack,
+            _ => ProjectType::Mod,
+        }
+    }
+}
+
+async fn post<T: DeserializeOwned>(
+    client: &Client,
+    path: &str,
+    body: serde_json::Value,
+) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+    let api_key = match curseforge_api_key() {
+        Some(result) => result,
+        None => return Err("CurseForge needs an API key. Set CURSEFORGE_API_KEY.".into()),
+    };
+    let response = client
+        .post(format!("{}{}", CURSEFORGE_API_BASE, path))
+        .header("x-api-key", api_key)
+        .json(&body)
+        .send()
+        .await?;
+    match response.status() {
+        StatusCode::OK => Ok(response.json::<Data<T>>().await?.data),
+        status => Err(format!("Received unexpected status code: {}", status).into()),
+    }
+}
+
+pub async fn fetch_curseforge_files(
+    client: &Client,
+    file_ids: &[u64],
+) -> Result<Vec<CurseForgeFile>, Box<dyn std::error::Error + Send + Sync>> {
+    post(
+        client,
+        "/v1/mods/files",
+        serde_json::json!({ "fileIds": file_ids }),
+    )
+    .await
+}
+
+pub async fn fetch_curseforge_mods(
+    client: &Client,
+    mod_ids: &[u64],
+) -> Result<Vec<CurseForgeMod>, Box<dyn std::error::Error + Send + Sync>> {
+    post(
+        client,
+        "/v1/mods",
+        serde_json::json!({ "modIds": mod_ids, "filterPcOnly": true }),
+    )
+    .await
+}
+
+// Looks up many files at once by their fingerprint (see curseforge_fingerprint), returning the ones found, keyed by fingerprint.
+pub async fn curseforge_files_from_fingerprints(
+    client: &Client,
+    fingerprints: &[u32],
+) -> Result<HashMap<u32, CurseForgeFile>, Box<dyn std::error::Error + Send + Sync>> {
+    let matches: FingerprintMatches = post(
+        client,
+        &format!("/v1/fingerprints/{}", MINECRAFT_GAME_ID),
+        serde_json::json!({ "fingerprints": fingerprints }),
+    )
+    .await?;
+    Ok(matches
+        .exact_matches
+        .into_iter()
+        .map(|m| (m.file.file_fingerprint, m.file))
+        .collect())
+}
+
+// CurseForge's fingerprint of a file: MurmurHash2 (seed 1) of its bytes, without whitespace (tabs, newlines and spaces).
+pub fn curseforge_fingerprint(bytes: &[u8]) -> u32 {
+    const M: u32 = 0x5bd1e995;
+    let data: Vec<u8> = bytes
+        .iter()
+        .copied()
+        .filter(|b| !matches!(b, 9 | 10 | 13 | 32))
+        .collect();
+
+    let mut h: u32 = 1 ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> 24;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M) ^ k;
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        for (i, byte) in rest.iter().enumerate() {
+            h ^= (*byte as u32) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^ (h >> 15)
+}
+
+pub fn convert_curseforge_to_lockmod(file: &CurseForgeFile, project: &CurseForgeMod) -> LockMod {
+    let dependencies = file
+        .dependencies
+        .iter()
+        .filter_map(|dependency| {
+            let dependency_type = match dependency.relation_type {
+                1 => DependencyType::Embedded,
+                2 => DependencyType::Optional,
+                3 => DependencyType::Required,
+                5 => DependencyType::Incompatible,
+                _ => return None, // Tools and includes are not needed to run the mod.
+            };
+            Some(LockDependency {
+                source: ModSources::CurseForge,
+                project_id: dependency.mod_id.to_string(),
+                dependency_type,
+            })
+        })
+        .collect();
+
+    // Sides are only listed by some files. A file listing one side is not for the other.
+    let has_side = |side: &str| file.game_versions.iter().any(|v| v == side);
+    let (client_side, server_side) = match (has_side("Client"), has_side("Server")) {
+        (false, false) => (SideSupport::Unknown, SideSupport::Unknown),
+        (client, server) => (side_support(client), side_support(server)),
+    };
+
+    LockMod {
+        name: project.name.clone(),
+        source: ModSources::CurseForge,
+        id: project.id.to_string(),
+        version: file.display_name.clone(),
+        file_name: file.file_name.clone(),
+        release_date: file.file_date.clone(),
+        sha512: String::new(),
+        sha1: file
+            .hashes
+            .iter()
+            .find(|hash| hash.algo == 1)
+            .map(|hash| hash.value.clone()),
+        download_url: file.download_url.clone().unwrap_or_default(),
+        file_id: Some(file.id),
+        dependencies,
+        size: file.file_length,
+        disabled: false,
+        project_type: project.project_type(),
+        loaders: file
+            .game_versions
+            .iter()
+            .filter_map(|v| v.parse::<GameLoader>().ok())
+            .collect(),
+        game_versions: file
+            .game_versions
+            .iter()
+            .filter(|v| v.starts_with(|c: char| c.is_ascii_digit()))
+            .cloned()
+            .collect(),
+        client_side,
+        server_side,
+    }
+}
+
+fn side_support(listed: bool) -> SideSupport {
+    match listed {
+        true => SideSupport::Required,
+        false => SideSupport::Unsupported,
+    }
+}
@@ -1,7 +1,9 @@
+use std::collections::HashMap;
+
 use reqwest::{Client, StatusCode};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::datatypes::{GameLoader, LockDependency, LockMod, ModSources};
+use crate::datatypes::{GameLoader, LockDependency, LockMod, ModSources, ProjectType};
 
 const MODRINTH_API_BASE: &str = "https://api.modrinth.com";
 #[derive(Debug, Deserialize)]
@@ -12,6 +14,10 @@ struct ModrinthVersion {
     date_published: String,
     files: Vec<File>,
     version_number: String, // Convert to something better maybe later?
+    #[serde(default)]
+    loaders: Vec<String>,
+    #[serde(default)]
+    game_versions: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -32,22 +38,124 @@ struct File {
 #[derive(Debug, Deserialize)]
 struct Hashes {
     sha512: String,
+    sha1: String,
 }
 
 #[derive(Deserialize)]
 struct ModrinthProject {
+    id: String,
     title: String,
+    project_type: String,
+    #[serde(default)]
+    loaders: Vec<String>,
+    #[serde(default)]
+    client_side: String,
+    #[serde(default)]
+    server_side: String,
+}
+
+impl ModrinthProject {
+    // Modrinth lists datapacks as "mod" projects with the "datapack" loader, so those are told apart by their loaders.
+    // Projects which are both a mod and a datapack are treated as mods, if they support the game loader.
+    fn project_type(&self, game_loader: Option<&GameLoader>) -> ProjectType {
+        match self.project_type.as_str() {
+            "resourcepack" => ProjectType::ResourcePack,
+            "shader" => ProjectType::Shader,
+            "datapack" => ProjectType::Datapack,
+            _ => {
+                let datapack = self.loaders.iter().any(|l| l == "datapack");
+                let supports_loader = match game_loader {
+                    Some(game_loader) => self.loaders.contains(&game_loader.to_string()),
+                    None => self.loaders.iter().any(|l| l != "datapack"),
+                };
+                if datapack && !supports_loader {
+                    ProjectType::Datapack
+                } else {
+                    ProjectType::Mod
+                }
+            }
+        }
+    }
+}
+
+// Entry of /v2/tag/game_version. Modrinth lists these newest first.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModrinthGameVersion {
+    pub version: String,
+    pub version_type: String, // release, snapshot, alpha or beta
+    pub date: String,
+    pub major: bool,
+}
+
+// Entry of /v2/tag/loader.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModrinthLoader {
+    pub name: String,
+    pub supported_project_types: Vec<String>,
 }
 
+pub async fn fetch_modrinth_game_versions(
+    client: &Client,
+) -> Result<Vec<ModrinthGameVersion>, Box<dyn std::error::Error + Send + Sync>> {
+    let response = client
+        .get(format!("{}/v2/tag/game_version", MODRINTH_API_BASE))
+        .send()
+        .await?;
+    match response.status() {
+        StatusCode::OK => Ok(response.json::<Vec<ModrinthGameVersion>>().await?),
+        status => Err(format!("Received unexpected status code: {}", status).into()),
+    }
+}
+
+pub async fn fetch_modrinth_loaders(
+    client: &Client,
+) -> Result<Vec<ModrinthLoader>, Box<dyn std::error::Error + Send + Sync>> {
+    let response = client
+        .get(format!("{}/v2/tag/loader", MODRINTH_API_BASE))
+        .send()
+        .await?;
+    match response.status() {
+        StatusCode::OK => Ok(response.json::<Vec<ModrinthLoader>>().await?),
+        status => Err(format!("Received unexpected status code: {}", status).into()),
+    }
+}
+
+async fn fetch_modrinth_project(
+    client: &Client,
+    id_slug: &str,
+) -> Result<ModrinthProject, Box<dyn std::error::Error + Send + Sync>> {
+    let response = client
+        .get(format!("{}/v2/project/{}", MODRINTH_API_BASE, id_slug))
+        .send()
+        .await?;
+    match response.status() {
+        StatusCode::OK => Ok(response.json::<ModrinthProject>().await?),
+        StatusCode::NOT_FOUND => Err(format!("(404 Not Found) {}", id_slug).into()),
+        status => Err(format!("Received unexpected status code: {}", status).into()),
+    }
+}
+
+// `project_type` is detected from the project if not given. Versions are filtered by the loaders of the project type.
 pub async fn fetch_modrinth_mod(
     client: &Client,
     id_slug: &str,
     minecraft_version: &String,
     loader: &GameLoader,
+    project_type: Option<ProjectType>,
 ) -> Result<LockMod, Box<dyn std::error::Error + Send + Sync>> {
+    let project = fetch_modrinth_project(client, id_slug).await?;
+    let project_type = project_type.unwrap_or_else(|| project.project_type(Some(loader)));
+    let loaders: Vec<String> = project_type
+        .loaders(loader)
+        .iter()
+        .map(|loader| format!("\"{}\"", loader))
+        .collect();
     let url = format!(
-        "{}/v2/project/{}/version?game_versions=[\"{}\"]&loaders=[\"{}\"]",
-        MODRINTH_API_BASE, id_slug, minecraft_version, loader
+        "{}/v2/project/{}/version?game_versions=[\"{}\"]&loaders=[{}]",
+        MODRINTH_API_BASE,
+        id_slug,
+        minecraft_version,
+        loaders.join(",")
     );
     let response = match client.get(&url).send().await {
         Ok(resp) => resp,
@@ -62,14 +170,7 @@ pub async fn fetch_modrinth_mod(
             // The request was successful, deserialize the JSON
             let modrinth_mod = response.json::<Vec<ModrinthVersion>>().await?;
             if let Some(first_mod) = modrinth_mod.first() {
-                let title = client
-                    .get(format!("{}/v2/project/{}", MODRINTH_API_BASE, id_slug))
-                    .send()
-                    .await?
-                    .json::<ModrinthProject>()
-                    .await?
-                    .title;
-                convert_modrinth_to_lockmod(first_mod, title)
+                convert_modrinth_to_lockmod(first_mod, &project, project_type)
             } else {
                 // Handle empty array case
                 let error_msg = format!("( No Mod File ) {}", id_slug);
@@ -91,7 +192,8 @@ pub async fn fetch_modrinth_mod(
 
 fn convert_modrinth_to_lockmod(
     modrinth_version: &ModrinthVersion,
-    title: String,
+    project: &ModrinthProject,
+    project_type: ProjectType,
 ) -> Result<LockMod, Box<dyn std::error::Error + Send + Sync>> {
     if let Some(first_file) = modrinth_version.files.first() {
         let dependencies: Result<Vec<LockDependency>, String> = modrinth_version
@@ -101,16 +203,28 @@ fn convert_modrinth_to_lockmod(
             .collect();
 
         let lock_mod = LockMod {
-            name: title,
+            name: project.title.clone(),
             source: ModSources::Modrinth,
             id: modrinth_version.project_id.clone(),
             version: modrinth_version.version_number.clone(),
             file_name: first_file.filename.clone(),
             release_date: modrinth_version.date_published.clone(),
             sha512: first_file.hashes.sha512.clone(),
+            sha1: Some(first_file.hashes.sha1.clone()),
             download_url: first_file.url.clone(),
+            file_id: None,
             dependencies: dependencies?,
             size: first_file.size,
+            disabled: false,
+            project_type,
+            loaders: modrinth_version
+                .loaders
+                .iter()
+                .filter_map(|loader| loader.parse().ok())
+                .collect(),
+            game_versions: modrinth_version.game_versions.clone(),
+            client_side: project.client_side.parse().unwrap_or_default(),
+            server_side: project.server_side.parse().unwrap_or_default(),
         };
 
         Ok(lock_mod)
@@ -122,6 +236,53 @@ fn convert_modrinth_to_lockmod(
     }
 }
 
+// Looks up many files at once (/v2/version_files), returning the lockfile entries of the ones found, keyed by sha512.
+// The project type is left to the caller, as it depends on the folder the file is in.
+pub async fn modrinth_mods_from_hashes(
+    client: &Client,
+    hashes: &[String],
+) -> Result<HashMap<String, LockMod>, Box<dyn std::error::Error + Send + Sync>> {
+    let response = client
+        .post(format!("{}/v2/version_files", MODRINTH_API_BASE))
+        .json(&serde_json::json!({ "hashes": hashes, "algorithm": "sha512" }))
+        .send()
+        .await?;
+    let versions = match response.status() {
+        StatusCode::OK => response.json::<HashMap<String, ModrinthVersion>>().await?,
+        status => return Err(format!("Received unexpected status code: {}", status).into()),
+    };
+    if versions.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    // Fetch the titles of every project in one request as well.
+    let mut project_ids: Vec<String> = versions.values().map(|v| v.project_id.clone()).collect();
+    project_ids.sort();
+    project_ids.dedup();
+    let response = client
+        .get(format!("{}/v2/projects", MODRINTH_API_BASE))
+        .query(&[("ids", serde_json::to_string(&project_ids)?)])
+        .send()
+        .await?;
+    let projects = match response.status() {
+        StatusCode::OK => response.json::<Vec<ModrinthProject>>().await?,
+        status => return Err(format!("Received unexpected status code: {}", status).into()),
+    };
+
+    let mut lock_mods: HashMap<String, LockMod> = HashMap::new();
+    for (hash, mut version) in versions {
+        let project = match projects.iter().find(|p| p.id == version.project_id) {
+            Some(result) => result,
+            None => continue,
+        };
+        // Versions can have more than one file, so put the one that was looked up first.
+        version.files.sort_by_key(|file| file.hashes.sha512 != hash);
+        let lock_mod = convert_modrinth_to_lockmod(&version, project, project.project_type(None))?;
+        lock_mods.insert(hash, lock_mod);
+    }
+    Ok(lock_mods)
+}
+
 pub async fn modrinth_mod_from_hash(
     client: &Client,
     hash: &str,
@@ -142,19 +303,15 @@ pub async fn modrinth_mod_from_hash(
     match response.status() {
         StatusCode::OK => {
             // The request was successful, deserialize the JSON
-            let modrinth_mod = response.json::<ModrinthVersion>().await?;
-            let title = client
-                .get(format!(
-                    "{}/v2/project/{}",
-                    MODRINTH_API_BASE, modrinth_mod.project_id
-                ))
-                .send()
-                .await?
-                .json::<ModrinthProject>()
-                .await?
-                .title;
+            let mut modrinth_mod = response.json::<ModrinthVersion>().await?;
+            // Versions can have more than one file, so put the one that was looked up first.
+            modrinth_mod
+                .files
+                .sort_by_key(|file| file.hashes.sha512 != hash);
+            let project = fetch_modrinth_project(client, &modrinth_mod.project_id).await?;
             // TODO: Add version and loader verification here!
-            convert_modrinth_to_lockmod(&modrinth_mod, title)
+            let project_type = project.project_type(None);
+            convert_modrinth_to_lockmod(&modrinth_mod, &project, project_type)
         }
         StatusCode::NOT_FOUND => {
             // The resource was not found (404)
@@ -6,14 +6,13 @@ use crate::{
     actionheader, alert,
     api::modrinth::fetch_modrinth_mod,
     commands::command_structs::CommandOptions,
-    config::{read_lockfile, save_config, save_lockfile},
+    config::{read_config_with_alerts, read_lockfile},
     confirm,
-    datatypes::{LockMod, Mod, ModSources},
+    datatypes::{Config, GameLoader, LockMod, Mod, ModSources, ProjectType},
     errors::ModManError,
-    info,
-    install::download_all_mods,
-    request,
-    utils::convert_lock_mods_to_tuples,
+    info, request,
+    transactions::{check_incomplete_transactions, Transaction},
+    utils::convert_lock_mods_to_downloads,
     APP_USER_AGENT,
 };
 use colored::Colorize;
@@ -29,11 +28,17 @@ pub async fn command_add(options: &CommandOptions) -> Result<(), ModManError> {
         <package_slug / package_ID>             - The name of the package being installed.
 
         ModMan prioritizes modrinth over curseforge. Therefore, if source is left blank (text before @), then modrinth is used.
+        CurseForge mods can't be added yet. They are reported (along with local mods), and the other mods are still added.
 
         This argument can be repeated as much times as possible to install multiple mods at a time.
 
+        The flags are as follows for 'add' command:
+        --ignore-dependencies       - Do not install the dependencies of the mods.
+        --type <project type>       - Install the packages as this project type (mod, resourcepack, shader or datapack),
+                                      instead of detecting it. Each project type is installed to its own folder.
+
         Step-by-Step Workflow:
-            0. READ TRANSACTIONS: If there was a failed transaction (or more), error out and request user to go to `modman transactions`
+            0. READ TRANSACTIONS: If there was a failed transaction (or more), error out and request user to go to `modman transactions`.
             1. READ: Read config and lockfile.
             2. FETCH: Fetch requested mods.
             3. RESOLVE: Resolve dependencies.
@@ -42,11 +47,11 @@ pub async fn command_add(options: &CommandOptions) -> Result<(), ModManError> {
                 Maybe: Devise a seperate command which will be called, like `modman sync` which deletes mods that do not exist at mod folder, and add mods
                 that are not in the lockfile but exist in the mods folder.
             5. COMPARE: Compare list of mods to install with lockfile to determine reinstalling already installed mods (the lockmod).
-            6. TRANSACTION: If there are mods to install, request user confirmation, then install mods.
-                TODO: BETTER TRANSACTION SYSTEM THAT LOGS THE TRANSACTION STEPS FOR FAILURE REDUNDANCY.
-                Maybe: Add a list of successful installs `if success successful.push(mods_to_install.pop())` to use in step 7
-                TODO ABOVE
+            6. TRANSACTION: If there are mods to install, request user confirmation, then journal the transaction steps (downloads,
+                lockfile and config writes) in .modman/transactions before running them. If the transaction is interrupted or fails,
+                it can be resumed or rolled back with `modman transactions`.
             7. SYNC (config and lockfile): Add (non-duplicate) explicit mod dependencies to config. Add (non-duplicate) indirect mod dependencies to lockfile.
+                This is the last step of the transaction.
     */
 
     // Parse parameters
@@ -60,11 +65,22 @@ pub async fn command_add(options: &CommandOptions) -> Result<(), ModManError> {
         info!("'--ignore-dependencies' tag detected. Ignoring dependencies...");
     }
 
+    let project_type: Option<ProjectType> = match options.flag_value("--type") {
+        Some(value) => match value.parse::<ProjectType>() {
+            Ok(result) => Some(result),
+            Err(e) => return Err(ModManError::InvalidCommandArguments(e)),
+        },
+        None => None,
+    };
+
     let current_directory = match crate::utils::get_current_working_dir() {
         Ok(result) => result,
         Err(e) => return Err(ModManError::IoError(e)),
     };
 
+    // (0) Read transactions
+    check_incomplete_transactions(&current_directory)?;
+
     // Client Creation
     let client = Arc::new(match Client::builder().user_agent(APP_USER_AGENT).build() {
         Ok(result) => result,
@@ -73,7 +89,9 @@ pub async fn command_add(options: &CommandOptions) -> Result<(), ModManError> {
 
     // Sync files
     info!("Syncing files...");
-    let sync_results = sync_files(&current_directory, &client).await?;
+    let sync_results = sync_files(&current_directory, &client, false).await?;
+    sync_results.print_renamed();
+    sync_results.print_toggled();
     if !sync_results.missing_dependencies.is_empty() {
         info!("There are missing mod dependencies. They will be added to the transaction.")
     }
@@ -86,25 +104,7 @@ pub async fn command_add(options: &CommandOptions) -> Result<(), ModManError> {
 
     // (1) Read config and lockfile
     // Load config
-    let mut config = match crate::config::read_config(&current_directory) {
-        Ok(result) => result,
-        Err(ModManError::FileNotFound) => {
-            alert!("No config file (modman.toml) found for this directory!");
-            alert!("Please run 'modman init' to generate a config file.");
-            return Err(ModManError::FileNotFound);
-        }
-        Err(ModManError::FileIsEmpty) => {
-            alert!("Config file (modman.toml) is empty!");
-            alert!("Please run 'modman init' to generate a config file.");
-            return Err(ModManError::FileIsEmpty);
-        }
-        Err(ModManError::DeserializationError(e)) => {
-            alert!("Either config file modman.toml has incorrect information, or is corrupt. Please modify modman.toml, or");
-            alert!("delete it to reset the configuration.");
-            return Err(ModManError::DeserializationError(e));
-        }
-        Err(e) => return Err(e),
-    };
+    let mut config = read_config_with_alerts(&current_directory)?;
 
     // Load lockfile
     let mut current_lockfile: Vec<LockMod> = match read_lockfile(&current_directory) {
@@ -137,18 +137,22 @@ pub async fn command_add(options: &CommandOptions) -> Result<(), ModManError> {
             if source.is_empty() {
                 return Err(ModManError::InvalidCommandArguments(source.to_string()));
             }
-            packages.push(match Package::new(search_term.to_string(), Some(source)) {
-                Ok(result) => result,
-                Err(_) => return Err(ModManError::InvalidCommandArguments(arg.to_string())),
-            });
+            packages.push(
+                match Package::new(search_term.to_string(), Some(source), project_type.clone()) {
+                    Ok(result) => result,
+                    Err(_) => return Err(ModManError::InvalidCommandArguments(arg.to_string())),
+                },
+            );
         } else {
             if arg.is_empty() {
                 return Err(ModManError::InvalidCommandArguments(arg.to_string()));
             }
-            packages.push(match Package::new(arg.to_string(), None) {
-                Ok(result) => result,
-                Err(_) => return Err(ModManError::InvalidCommandArguments(arg.to_string())),
-            });
+            packages.push(
+                match Package::new(arg.to_string(), None, project_type.clone()) {
+                    Ok(result) => result,
+                    Err(_) => return Err(ModManError::InvalidCommandArguments(arg.to_string())),
+                },
+            );
         }
     }
 
@@ -156,6 +160,7 @@ pub async fn command_add(options: &CommandOptions) -> Result<(), ModManError> {
         packages.push(Package {
             search_term: dependency.project_id,
             source: dependency.source,
+            project_type: None,
         })
     }
 
@@ -163,36 +168,32 @@ pub async fn command_add(options: &CommandOptions) -> Result<(), ModManError> {
         packages.push(Package {
             search_term: new_mod.id,
             source: new_mod.source,
+            project_type: Some(new_mod.project_type),
         })
     }
 
+    // Mods with bad checksums are still in the lockfile, but must be fetched and installed again.
+    let reinstall_ids: HashSet<String> = sync_results
+        .to_reinstall_bad_checksum
+        .iter()
+        .map(|lock_mod| lock_mod.id.clone())
+        .collect();
     for mod_to_reinstall in sync_results.to_reinstall_bad_checksum {
         packages.push(Package {
             search_term: mod_to_reinstall.id,
             source: mod_to_reinstall.source,
+            project_type: Some(mod_to_reinstall.project_type),
         })
     }
 
     for package in packages {
         let client = Arc::clone(&client);
-        let mod_match: tokio::task::JoinHandle<Result<LockMod, ModManError>> = match package.source
-        {
-            ModSources::Modrinth => {
-                let search_term = package.search_term.clone();
-                let game_version = config.game_version.clone();
-                let game_loader = config.game_loader.clone();
-                tokio::spawn(async move {
-                    match fetch_modrinth_mod(&client, &search_term, &game_version, &game_loader)
-                        .await
-                    {
-                        Ok(result) => Ok(result),
-                        Err(err) => Err(ModManError::CannotFindMod(format!("{}", err))),
-                    }
-                })
-            }
-            ModSources::CurseForge => unimplemented!(),
-            ModSources::Local => unimplemented!(),
-        };
+        let game_version = config.game_version.clone();
+        let game_loader = config.game_loader.clone();
+        let mod_match: tokio::task::JoinHandle<Result<LockMod, ModManError>> =
+            tokio::spawn(async move {
+                fetch_package(&client, package, &game_version, &game_loader).await
+            });
         mod_matches.push(mod_match);
     }
 
@@ -218,9 +219,10 @@ pub async fn command_add(options: &CommandOptions) -> Result<(), ModManError> {
                 confirm!(message);
                 // Check if mod is not already installed and its dependencies are not already installed
                 if !already_installed_mods.contains(&mod_result.id)
-                    && !current_lockfile
-                        .iter()
-                        .any(|lock_mod| lock_mod.id == mod_result.id)
+                    && (reinstall_ids.contains(&mod_result.id)
+                        || !current_lockfile
+                            .iter()
+                            .any(|lock_mod| lock_mod.id == mod_result.id))
                 {
                     mods_to_install.push(mod_result.clone());
                     explicit_mods.push(mod_result.clone());
@@ -282,32 +284,148 @@ pub async fn command_add(options: &CommandOptions) -> Result<(), ModManError> {
     println!();
     actionheader!("Transaction");
 
-    let tuples = convert_lock_mods_to_tuples(&config, mods_to_install.clone());
-    match download_all_mods(&client, tuples).await {
-        Ok(_) => {}
-        Err(e) => return Err(ModManError::TransactionDownloadError(e)),
+    // Mods that are disabled stay disabled when a new version replaces them.
+    for lock_mod in mods_to_install.iter_mut() {
+        lock_mod.disabled = current_lockfile
+            .iter()
+            .any(|installed_mod| installed_mod.id == lock_mod.id && installed_mod.disabled);
+    }
+
+    // Downloads replace the files of older versions of the same mods, so work them out before updating the lockfile.
+    // Mods for the other side (see 'side' in modman.toml) are only added to the lockfile.
+    let (to_download, other_side): (Vec<LockMod>, Vec<LockMod>) = mods_to_install
+        .iter()
+        .cloned()
+        .partition(|lock_mod| config.wants(lock_mod));
+    for lock_mod in &other_side {
+        info!(
+            format!("Not installed on the {} side:", config.side),
+            lock_mod.name.clone()
+        );
+    }
+    let downloads =
+        convert_lock_mods_to_downloads(&current_directory, &config, to_download, &current_lockfile);
+    let lockfile_before = current_lockfile.clone();
+    let config_mods_before = config.mods.clone();
+
+    apply_installed_mods(
+        &mut current_lockfile,
+        &mut config,
+        &mods_to_install,
+        &explicit_mods,
+    );
+
+    // (6) Journal every step of the transaction before acting on it.
+    let mut transaction = Transaction::begin(&current_directory, "add")?;
+    for download in downloads {
+        transaction.plan_download(&current_directory, download)?;
+    }
+    transaction.plan_write_lockfile(&current_directory, &current_lockfile)?;
+    transaction.plan_write_config(&current_directory, &config)?;
+
+    let report = match transaction.download(&current_directory, &client).await {
+        Ok(result) => result,
+        Err(e) => {
+            alert!("Transaction failed. Run 'modman transactions' to resume or roll it back.");
+            return Err(e);
+        }
     };
+
+    // (7) Only mods that were actually installed and verified go into the lockfile and config.
+    let failed = report.failed();
+    if !failed.is_empty() {
+        let installed_mods: Vec<LockMod> = mods_to_install
+            .into_iter()
+            .filter(|lock_mod| {
+                !failed.iter().any(|outcome| {
+                    outcome.task.dest == config.installed_path(&current_directory, lock_mod)
+                })
+            })
+            .collect();
+
+        current_lockfile = lockfile_before;
+        config.mods = config_mods_before;
+        apply_installed_mods(
+            &mut current_lockfile,
+            &mut config,
+            &installed_mods,
+            &explicit_mods,
+        );
+
+        transaction.drop_failed_downloads(&current_directory)?;
+        transaction.plan_write_lockfile(&current_directory, &current_lockfile)?;
+        transaction.plan_write_config(&current_directory, &config)?;
+    }
+
+    if let Err(e) = transaction.finish(&current_directory) {
+        alert!("Transaction failed. Run 'modman transactions' to resume or roll it back.");
+        return Err(e);
+    }
+
+    if !failed.is_empty() {
+        report.print_failures();
+        alert!("Transaction finished, but some mods failed to download. They were not added to the config and lockfile.");
+        return Err(ModManError::DownloadsFailed(failed.len()));
+    }
+
     confirm!("Transaction finished. All fetched mods have been downloaded.");
-    info!("Writing to config and lockfile...");
+    confirm!("Lockfile saved successfully.");
+    confirm!("Config file saved successfully.");
 
-    current_lockfile.append(&mut mods_to_install.clone());
-    match save_lockfile(&current_directory, &current_lockfile) {
-        Ok(_) => confirm!("Lockfile saved successfully."),
-        Err(e) => return Err(e),
-    };
+    Ok(())
+}
+
+// Fetches the version of a package to install for the game version and loader.
+pub async fn fetch_package(
+    client: &Client,
+    package: Package,
+    game_version: &String,
+    game_loader: &GameLoader,
+) -> Result<LockMod, ModManError> {
+    match package.source {
+        ModSources::Modrinth => fetch_modrinth_mod(
+            client,
+            &package.search_term,
+            game_version,
+            game_loader,
+            package.project_type,
+        )
+        .await
+        .map_err(|err| ModManError::CannotFindMod(format!("{}", err))),
+        // TODO: Fetch CurseForge mods by project id. Mods from CurseForge modpacks and packwiz packs end up here
+        // when their files are missing or modified.
+        ModSources::CurseForge => Err(ModManError::CurseForgeNotSupported(package.search_term)),
+        ModSources::Local => Err(ModManError::InvalidCommandArguments(format!(
+            "'{}' is a local mod. Copy it into its folder, then run 'modman sync'.",
+            package.search_term
+        ))),
+    }
+}
+
+// Adds installed mods to the lockfile (replacing older versions of the same mods), and the explicit ones among them to the config.
+fn apply_installed_mods(
+    lockfile: &mut Vec<LockMod>,
+    config: &mut Config,
+    installed_mods: &[LockMod],
+    explicit_mods: &[LockMod],
+) {
+    lockfile.retain(|lock_mod| !installed_mods.iter().any(|m| m.id == lock_mod.id));
+    lockfile.extend(installed_mods.iter().cloned());
 
     for mod_match in explicit_mods {
+        if !installed_mods.iter().any(|m| m.id == mod_match.id)
+            || config.mods.iter().any(|m| m.id == mod_match.id)
+        {
+            continue;
+        }
         let mod_input: Mod = Mod {
-            source: mod_match.source,
-            id: mod_match.id,
-            name: mod_match.name,
+            source: mod_match.source.clone(),
+            id: mod_match.id.clone(),
+            name: mod_match.name.clone(),
+            enabled: !mod_match.disabled,
+            project_type: mod_match.project_type.clone(),
+            side: None,
         };
         config.mods.push(mod_input);
     }
-    match save_config(&current_directory, &config) {
-        Ok(_) => confirm!("Config file saved successfully."),
-        Err(e) => return Err(e),
-    }
-
-    Ok(())
 }
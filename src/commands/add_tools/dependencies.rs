@@ -23,7 +23,8 @@ pub async fn handle_dependencies(
                     let loader = loader.clone();
 
                     tasks.push(tokio::spawn(async move {
-                        fetch_modrinth_mod(&client, &dep_id, &minecraft_version, &loader).await
+                        fetch_modrinth_mod(&client, &dep_id, &minecraft_version, &loader, None)
+                            .await
                     }));
                 }
                 DependencyType::Optional => {
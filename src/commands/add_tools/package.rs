@@ -1,14 +1,19 @@
-use crate::datatypes::ModSources;
+use crate::datatypes::{ModSources, ProjectType};
 
 #[derive(Debug)]
 pub struct Package {
     pub search_term: String,
     pub source: ModSources,
+    pub project_type: Option<ProjectType>, // Detected from the source if not set.
 }
 
 impl Package {
     // A constructor to create a new Package instance.
-    pub fn new(search_term: String, source: Option<&str>) -> Result<Self, String> {
+    pub fn new(
+        search_term: String,
+        source: Option<&str>,
+        project_type: Option<ProjectType>,
+    ) -> Result<Self, String> {
         let source: ModSources = match source {
             Some(s) => s.parse::<crate::datatypes::ModSources>()?,
             None => ModSources::Modrinth, // Default to Modrinth if no source is provided
@@ -16,6 +21,7 @@ impl Package {
         Ok(Package {
             search_term,
             source,
+            project_type,
         })
     }
 }
@@ -0,0 +1,156 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+};
+
+use colored::Colorize;
+
+use crate::{
+    actionheader, alert,
+    commands::command_structs::CommandOptions,
+    config::{read_config_with_alerts, read_lockfile},
+    confirm,
+    conflicts::{find_conflicts, Conflict},
+    datatypes::{LockMod, ProjectType},
+    errors::ModManError,
+    info, request,
+    transactions::{check_incomplete_transactions, Transaction},
+};
+
+pub fn command_check(options: &CommandOptions) -> Result<(), ModManError> {
+    /*
+        The flags are as follows for 'check' command:
+        --fix   - Resolve each conflict interactively, by choosing which jars to remove.
+
+        Checks the mods folder for duplicate projects, duplicate internal mod ids, and jars made for another loader.
+        Any of these will stop the game from launching. Fails if there are conflicts left.
+    */
+
+    let fix = options.flags.contains(&"--fix".to_string());
+
+    let current_directory = match crate::utils::get_current_working_dir() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::IoError(e)),
+    };
+
+    if fix {
+        check_incomplete_transactions(&current_directory)?;
+    }
+
+    let mut config = read_config_with_alerts(&current_directory)?;
+    let mut lockfile: Vec<LockMod> = match read_lockfile(&current_directory) {
+        Ok(result) => result,
+        Err(ModManError::FileNotFound) => Vec::new(),
+        Err(ModManError::FileIsEmpty) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+
+    let conflicts = find_conflicts(&current_directory, &config, &lockfile)?;
+    if conflicts.is_empty() {
+        confirm!("No conflicts found.");
+        return Ok(());
+    }
+
+    actionheader!("Conflicts");
+    for conflict in &conflicts {
+        alert!(conflict.to_string());
+    }
+
+    if !fix {
+        info!("Run 'modman check --fix' to resolve them.");
+        return Err(ModManError::ConflictsFound(conflicts.len()));
+    }
+
+    // Ask how to resolve each conflict. Files are only removed once every conflict has been gone through.
+    println!();
+    actionheader!("Resolve Conflicts");
+    let mut to_remove: BTreeSet<String> = BTreeSet::new();
+    for conflict in &conflicts {
+        let file_names: Vec<String> = conflict
+            .file_names()
+            .into_iter()
+            .filter(|file_name| !to_remove.contains(file_name))
+            .collect();
+        if file_names.is_empty() {
+            continue;
+        }
+        info!(conflict.to_string());
+        match conflict {
+            Conflict::LoaderMismatch { file_name, .. } => {
+                request!(format!("Remove '{}'?", file_name), "[y/N]");
+                let input = read_input().to_lowercase();
+                if input == "y" || input == "yes" {
+                    to_remove.insert(file_name.clone());
+                }
+            }
+            _ => {
+                if file_names.len() < 2 {
+                    continue;
+                }
+                for (index, file_name) in file_names.iter().enumerate() {
+                    println!("    {}. {}", index + 1, file_name);
+                }
+                request!(
+                    "Keep which file?",
+                    format!("[1-{}, or leave empty to skip]", file_names.len())
+                );
+                let keep = match read_input().parse::<usize>() {
+                    Ok(result) if (1..=file_names.len()).contains(&result) => result - 1,
+                    _ => continue,
+                };
+                for (index, file_name) in file_names.into_iter().enumerate() {
+                    if index != keep {
+                        to_remove.insert(file_name);
+                    }
+                }
+            }
+        }
+    }
+
+    if to_remove.is_empty() {
+        info!("Nothing to remove.");
+        return Err(ModManError::ConflictsFound(conflicts.len()));
+    }
+
+    // Remove the files, along with their lockfile entries and any config entries left without a file.
+    let removed_ids: Vec<String> = lockfile
+        .iter()
+        .filter(|m| m.project_type == ProjectType::Mod && to_remove.contains(&m.file_name))
+        .map(|m| m.id.clone())
+        .collect();
+    lockfile.retain(|m| !(m.project_type == ProjectType::Mod && to_remove.contains(&m.file_name)));
+    config
+        .mods
+        .retain(|m| !removed_ids.contains(&m.id) || lockfile.iter().any(|l| l.id == m.id));
+
+    println!();
+    actionheader!("Transaction");
+    let mods_path = config.project_path(&current_directory, &ProjectType::Mod);
+    let mut transaction = Transaction::begin(&current_directory, "check")?;
+    for file_name in &to_remove {
+        transaction.plan_delete(&current_directory, mods_path.join(file_name))?;
+    }
+    transaction.plan_write_lockfile(&current_directory, &lockfile)?;
+    transaction.plan_write_config(&current_directory, &config)?;
+    if let Err(e) = transaction.finish(&current_directory) {
+        alert!("Transaction failed. Run 'modman transactions' to resume or roll it back.");
+        return Err(e);
+    }
+    for file_name in &to_remove {
+        info!("Removed:", file_name.clone());
+    }
+
+    let remaining = find_conflicts(&current_directory, &config, &lockfile)?;
+    if !remaining.is_empty() {
+        alert!(format!("{} conflict(s) are left.", remaining.len()));
+        return Err(ModManError::ConflictsFound(remaining.len()));
+    }
+    confirm!("All conflicts resolved.");
+    Ok(())
+}
+
+fn read_input() -> String {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    input.trim().to_string()
+}
@@ -3,12 +3,44 @@ use std::env::Args;
 use colored::Colorize;
 
 use crate::{
-    commands::{add, command_structs, init, version},
+    commands::{
+        add, check, command_structs, export, graph, import, init, install, toggle, transactions,
+        upgrade, verify, version, why,
+    },
+    config,
     errors::ModManError,
 };
 
 use super::sync;
 
+// Flags which take a value. These can be passed as either '--flag=value' or '--flag value'.
+const VALUE_FLAGS: &[&str] = &[
+    "--format",
+    "--type",
+    "--game-version",
+    "--loader",
+    "--release-types",
+    "--mods-folder",
+    "--from-folder",
+    "--profile",
+    "--output",
+    "--overrides",
+    "--loader-version",
+    "--instance",
+];
+
+// The profile given with '--profile', or else in MODMAN_PROFILE (`env_profile`). Empty names select no profile.
+fn selected_profile(
+    options: &command_structs::CommandOptions,
+    env_profile: Option<String>,
+) -> Option<String> {
+    let profile = match options.flag_value("--profile") {
+        Some(profile) => Some(profile.to_string()),
+        None => env_profile,
+    };
+    profile.filter(|profile| !profile.is_empty())
+}
+
 pub async fn handle_command(mut args: Args) -> Result<(), ModManError> {
     args.next(); // Skip first args, which is the program binary.
 
@@ -28,28 +60,49 @@ pub async fn handle_command(mut args: Args) -> Result<(), ModManError> {
     }
 
     // Parse the remaining arguments
-    for arg in args {
-        if arg.starts_with("--") {
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        if VALUE_FLAGS.contains(&arg.as_str())
+            && args.peek().is_some_and(|next| !next.starts_with("--"))
+        {
+            let value = args.next().unwrap_or_default();
+            command_options.flags.push(format!("{}={}", arg, value));
+        } else if arg.starts_with("--") {
             command_options.flags.push(arg);
         } else {
             command_options.parameters.push(arg);
         }
     }
 
+    // The profile is used by every command, for the whole run.
+    if let Some(profile) = selected_profile(&command_options, std::env::var("MODMAN_PROFILE").ok())
+    {
+        config::set_active_profile(&profile);
+    }
+
     let command_result: Result<(), ModManError> =
         match command_options.command.to_lowercase().as_str() {
             "help" => command_help(),
             "version" => version::command_version(),
-            "install" => todo!(),
+            "install" => install::command_install().await,
             "add" => add::command_add(&command_options).await,
-            "init" => init::command_init(),
+            "init" => init::command_init(&command_options).await,
             "sync" => sync::command_sync(&command_options).await,
             "remove" => todo!(),
             "search" => todo!(),
             "update" => todo!(),
-            "upgrade" => todo!(),
+            "upgrade" => upgrade::command_upgrade(&command_options).await,
             "list" => todo!(),
             "info" => todo!(),
+            "disable" => toggle::command_disable(&command_options),
+            "enable" => toggle::command_enable(&command_options),
+            "check" => check::command_check(&command_options),
+            "verify" => verify::command_verify(),
+            "why" => why::command_why(&command_options),
+            "graph" => graph::command_graph(&command_options),
+            "export" => export::command_export(&command_options).await,
+            "import" => import::command_import(&command_options).await,
+            "transactions" => transactions::command_transactions(&command_options).await,
             _ => {
                 println!("Unknown command '{}'.", command_options.command);
                 command_help()?;
@@ -69,3 +122,59 @@ fn command_help() -> Result<(), ModManError> {
     println!("HELP...");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::profile_lockfile_name, datatypes::Config};
+
+    fn options(flags: &[&str]) -> command_structs::CommandOptions {
+        command_structs::CommandOptions {
+            command: "sync".to_string(),
+            flags: flags.iter().map(|flag| flag.to_string()).collect(),
+            parameters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn profile_flag_takes_priority_over_env_var() {
+        let selected =
+            selected_profile(&options(&["--profile=server"]), Some("client".to_string()));
+        assert_eq!(selected.as_deref(), Some("server"));
+        let selected = selected_profile(&options(&[]), Some("client".to_string()));
+        assert_eq!(selected.as_deref(), Some("client"));
+        assert_eq!(selected_profile(&options(&[]), None), None);
+        assert_eq!(selected_profile(&options(&["--profile="]), None), None);
+    }
+
+    #[test]
+    fn selected_profile_picks_its_lockfile() {
+        let file: Config = toml::from_str(
+            r#"
+game_version = "1.20.1"
+game_loader = "Fabric"
+allowed_release_types = ["Release"]
+mods_folder = "mods"
+mods = []
+
+[profiles.server]
+
+[profiles.client]
+lockfile = "client.lock"
+"#,
+        )
+        .unwrap();
+
+        let from_flag =
+            selected_profile(&options(&["--profile=server"]), Some("client".to_string()));
+        assert_eq!(
+            profile_lockfile_name(&file, &from_flag.unwrap()).unwrap(),
+            "modman.server.lock"
+        );
+        let from_env = selected_profile(&options(&[]), Some("client".to_string()));
+        assert_eq!(
+            profile_lockfile_name(&file, &from_env.unwrap()).unwrap(),
+            "client.lock"
+        );
+    }
+}
@@ -3,3 +3,12 @@ pub struct CommandOptions {
     pub flags: Vec<String>,
     pub parameters: Vec<String>,
 }
+
+impl CommandOptions {
+    // Returns the value of a flag passed as '--flag=value' (or '--flag value' for flags listed in VALUE_FLAGS).
+    pub fn flag_value(&self, flag: &str) -> Option<&str> {
+        self.flags
+            .iter()
+            .find_map(|f| f.strip_prefix(flag)?.strip_prefix('='))
+    }
+}
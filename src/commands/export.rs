@@ -0,0 +1,718 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use colored::Colorize;
+use reqwest::Client;
+use zip::ZipWriter;
+
+use crate::{
+    actionheader, alert,
+    api::curseforge::{
+        curseforge_api_key, curseforge_files_from_fingerprints, curseforge_fingerprint,
+    },
+    commands::command_structs::CommandOptions,
+    config::{read_config_with_alerts, read_lockfile},
+    confirm,
+    datatypes::{Config, GameLoader, LockMod, ModSources},
+    errors::ModManError,
+    info,
+    install::{calculate_sha1, verify_file_hashes},
+    modpack::{
+        curseforge::{
+            self, curseforge_ids, is_supported_loader, CurseForgeManifest, ManifestFile,
+            ManifestMinecraft, ManifestModLoader, MANIFEST_FILE_NAME,
+        },
+        finish_zip,
+        mrpack::{
+            self, game_folder, is_allowed_download, loader_dependency, mrpack_file, MrpackFile,
+            MrpackIndex, INDEX_FILE_NAME,
+        },
+        packwiz::{
+            self, metafile, metafile_name, PackwizIndex, PackwizIndexFile, PackwizIndexFileRef,
+            PackwizMod, PackwizPack,
+        },
+        zip_bytes, zip_file, zip_folder,
+    },
+    transactions::check_incomplete_transactions,
+    APP_USER_AGENT,
+};
+
+pub async fn command_export(options: &CommandOptions) -> Result<(), ModManError> {
+    /*
+        The arguments are as follows for 'export' command:
+        <format>                        - The modpack format: 'mrpack' (Modrinth), 'curseforge' or 'packwiz'.
+
+        Flags:
+        --output <file>                 - Where to write the modpack. Default is '<name>-<version>.mrpack' (or '.zip'),
+                                            or a '<name>-<version>' folder for packwiz.
+        --force                         - Write a packwiz pack into a folder which is not empty.
+        --overrides <folder>            - Folder bundled as overrides. Default is 'overrides_folder' in [modpack].
+        --loader-version <version>      - Version of the loader. Default is 'loader_version' in modman.toml.
+    */
+
+    let format = match options.parameters.first() {
+        Some(result) => result.to_lowercase(),
+        None => return Err(ModManError::NoArguments),
+    };
+    match format.as_str() {
+        "mrpack" => export_mrpack(options),
+        "curseforge" => export_curseforge(options).await,
+        "packwiz" => export_packwiz(options),
+        _ => Err(ModManError::InvalidCommandArguments(format!(
+            "Unknown modpack format '{}'. Supported formats: mrpack, curseforge, packwiz",
+            format
+        ))),
+    }
+}
+
+// The project, and where its modpack goes.
+struct Export {
+    dir: PathBuf,
+    config: Config,
+    lockfile: Vec<LockMod>,
+    name: String,
+    overrides_folder: PathBuf,
+    output_path: PathBuf,
+}
+
+// Modpacks are written to '<name>-<version>.<extension>', or a '<name>-<version>' folder if there is no extension.
+fn prepare_export(
+    options: &CommandOptions,
+    extension: Option<&str>,
+) -> Result<Export, ModManError> {
+    let current_directory = match crate::utils::get_current_working_dir() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::IoError(e)),
+    };
+
+    check_incomplete_transactions(&current_directory)?;
+
+    let config = read_config_with_alerts(&current_directory)?;
+    let lockfile: Vec<LockMod> = match read_lockfile(&current_directory) {
+        Ok(result) => result,
+        Err(ModManError::FileNotFound) | Err(ModManError::FileIsEmpty) => {
+            alert!("The lockfile (modman.lock) is missing or empty. Run 'modman add' or 'modman sync' to create it.");
+            return Err(ModManError::NoMods("export".to_owned()));
+        }
+        Err(e) => return Err(e),
+    };
+
+    let name = match &config.modpack.name {
+        Some(result) => result.clone(),
+        None => current_directory
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "modpack".to_string()),
+    };
+    let overrides_folder = match options.flag_value("--overrides") {
+        Some(result) => current_directory.join(result),
+        None => current_directory.join(&config.modpack.overrides_folder),
+    };
+    // The default overrides folder is optional, but one that was passed has to exist.
+    if options.flag_value("--overrides").is_some() && !overrides_folder.is_dir() {
+        return Err(ModManError::InvalidCommandArguments(format!(
+            "'{}' is not a folder.",
+            overrides_folder.display()
+        )));
+    }
+    let output_path = match options.flag_value("--output") {
+        Some(result) => current_directory.join(result),
+        None => {
+            let output_name = format!("{}-{}", name, config.modpack.version);
+            match extension {
+                Some(extension) => current_directory.join(format!("{}.{}", output_name, extension)),
+                None => current_directory.join(output_name),
+            }
+        }
+    };
+
+    Ok(Export {
+        dir: current_directory,
+        config,
+        lockfile,
+        name,
+        overrides_folder,
+        output_path,
+    })
+}
+
+fn export_mrpack(options: &CommandOptions) -> Result<(), ModManError> {
+    /*
+        Exports the lockfile (modman.lock) as a Modrinth modpack (.mrpack), ready to upload.
+
+        Step-by-Step Workflow:
+            1. READ: Read config and lockfile.
+            2. FILES: Every lockfile entry downloaded from Modrinth (or another allowed host) is listed in modrinth.index.json,
+                with its hashes, size and the sides it is for.
+                Files that can't be downloaded from an allowed host (local mods, CurseForge, etc.) are bundled as overrides instead,
+                if they are installed.
+            3. ZIP: Write modrinth.index.json, the overrides folder and the bundled files to the zip.
+
+        Disabled mods are left out. Datapacks are left out too, as they belong to a world; put them in the overrides folder instead.
+    */
+
+    // (1) Read config and lockfile.
+    let export = prepare_export(options, Some("mrpack"))?;
+    let config = &export.config;
+    let dependencies = mrpack_dependencies(config, options.flag_value("--loader-version"))?;
+
+    // (2) List the files to download, and the files to bundle.
+    actionheader!("Export");
+    let mut files: Vec<MrpackFile> = Vec::new();
+    let mut bundled: Vec<(String, &LockMod)> = Vec::new(); // path in the game directory, lockfile entry
+    let mut skipped: usize = 0;
+    for lock_mod in &export.lockfile {
+        if lock_mod.disabled {
+            info!("Skipped (disabled):", lock_mod.name.clone());
+            skipped += 1;
+            continue;
+        }
+        let Some(path) = modpack_path(lock_mod) else {
+            skipped += 1;
+            continue;
+        };
+        let installed_path = config.installed_path(&export.dir, lock_mod);
+
+        if lock_mod.source == ModSources::Local || !is_allowed_download(&lock_mod.download_url) {
+            if is_installed(&export, lock_mod)? {
+                bundled.push((path, lock_mod));
+            } else {
+                alert!(format!(
+                    "Skipped '{}': it can't be downloaded from Modrinth, and is missing or modified at '{}'.",
+                    lock_mod.name,
+                    installed_path.display()
+                ));
+                skipped += 1;
+            }
+            continue;
+        }
+
+        // Lockfiles written before sha1 was recorded don't have it, so it is taken from the installed file.
+        let sha1 = match &lock_mod.sha1 {
+            Some(result) => result.clone(),
+            None if is_installed(&export, lock_mod)? => {
+                calculate_sha1(&installed_path).map_err(ModManError::IoError)?
+            }
+            None => {
+                alert!(format!(
+                    "Skipped '{}': the lockfile has no sha1 for it, and it is not installed. Run 'modman install' first.",
+                    lock_mod.name
+                ));
+                skipped += 1;
+                continue;
+            }
+        };
+        files.push(mrpack_file(config, lock_mod, path, sha1));
+    }
+
+    let index = MrpackIndex {
+        format_version: 1,
+        game: "minecraft".to_string(),
+        version_id: config.modpack.version.clone(),
+        name: export.name.clone(),
+        summary: config.modpack.summary.clone(),
+        files,
+        dependencies,
+    };
+
+    // (3) Write the zip.
+    let index_json = serde_json::to_string_pretty(&index).map_err(ModManError::JsonError)?;
+    info!("Files to download:", index.files.len().to_string());
+    write_modpack(
+        &export,
+        INDEX_FILE_NAME,
+        &index_json,
+        mrpack::OVERRIDES_FOLDER,
+        &bundled,
+        skipped,
+    )
+}
+
+async fn export_curseforge(options: &CommandOptions) -> Result<(), ModManError> {
+    /*
+        Exports the lockfile (modman.lock) as a CurseForge modpack, which can be imported in the CurseForge app.
+
+        Step-by-Step Workflow:
+            1. READ: Read config and lockfile.
+            2. FILES: Every CurseForge lockfile entry is listed in manifest.json, by its project and file id.
+                Disabled mods are listed as not required.
+                Local mods are bundled as overrides, if they are installed.
+            3. LOOK UP: Mods from other sources (e.g. Modrinth) are looked up on CurseForge by the fingerprint of their installed file.
+                This needs a CurseForge API key, in CURSEFORGE_API_KEY.
+                If any mod has no CurseForge equivalent, they are listed, and nothing is written.
+            4. ZIP: Write manifest.json, the overrides folder and the bundled files to the zip.
+
+        Datapacks are left out, as they belong to a world; put them in the overrides folder instead.
+    */
+
+    // (1) Read config and lockfile.
+    let export = prepare_export(options, Some("zip"))?;
+    let config = &export.config;
+    let mod_loaders = match &config.game_loader {
+        GameLoader::Vanilla => Vec::new(),
+        game_loader if is_supported_loader(game_loader) => vec![ManifestModLoader {
+            id: format!(
+                "{}-{}",
+                game_loader,
+                required_loader_version(config, options.flag_value("--loader-version"))?
+            ),
+            primary: true,
+        }],
+        game_loader => {
+            return Err(ModManError::InvalidCommandArguments(format!(
+                "CurseForge modpacks don't support the '{}' loader. Supported loaders: fabric, quilt, forge, neoforge",
+                game_loader
+            )))
+        }
+    };
+
+    // (2) List the CurseForge files, and the files to bundle.
+    actionheader!("Export");
+    let mut files: Vec<ManifestFile> = Vec::new();
+    let mut bundled: Vec<(String, &LockMod)> = Vec::new(); // path in the game directory, lockfile entry
+    let mut to_look_up: Vec<&LockMod> = Vec::new();
+    let mut skipped: usize = 0;
+    for lock_mod in &export.lockfile {
+        let Some(path) = modpack_path(lock_mod) else {
+            skipped += 1;
+            continue;
+        };
+        if let Some((project_id, file_id)) = curseforge_ids(lock_mod) {
+            files.push(ManifestFile {
+                project_id,
+                file_id,
+                required: !lock_mod.disabled,
+            });
+        } else if lock_mod.disabled {
+            info!("Skipped (disabled):", lock_mod.name.clone());
+            skipped += 1;
+        } else if lock_mod.source == ModSources::Local {
+            if is_installed(&export, lock_mod)? {
+                bundled.push((path, lock_mod));
+            } else {
+                alert!(format!(
+                    "Skipped '{}': it is a local mod, and is missing or modified at '{}'.",
+                    lock_mod.name,
+                    config.installed_path(&export.dir, lock_mod).display()
+                ));
+                skipped += 1;
+            }
+        } else {
+            to_look_up.push(lock_mod);
+        }
+    }
+
+    // (3) Look up the other mods on CurseForge.
+    let mut missing: Vec<(&LockMod, &str)> = Vec::new(); // lockfile entry, reason
+    if !to_look_up.is_empty() && curseforge_api_key().is_none() {
+        missing.extend(
+            to_look_up
+                .iter()
+                .map(|m| (*m, "set CURSEFORGE_API_KEY to look it up")),
+        );
+    } else if !to_look_up.is_empty() {
+        info!(format!(
+            "Looking up {} mod(s) on CurseForge...",
+            to_look_up.len()
+        ));
+        let mut fingerprints: Vec<(u32, &LockMod)> = Vec::new();
+        for lock_mod in to_look_up {
+            if !is_installed(&export, lock_mod)? {
+                missing.push((lock_mod, "not installed, so it can't be looked up"));
+                continue;
+            }
+            let bytes = fs::read(config.installed_path(&export.dir, lock_mod))
+                .map_err(ModManError::IoError)?;
+            fingerprints.push((curseforge_fingerprint(&bytes), lock_mod));
+        }
+
+        let client = Arc::new(match Client::builder().user_agent(APP_USER_AGENT).build() {
+            Ok(result) => result,
+            Err(e) => return Err(ModManError::ReqwestError(e)),
+        });
+        let values: Vec<u32> = fingerprints.iter().map(|(f, _)| *f).collect();
+        let found = curseforge_files_from_fingerprints(&client, &values)
+            .await
+            .map_err(|e| ModManError::APIFetchError(e.to_string()))?;
+        for (fingerprint, lock_mod) in fingerprints {
+            match found.get(&fingerprint) {
+                Some(cf_file) => {
+                    info!("Found on CurseForge:", lock_mod.name.clone());
+                    files.push(ManifestFile {
+                        project_id: cf_file.mod_id,
+                        file_id: cf_file.id,
+                        required: true,
+                    });
+                }
+                None => missing.push((lock_mod, "not on CurseForge")),
+            }
+        }
+    }
+    if !missing.is_empty() {
+        for (lock_mod, reason) in &missing {
+            alert!(format!(
+                "'{}' ({}) has no CurseForge equivalent: {}.",
+                lock_mod.name, lock_mod.source, reason
+            ));
+        }
+        info!("Replace them with CurseForge mods (or remove them) to export a CurseForge modpack.");
+        return Err(ModManError::NotOnCurseForge(missing.len()));
+    }
+
+    let manifest = CurseForgeManifest {
+        minecraft: ManifestMinecraft {
+            version: config.game_version.clone(),
+            mod_loaders,
+        },
+        manifest_type: "minecraftModpack".to_string(),
+        manifest_version: 1,
+        name: export.name.clone(),
+        version: config.modpack.version.clone(),
+        author: config.modpack.author.clone().unwrap_or_default(),
+        files,
+        overrides: curseforge::OVERRIDES_FOLDER.to_string(),
+    };
+
+    // (4) Write the zip.
+    let manifest_json = serde_json::to_string_pretty(&manifest).map_err(ModManError::JsonError)?;
+    info!("Files to download:", manifest.files.len().to_string());
+    write_modpack(
+        &export,
+        MANIFEST_FILE_NAME,
+        &manifest_json,
+        curseforge::OVERRIDES_FOLDER,
+        &bundled,
+        skipped,
+    )
+}
+
+fn export_packwiz(options: &CommandOptions) -> Result<(), ModManError> {
+    /*
+        Exports the lockfile (modman.lock) as a packwiz pack: a folder with pack.toml, index.toml and a metafile per mod.
+
+        Step-by-Step Workflow:
+            1. READ: Read config and lockfile.
+            2. METAFILES: Every lockfile entry gets a metafile ('<folder>/<name>.pw.toml') with its file name, hash and download url,
+                and its project and version on Modrinth ([update.modrinth]) or CurseForge ([update.curseforge]).
+                Disabled mods are written as optional mods, which are not installed by default.
+                Local mods are copied into the pack instead, if they are installed.
+            3. FILES: Copy the overrides folder into the pack.
+            4. INDEX: Write index.toml, with the sha256 of every file in the pack, then pack.toml.
+
+        Datapacks are left out, as they belong to a world; put them in the overrides folder instead.
+    */
+
+    // (1) Read config and lockfile.
+    let force = options.flags.contains(&"--force".to_string());
+    let export = prepare_export(options, None)?;
+    let config = &export.config;
+    let mut versions = BTreeMap::new();
+    versions.insert("minecraft".to_string(), config.game_version.clone());
+    if config.game_loader != GameLoader::Vanilla {
+        if !packwiz::LOADERS.contains(&config.game_loader) {
+            return Err(ModManError::InvalidCommandArguments(format!(
+                "packwiz doesn't support the '{}' loader. Supported loaders: fabric, quilt, forge, neoforge, liteloader",
+                config.game_loader
+            )));
+        }
+        versions.insert(
+            config.game_loader.to_string(),
+            required_loader_version(config, options.flag_value("--loader-version"))?,
+        );
+    }
+
+    let output = &export.output_path;
+    if output.exists() && !output.is_dir() {
+        return Err(ModManError::InvalidCommandArguments(format!(
+            "'{}' is not a folder.",
+            display_path(&export.dir, output)
+        )));
+    }
+    let is_empty = !output.is_dir()
+        || fs::read_dir(output)
+            .map_err(ModManError::IoError)?
+            .next()
+            .is_none();
+    if !is_empty && !force {
+        return Err(ModManError::InvalidCommandArguments(format!(
+            "'{}' is not empty. Pass '--force' to write the pack into it anyway.",
+            display_path(&export.dir, output)
+        )));
+    }
+
+    // (2) Write the metafiles, and copy the local mods.
+    actionheader!("Export");
+    let mut index_files: Vec<PackwizIndexFile> = Vec::new();
+    let mut metafile_count: usize = 0;
+    let mut skipped: usize = 0;
+    for lock_mod in &export.lockfile {
+        let Some(path) = modpack_path(lock_mod) else {
+            skipped += 1;
+            continue;
+        };
+        let installed_path = config.installed_path(&export.dir, lock_mod);
+
+        if lock_mod.source == ModSources::Local {
+            if is_installed(&export, lock_mod)? {
+                info!("Copied into the pack:", lock_mod.name.clone());
+                let bytes = fs::read(&installed_path).map_err(ModManError::IoError)?;
+                write_into_pack(output, &mut index_files, &path, &bytes, false)?;
+            } else {
+                alert!(format!(
+                    "Skipped '{}': it is a local mod, and is missing or modified at '{}'.",
+                    lock_mod.name,
+                    installed_path.display()
+                ));
+                skipped += 1;
+            }
+            continue;
+        }
+
+        let Some(packwiz_mod) = packwiz_mod(&export, lock_mod)? else {
+            skipped += 1;
+            continue;
+        };
+        // Metafiles go in the folder of the file. Mods with the same name get their id in the name of their metafile.
+        let folder = path.split('/').next().unwrap_or_default();
+        let mut metafile_path = format!("{}/{}", folder, metafile_name(&lock_mod.name));
+        if index_files.iter().any(|f| f.file == metafile_path) {
+            metafile_path = format!(
+                "{}/{}",
+                folder,
+                metafile_name(&format!("{} {}", lock_mod.name, lock_mod.id))
+            );
+        }
+        let metafile =
+            toml::to_string_pretty(&packwiz_mod).map_err(ModManError::SerializationError)?;
+        write_into_pack(
+            output,
+            &mut index_files,
+            &metafile_path,
+            metafile.as_bytes(),
+            true,
+        )?;
+        metafile_count += 1;
+    }
+
+    // (3) Copy the overrides folder.
+    if export.overrides_folder.is_dir() {
+        for (path, source) in list_folder(&export.overrides_folder, "")? {
+            let bytes = fs::read(&source).map_err(ModManError::IoError)?;
+            write_into_pack(output, &mut index_files, &path, &bytes, false)?;
+        }
+    }
+
+    // (4) Write index.toml and pack.toml.
+    index_files.sort_by(|a, b| a.file.cmp(&b.file));
+    let copied_count = index_files.len() - metafile_count;
+    let index = PackwizIndex {
+        hash_format: packwiz::HASH_FORMAT.to_string(),
+        files: index_files,
+    };
+    let index_toml = toml::to_string_pretty(&index).map_err(ModManError::SerializationError)?;
+    fs::write(output.join(packwiz::INDEX_FILE_NAME), &index_toml).map_err(ModManError::IoError)?;
+    let pack = PackwizPack {
+        name: export.name.clone(),
+        author: config.modpack.author.clone(),
+        version: Some(config.modpack.version.clone()),
+        description: config.modpack.summary.clone(),
+        pack_format: packwiz::PACK_FORMAT.to_string(),
+        index: PackwizIndexFileRef {
+            file: packwiz::INDEX_FILE_NAME.to_string(),
+            hash_format: packwiz::HASH_FORMAT.to_string(),
+            hash: packwiz::sha256(index_toml.as_bytes()),
+        },
+        versions,
+    };
+    let pack_toml = toml::to_string_pretty(&pack).map_err(ModManError::SerializationError)?;
+    fs::write(output.join(packwiz::PACK_FILE_NAME), pack_toml).map_err(ModManError::IoError)?;
+
+    info!("Metafiles:", metafile_count.to_string());
+    info!("Files copied into the pack:", copied_count.to_string());
+    if skipped > 0 {
+        alert!(format!("{} file(s) were left out of the pack.", skipped));
+    }
+    confirm!(format!(
+        "Exported '{}' {} to {}",
+        export.name,
+        config.modpack.version,
+        display_path(&export.dir, output)
+    ));
+    Ok(())
+}
+
+// The metafile of a lockfile entry. Lockfiles written before sha1 was recorded don't have the sha1 that CurseForge files
+// are checked with, so it is taken from the installed file.
+fn packwiz_mod(export: &Export, lock_mod: &LockMod) -> Result<Option<PackwizMod>, ModManError> {
+    let mut lock_mod = lock_mod.clone();
+    if lock_mod.sha1.is_none()
+        && curseforge_ids(&lock_mod).is_some()
+        && is_installed(export, &lock_mod)?
+    {
+        let installed_path = export.config.installed_path(&export.dir, &lock_mod);
+        lock_mod.sha1 = Some(calculate_sha1(&installed_path).map_err(ModManError::IoError)?);
+    }
+    let result = metafile(&export.config, &lock_mod);
+    if result.is_none() {
+        alert!(format!(
+            "Skipped '{}': the lockfile has no hash for it. Run 'modman install' first.",
+            lock_mod.name
+        ));
+    }
+    Ok(result)
+}
+
+// Writes a file into the pack, and lists it in the index (replacing any file listed at the same path).
+fn write_into_pack(
+    output: &Path,
+    index_files: &mut Vec<PackwizIndexFile>,
+    path: &str,
+    bytes: &[u8],
+    metafile: bool,
+) -> Result<(), ModManError> {
+    let dest = output.join(path);
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(ModManError::IoError)?;
+    }
+    fs::write(&dest, bytes).map_err(ModManError::IoError)?;
+    index_files.retain(|f| f.file != path);
+    index_files.push(PackwizIndexFile {
+        file: path.to_string(),
+        hash: packwiz::sha256(bytes),
+        hash_format: None,
+        metafile,
+    });
+    Ok(())
+}
+
+// Every file in `folder` (and its subfolders), with its path under `prefix` ('/' separated).
+fn list_folder(folder: &Path, prefix: &str) -> Result<Vec<(String, PathBuf)>, ModManError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(folder).map_err(ModManError::IoError)? {
+        let entry = entry.map_err(ModManError::IoError)?;
+        let name = match prefix {
+            "" => entry.file_name().to_string_lossy().to_string(),
+            _ => format!("{}/{}", prefix, entry.file_name().to_string_lossy()),
+        };
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(list_folder(&path, &name)?);
+        } else if path.is_file() {
+            files.push((name, path));
+        }
+    }
+    Ok(files)
+}
+
+// Path of a lockfile entry in the game directory, e.g. 'mods/sodium.jar'.
+fn modpack_path(lock_mod: &LockMod) -> Option<String> {
+    match game_folder(&lock_mod.project_type) {
+        Some(folder) => Some(format!("{}/{}", folder, lock_mod.file_name)),
+        None => {
+            alert!(format!(
+                "Skipped '{}': datapacks belong to a world. Put it in the overrides folder to include it.",
+                lock_mod.name
+            ));
+            None
+        }
+    }
+}
+
+// Whether the file of a lockfile entry is installed, and unchanged.
+fn is_installed(export: &Export, lock_mod: &LockMod) -> Result<bool, ModManError> {
+    let installed_path = export.config.installed_path(&export.dir, lock_mod);
+    Ok(installed_path.is_file()
+        && verify_file_hashes(&installed_path, &lock_mod.sha512, lock_mod.sha1.as_deref())
+            .map_err(ModManError::IoError)?)
+}
+
+// Writes the modpack: its index or manifest, the overrides folder, and the bundled files (as overrides).
+fn write_modpack(
+    export: &Export,
+    manifest_name: &str,
+    manifest_json: &str,
+    overrides_prefix: &str,
+    bundled: &[(String, &LockMod)],
+    skipped: usize,
+) -> Result<(), ModManError> {
+    let file = File::create(&export.output_path).map_err(ModManError::IoError)?;
+    let mut zip = ZipWriter::new(file);
+    zip_bytes(&mut zip, manifest_name, manifest_json.as_bytes())?;
+
+    let mut override_count = 0;
+    if export.overrides_folder.is_dir() {
+        override_count += zip_folder(&mut zip, overrides_prefix, &export.overrides_folder)?;
+    }
+    for (path, lock_mod) in bundled {
+        info!("Bundled in overrides:", lock_mod.name.clone());
+        zip_file(
+            &mut zip,
+            &format!("{}/{}", overrides_prefix, path),
+            &export.config.installed_path(&export.dir, lock_mod),
+        )?;
+        override_count += 1;
+    }
+    finish_zip(zip)?;
+
+    info!("Files in overrides:", override_count.to_string());
+    if skipped > 0 {
+        alert!(format!("{} file(s) were left out of the modpack.", skipped));
+    }
+    confirm!(format!(
+        "Exported '{}' {} to {}",
+        export.name,
+        export.config.modpack.version,
+        display_path(&export.dir, &export.output_path)
+    ));
+    Ok(())
+}
+
+// 'minecraft' and the loader, to their versions.
+fn mrpack_dependencies(
+    config: &Config,
+    loader_version: Option<&str>,
+) -> Result<BTreeMap<String, String>, ModManError> {
+    let mut dependencies = BTreeMap::new();
+    dependencies.insert("minecraft".to_string(), config.game_version.clone());
+    if config.game_loader == GameLoader::Vanilla {
+        return Ok(dependencies);
+    }
+
+    let loader = match loader_dependency(&config.game_loader) {
+        Some(result) => result,
+        None => {
+            return Err(ModManError::InvalidCommandArguments(format!(
+                "Modrinth modpacks don't support the '{}' loader. Supported loaders: fabric, quilt, forge, neoforge",
+                config.game_loader
+            )))
+        }
+    };
+    dependencies.insert(
+        loader.to_string(),
+        required_loader_version(config, loader_version)?,
+    );
+    Ok(dependencies)
+}
+
+// Modpacks name the exact version of the loader to install.
+fn required_loader_version(
+    config: &Config,
+    loader_version: Option<&str>,
+) -> Result<String, ModManError> {
+    match loader_version.or(config.loader_version.as_deref()) {
+        Some(result) => Ok(result.to_string()),
+        None => Err(ModManError::InvalidCommandArguments(format!(
+            "The modpack needs the version of {}. Set 'loader_version' in modman.toml, or pass '--loader-version <version>'.",
+            config.game_loader
+        ))),
+    }
+}
+
+// Paths are shown relative to the project directory, if they are inside it.
+fn display_path(dir: &Path, path: &Path) -> String {
+    path.strip_prefix(dir).unwrap_or(path).display().to_string()
+}
@@ -0,0 +1,323 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::{
+    commands::command_structs::CommandOptions,
+    config::{read_config_with_alerts, read_lockfile},
+    datatypes::{DependencyType, LockMod, ModSources},
+    errors::ModManError,
+};
+
+#[derive(Serialize)]
+struct GraphNode {
+    id: String,
+    name: String,
+    source: ModSources,
+    version: Option<String>,
+    file_name: Option<String>,
+    explicit: bool,
+    locked: bool, // False if the node is a dependency which is not in the lockfile (e.g. skipped optional dependencies).
+}
+
+#[derive(Serialize)]
+struct GraphEdge {
+    from: String,
+    to: String,
+    dependency_type: DependencyType,
+}
+
+#[derive(Serialize)]
+struct Graph {
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+}
+
+pub fn command_graph(options: &CommandOptions) -> Result<(), ModManError> {
+    /*
+        The flags are as follows for 'graph' command:
+        --format <dot / json>   - The output format. Defaults to dot (Graphviz).
+
+        Prints the full resolved dependency graph from modman.lock to stdout.
+        e.g. `modman graph --format dot | dot -Tsvg > graph.svg`
+    */
+
+    let format = options.flag_value("--format").unwrap_or("dot");
+
+    let current_directory = match crate::utils::get_current_working_dir() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::IoError(e)),
+    };
+
+    let config = read_config_with_alerts(&current_directory)?;
+    let lockfile: Vec<LockMod> = match read_lockfile(&current_directory) {
+        Ok(result) => result,
+        Err(ModManError::FileNotFound) => Vec::new(),
+        Err(ModManError::FileIsEmpty) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+
+    let explicit_ids: HashSet<&str> = config.mods.iter().map(|m| m.id.as_str()).collect();
+    let graph = build_graph(&lockfile, &explicit_ids);
+
+    match format.to_lowercase().as_str() {
+        "dot" => print!("{}", graph_to_dot(&graph)),
+        "json" => println!(
+            "{}",
+            serde_json::to_string_pretty(&graph).map_err(ModManError::JsonError)?
+        ),
+        _ => {
+            return Err(ModManError::InvalidCommandArguments(format!(
+                "Unknown graph format '{}'. Use 'dot' or 'json'.",
+                format
+            )))
+        }
+    }
+
+    Ok(())
+}
+
+fn build_graph(lockfile: &[LockMod], explicit_ids: &HashSet<&str>) -> Graph {
+    let mut nodes: Vec<GraphNode> = lockfile
+        .iter()
+        .map(|lock_mod| GraphNode {
+            id: lock_mod.id.clone(),
+            name: lock_mod.name.clone(),
+            source: lock_mod.source.clone(),
+            version: Some(lock_mod.version.clone()),
+            file_name: Some(lock_mod.file_name.clone()),
+            explicit: explicit_ids.contains(lock_mod.id.as_str()),
+            locked: true,
+        })
+        .collect();
+    let mut edges: Vec<GraphEdge> = Vec::new();
+    let mut known_ids: HashSet<String> = lockfile.iter().map(|m| m.id.clone()).collect();
+
+    for lock_mod in lockfile {
+        for dep in &lock_mod.dependencies {
+            // Dependencies that were never installed still get a node, so that the edge has somewhere to point to.
+            if known_ids.insert(dep.project_id.clone()) {
+                nodes.push(GraphNode {
+                    id: dep.project_id.clone(),
+                    name: dep.project_id.clone(),
+                    source: dep.source.clone(),
+                    version: None,
+                    file_name: None,
+                    explicit: false,
+                    locked: false,
+                });
+            }
+            edges.push(GraphEdge {
+                from: lock_mod.id.clone(),
+                to: dep.project_id.clone(),
+                dependency_type: dep.dependency_type.clone(),
+            });
+        }
+    }
+
+    Graph { nodes, edges }
+}
+
+fn graph_to_dot(graph: &Graph) -> String {
+    let mut dot = String::from("digraph modman {\n    rankdir=LR;\n    node [shape=box];\n");
+
+    for node in &graph.nodes {
+        let label = match &node.version {
+            Some(version) => format!(
+                "{}\\n{} {}",
+                escape_dot(&node.name),
+                node.source,
+                escape_dot(version)
+            ),
+            None => format!("{}\\n(not installed)", escape_dot(&node.name)),
+        };
+        let style = if node.explicit {
+            ", style=\"filled,bold\", fillcolor=\"lightblue\""
+        } else if !node.locked {
+            ", style=dashed, fontcolor=gray"
+        } else {
+            ""
+        };
+        dot.push_str(&format!(
+            "    \"{}\" [label=\"{}\"{}];\n",
+            escape_dot(&node.id),
+            label,
+            style
+        ));
+    }
+
+    for edge in &graph.edges {
+        let style = match edge.dependency_type {
+            DependencyType::Required => "",
+            DependencyType::Optional => ", style=dashed",
+            DependencyType::Incompatible => ", color=red, fontcolor=red",
+            DependencyType::Embedded => ", style=dotted",
+        };
+        dot.push_str(&format!(
+            "    \"{}\" -> \"{}\" [label=\"{}\"{}];\n",
+            escape_dot(&edge.from),
+            escape_dot(&edge.to),
+            edge.dependency_type,
+            style
+        ));
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+fn escape_dot(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::{LockDependency, ProjectType, SideSupport};
+
+    fn lock_mod(id: &str, name: &str, dependencies: Vec<(&str, DependencyType)>) -> LockMod {
+        LockMod {
+            name: name.to_string(),
+            source: ModSources::Modrinth,
+            id: id.to_string(),
+            version: "1.0.0".to_string(),
+            file_name: format!("{}.jar", id),
+            release_date: "Unknown".to_string(),
+            sha512: String::new(),
+            sha1: None,
+            download_url: "Unknown".to_string(),
+            file_id: None,
+            dependencies: dependencies
+                .into_iter()
+                .map(|(project_id, dependency_type)| LockDependency {
+                    source: ModSources::Modrinth,
+                    project_id: project_id.to_string(),
+                    dependency_type,
+                })
+                .collect(),
+            size: 0,
+            disabled: false,
+            project_type: ProjectType::Mod,
+            loaders: vec![],
+            game_versions: vec![],
+            client_side: SideSupport::Unknown,
+            server_side: SideSupport::Unknown,
+        }
+    }
+
+    // Iris and Indium need Sodium. Iris also suggests ModMenu, which was not installed.
+    fn lockfile() -> Vec<LockMod> {
+        vec![
+            lock_mod(
+                "iris",
+                "Iris",
+                vec![
+                    ("sodium", DependencyType::Required),
+                    ("modmenu", DependencyType::Optional),
+                ],
+            ),
+            lock_mod(
+                "indium",
+                "Indium",
+                vec![("sodium", DependencyType::Required)],
+            ),
+            lock_mod("sodium", "Sodium", vec![]),
+        ]
+    }
+
+    #[test]
+    fn dependencies_that_are_not_installed_get_one_node() {
+        let lockfile = lockfile();
+        let graph = build_graph(&lockfile, &HashSet::from(["iris"]));
+
+        let nodes: Vec<(&str, bool, bool)> = graph
+            .nodes
+            .iter()
+            .map(|n| (n.id.as_str(), n.explicit, n.locked))
+            .collect();
+        assert_eq!(
+            nodes,
+            vec![
+                ("iris", true, true),
+                ("indium", false, true),
+                ("sodium", false, true),
+                ("modmenu", false, false),
+            ]
+        );
+        let edges: Vec<(&str, &str)> = graph
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str()))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("iris", "sodium"),
+                ("iris", "modmenu"),
+                ("indium", "sodium")
+            ]
+        );
+    }
+
+    #[test]
+    fn dot_output() {
+        let lockfile = vec![
+            lock_mod(
+                "iris",
+                "Iris \"Shaders\"",
+                vec![
+                    ("sodium", DependencyType::Required),
+                    ("optifine", DependencyType::Incompatible),
+                ],
+            ),
+            lock_mod("sodium", "Sodium", vec![]),
+        ];
+        let graph = build_graph(&lockfile, &HashSet::from(["iris"]));
+
+        assert_eq!(
+            graph_to_dot(&graph),
+            concat!(
+                "digraph modman {\n",
+                "    rankdir=LR;\n",
+                "    node [shape=box];\n",
+                "    \"iris\" [label=\"Iris \\\"Shaders\\\"\\nModrinth 1.0.0\", style=\"filled,bold\", fillcolor=\"lightblue\"];\n",
+                "    \"sodium\" [label=\"Sodium\\nModrinth 1.0.0\"];\n",
+                "    \"optifine\" [label=\"optifine\\n(not installed)\", style=dashed, fontcolor=gray];\n",
+                "    \"iris\" -> \"sodium\" [label=\"required\"];\n",
+                "    \"iris\" -> \"optifine\" [label=\"incompatible\", color=red, fontcolor=red];\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn json_output() {
+        let lockfile = lockfile();
+        let graph = build_graph(&lockfile, &HashSet::from(["iris"]));
+        let json: serde_json::Value = serde_json::to_value(&graph).unwrap();
+
+        assert_eq!(
+            json["nodes"][0],
+            serde_json::json!({
+                "id": "iris",
+                "name": "Iris",
+                "source": "Modrinth",
+                "version": "1.0.0",
+                "file_name": "iris.jar",
+                "explicit": true,
+                "locked": true,
+            })
+        );
+        assert_eq!(json["nodes"][3]["version"], serde_json::Value::Null);
+        assert_eq!(json["nodes"][3]["locked"], false);
+        assert_eq!(
+            json["edges"][1],
+            serde_json::json!({"from": "iris", "to": "modmenu", "dependency_type": "Optional"})
+        );
+    }
+
+    #[test]
+    fn dot_values_are_escaped() {
+        assert_eq!(escape_dot(r#"a "b" \c"#), r#"a \"b\" \\c"#);
+    }
+}
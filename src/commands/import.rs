@@ -0,0 +1,983 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use colored::Colorize;
+use reqwest::Client;
+use zip::ZipArchive;
+
+use crate::{
+    actionheader, alert,
+    api::{
+        curseforge::{
+            convert_curseforge_to_lockmod, curseforge_api_key, fetch_curseforge_files,
+            fetch_curseforge_mods,
+        },
+        modrinth::modrinth_mods_from_hashes,
+    },
+    commands::command_structs::CommandOptions,
+    config::{
+        active_profile, add_profile, apply_profile, config_contents, lockfile_name,
+        profile_config_contents, profile_lockfile_name, read_config_file,
+    },
+    config_sync::local_lock_mod,
+    confirm,
+    datatypes::{
+        default_datapacks_folder, default_resourcepacks_folder, default_shaderpacks_folder, Config,
+        DownloadSettings, GameLoader, LockMod, Mod, ModpackSettings, Profile, ProjectType,
+        ReleaseTypes, Side,
+    },
+    errors::ModManError,
+    info,
+    install::calculate_sha512,
+    metadata::read_metadata,
+    modpack::{
+        curseforge::{loader_from_mod_loaders, CurseForgeManifest, MANIFEST_FILE_NAME},
+        is_relative_path,
+        mrpack::{
+            self, file_name_of_path, loader_from_dependencies, local_lock_mod_from_file,
+            project_type_of_path, side_from_env, MrpackIndex, INDEX_FILE_NAME,
+        },
+        open_zip,
+        packwiz::{
+            hash_matches, loader_from_versions, lock_mod_from_metafile, side_from_packwiz,
+            PackwizCurseForge, PackwizIndex, PackwizIndexFile, PackwizMod, PackwizPack,
+            METAFILE_EXTENSION,
+        },
+        read_zip_entry, read_zip_folder,
+    },
+    transactions::{check_incomplete_transactions, Transaction},
+    utils::{calculate_total_size, convert_lock_mods_to_downloads},
+    APP_USER_AGENT,
+};
+
+pub async fn command_import(options: &CommandOptions) -> Result<(), ModManError> {
+    /*
+        The arguments are as follows for 'import' command:
+        <format>        - The modpack format: 'mrpack' (Modrinth), 'curseforge' or 'packwiz'.
+        <file>          - The modpack to import (the pack.toml of a packwiz pack).
+
+        Flags:
+        --profile <name>    - Import into a new profile, instead of a new modman.toml.
+        --force             - Overwrite an existing modman.toml, or replace an existing profile.
+
+        Importing a CurseForge modpack (or a packwiz pack with CurseForge mods) needs a CurseForge API key, in CURSEFORGE_API_KEY.
+    */
+
+    let (format, file) = match options.parameters.as_slice() {
+        [format, file, ..] => (format.to_lowercase(), file),
+        _ => return Err(ModManError::NoArguments),
+    };
+    match format.as_str() {
+        "mrpack" => import_mrpack(options, file).await,
+        "curseforge" => import_curseforge(options, file).await,
+        "packwiz" => import_packwiz(options, file).await,
+        _ => Err(ModManError::InvalidCommandArguments(format!(
+            "Unknown modpack format '{}'. Supported formats: mrpack, curseforge, packwiz",
+            format
+        ))),
+    }
+}
+
+// What every modpack format says about the pack itself.
+struct PackInfo {
+    name: String,
+    version: String,
+    summary: Option<String>,
+    author: Option<String>,
+    game_version: String,
+    game_loader: GameLoader,
+    loader_version: Option<String>,
+}
+
+// Where a pack is imported to: a new modman.toml, or a new profile in the existing one.
+struct ImportTarget {
+    config: Config,          // As seen by the profile, when importing into one.
+    game_directory: PathBuf, // The pack's overrides are written here.
+    file: Option<Config>, // modman.toml as it will be, with the new profile in it. None for a new modman.toml.
+}
+
+async fn import_mrpack(options: &CommandOptions, file: &str) -> Result<(), ModManError> {
+    /*
+        Imports a Modrinth modpack (.mrpack), to start customising a published pack with modman.
+
+        Step-by-Step Workflow:
+            1. READ: Read modrinth.index.json from the pack.
+            2. CONFIG: Create modman.toml, or the profile selected with '--profile', with the game version and loader
+                from the pack's dependencies. A profile gets its own folders, under 'profiles/<name>'.
+            3. IDENTIFY: Look up the pack's files on Modrinth by their sha512 (all in one request).
+                Files that aren't on Modrinth are added as local mods.
+            4. OVERRIDES: Stage the pack's overrides, to be written into the game directory (the project directory, or the profile's
+                folder). Files for the mods, resourcepacks or shaderpacks folder are added as local mods.
+            5. TRANSACTION: Download the pack's files, and write the overrides, modman.toml and the lockfile, as a transaction.
+
+        Nothing is written outside the transaction, so a failed import can be resumed or rolled back with `modman transactions`.
+    */
+
+    let current_directory = match crate::utils::get_current_working_dir() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::IoError(e)),
+    };
+
+    check_incomplete_transactions(&current_directory)?;
+
+    let force = options.flags.contains(&"--force".to_string());
+
+    // (1) Read modrinth.index.json.
+    let mut zip = open_pack(&current_directory, file)?;
+    let index: MrpackIndex = match read_zip_entry(&mut zip, INDEX_FILE_NAME)? {
+        Some(result) => serde_json::from_str(&result).map_err(ModManError::JsonError)?,
+        None => {
+            return Err(ModManError::InvalidCommandArguments(format!(
+                "'{}' has no {}. Is it a Modrinth modpack?",
+                file, INDEX_FILE_NAME
+            )))
+        }
+    };
+    let game_version = match index.dependencies.get("minecraft") {
+        Some(result) if index.game == "minecraft" => result.clone(),
+        _ => {
+            return Err(ModManError::InvalidCommandArguments(format!(
+                "'{}' is not a Minecraft modpack.",
+                file
+            )))
+        }
+    };
+    let (game_loader, loader_version) = match loader_from_dependencies(&index.dependencies) {
+        Some((game_loader, loader_version)) => (game_loader, Some(loader_version)),
+        None => (GameLoader::Vanilla, None),
+    };
+    let pack = PackInfo {
+        name: index.name.clone(),
+        version: index.version_id.clone(),
+        summary: index.summary.clone(),
+        author: None,
+        game_version,
+        game_loader,
+        loader_version,
+    };
+
+    // (2) Create modman.toml, or the profile.
+    let mut target = create_import_config(&current_directory, &pack, force)?;
+
+    // (3) Identify the pack's files on Modrinth.
+    let client = Arc::new(match Client::builder().user_agent(APP_USER_AGENT).build() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::ReqwestError(e)),
+    });
+    info!("Looking up files on Modrinth...");
+    let hashes: Vec<String> = index
+        .files
+        .iter()
+        .map(|f| f.hashes.sha512.clone())
+        .collect();
+    let mut modrinth_mods = match modrinth_mods_from_hashes(&client, &hashes).await {
+        Ok(result) => result,
+        Err(e) => {
+            alert!(format!(
+                "Could not look up files on Modrinth ({}). They will be added as local mods.",
+                e
+            ));
+            HashMap::new()
+        }
+    };
+
+    let mut lock_mods: Vec<LockMod> = Vec::new();
+    let mut not_on_modrinth: Vec<String> = Vec::new();
+    for file in &index.files {
+        let project_type = match project_type_of_path(&file.path) {
+            Some(result) => result,
+            None => {
+                alert!(format!(
+                    "Skipped '{}': modman only manages mods, resource packs and shaders.",
+                    file.path
+                ));
+                continue;
+            }
+        };
+        let Some(file_name) = file_name_of_path(&file.path) else {
+            alert!(format!(
+                "Skipped '{}': its file name could point outside its folder.",
+                file.path
+            ));
+            continue;
+        };
+        let lock_mod = match modrinth_mods.remove(&file.hashes.sha512) {
+            Some(mut result) => {
+                result.file_name = file_name.to_string();
+                result.project_type = project_type;
+                result.disabled = false;
+                result
+            }
+            None => {
+                not_on_modrinth.push(file_name.to_string());
+                local_lock_mod_from_file(file, file_name, project_type)
+            }
+        };
+        add_mod(
+            &mut target.config,
+            &lock_mod,
+            side_from_env(file.env.as_ref()),
+        );
+        lock_mods.push(lock_mod);
+    }
+    for file_name in &not_on_modrinth {
+        info!("Not on Modrinth, added as a local mod:", file_name.clone());
+    }
+
+    // (4) Stage the overrides. Launchers use 'client-overrides' on clients, and 'server-overrides' on servers.
+    let side_overrides = match target.config.side {
+        Side::Server => "server-overrides",
+        _ => "client-overrides",
+    };
+    let mut transaction = Transaction::begin(&current_directory, "import")?;
+    let extracted = extract_overrides(
+        &mut zip,
+        &[mrpack::OVERRIDES_FOLDER, side_overrides],
+        &current_directory,
+        &mut transaction,
+        &mut target,
+        &mut lock_mods,
+    )?;
+
+    // (5) Download the files and write the overrides, modman.toml and the lockfile as a transaction.
+    install_import(
+        &current_directory,
+        &client,
+        transaction,
+        &target,
+        &mut lock_mods,
+        &extracted,
+    )
+    .await?;
+
+    confirm!(format!(
+        "Imported '{}' {} with {} file(s).",
+        pack.name,
+        pack.version,
+        lock_mods.len()
+    ));
+    Ok(())
+}
+
+async fn import_curseforge(options: &CommandOptions, file: &str) -> Result<(), ModManError> {
+    /*
+        Imports a CurseForge modpack (the zip exported by the CurseForge app), the same way as a Modrinth modpack.
+
+        Step-by-Step Workflow:
+            1. READ: Read manifest.json from the pack.
+            2. CONFIG: Create modman.toml, or the profile selected with '--profile', with the Minecraft version and primary loader
+                from the manifest.
+            3. IDENTIFY: Fetch the manifest's files and their projects from CurseForge (by project and file id).
+                Files that are not required by the pack are added disabled.
+                Files whose author doesn't allow downloads from other apps are skipped, and listed.
+            4. OVERRIDES: Stage the pack's overrides, to be written into the game directory, adding files for the mods,
+                resourcepacks or shaderpacks folder as local mods.
+            5. TRANSACTION: Download the pack's files, and write the overrides, modman.toml and the lockfile, as a transaction.
+    */
+
+    let current_directory = match crate::utils::get_current_working_dir() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::IoError(e)),
+    };
+
+    check_incomplete_transactions(&current_directory)?;
+
+    let force = options.flags.contains(&"--force".to_string());
+
+    // (1) Read manifest.json.
+    let mut zip = open_pack(&current_directory, file)?;
+    let manifest: CurseForgeManifest = match read_zip_entry(&mut zip, MANIFEST_FILE_NAME)? {
+        Some(result) => serde_json::from_str(&result).map_err(ModManError::JsonError)?,
+        None => {
+            return Err(ModManError::InvalidCommandArguments(format!(
+                "'{}' has no {}. Is it a CurseForge modpack?",
+                file, MANIFEST_FILE_NAME
+            )))
+        }
+    };
+    if manifest.manifest_type != "minecraftModpack" {
+        return Err(ModManError::InvalidCommandArguments(format!(
+            "'{}' is not a Minecraft modpack.",
+            file
+        )));
+    }
+    let (game_loader, loader_version) =
+        match loader_from_mod_loaders(&manifest.minecraft.mod_loaders) {
+            Some((game_loader, loader_version)) => (game_loader, Some(loader_version)),
+            None if manifest.minecraft.mod_loaders.is_empty() => (GameLoader::Vanilla, None),
+            None => {
+                let ids: Vec<&str> = manifest
+                    .minecraft
+                    .mod_loaders
+                    .iter()
+                    .map(|l| l.id.as_str())
+                    .collect();
+                return Err(ModManError::InvalidCommandArguments(format!(
+                    "The modpack's loader is not supported: {}",
+                    ids.join(", ")
+                )));
+            }
+        };
+    if curseforge_api_key().is_none() {
+        return Err(ModManError::InvalidCommandArguments(
+            "Importing a CurseForge modpack needs a CurseForge API key. Set CURSEFORGE_API_KEY."
+                .to_string(),
+        ));
+    }
+    let pack = PackInfo {
+        name: manifest.name.clone(),
+        version: manifest.version.clone(),
+        summary: None,
+        author: Some(manifest.author.clone()).filter(|author| !author.is_empty()),
+        game_version: manifest.minecraft.version.clone(),
+        game_loader,
+        loader_version,
+    };
+
+    // (2) Create modman.toml, or the profile.
+    let mut target = create_import_config(&current_directory, &pack, force)?;
+
+    // (3) Fetch the files and their projects from CurseForge.
+    let client = Arc::new(match Client::builder().user_agent(APP_USER_AGENT).build() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::ReqwestError(e)),
+    });
+    info!("Looking up files on CurseForge...");
+    let file_ids: Vec<u64> = manifest.files.iter().map(|f| f.file_id).collect();
+    let mut mod_ids: Vec<u64> = manifest.files.iter().map(|f| f.project_id).collect();
+    mod_ids.sort();
+    mod_ids.dedup();
+    let (files, projects) = tokio::join!(
+        fetch_curseforge_files(&client, &file_ids),
+        fetch_curseforge_mods(&client, &mod_ids)
+    );
+    let files = files.map_err(|e| ModManError::APIFetchError(e.to_string()))?;
+    let projects = projects.map_err(|e| ModManError::APIFetchError(e.to_string()))?;
+
+    let mut lock_mods: Vec<LockMod> = Vec::new();
+    let mut skipped: usize = 0;
+    for manifest_file in &manifest.files {
+        let found = files
+            .iter()
+            .find(|f| f.id == manifest_file.file_id)
+            .zip(projects.iter().find(|p| p.id == manifest_file.project_id));
+        let (cf_file, project) = match found {
+            Some(result) => result,
+            None => {
+                alert!(format!(
+                    "Skipped file {} of project {}: it is not on CurseForge.",
+                    manifest_file.file_id, manifest_file.project_id
+                ));
+                skipped += 1;
+                continue;
+            }
+        };
+        if cf_file.download_url.is_none() {
+            alert!(format!(
+                "Skipped '{}': its author doesn't allow downloads from other apps. Download '{}' from CurseForge by hand, then run 'modman sync'.",
+                project.name, cf_file.file_name
+            ));
+            skipped += 1;
+            continue;
+        }
+        let mut lock_mod = convert_curseforge_to_lockmod(cf_file, project);
+        lock_mod.disabled = !manifest_file.required;
+        add_mod(&mut target.config, &lock_mod, None);
+        lock_mods.push(lock_mod);
+    }
+
+    // (4) Stage the overrides.
+    let mut transaction = Transaction::begin(&current_directory, "import")?;
+    let extracted = extract_overrides(
+        &mut zip,
+        &[manifest.overrides.as_str()],
+        &current_directory,
+        &mut transaction,
+        &mut target,
+        &mut lock_mods,
+    )?;
+
+    // (5) Download the files and write the overrides, modman.toml and the lockfile as a transaction.
+    install_import(
+        &current_directory,
+        &client,
+        transaction,
+        &target,
+        &mut lock_mods,
+        &extracted,
+    )
+    .await?;
+
+    if skipped > 0 {
+        alert!(format!(
+            "{} file(s) of the modpack could not be imported.",
+            skipped
+        ));
+    }
+    confirm!(format!(
+        "Imported '{}' {} with {} file(s).",
+        pack.name,
+        pack.version,
+        lock_mods.len()
+    ));
+    Ok(())
+}
+
+async fn import_packwiz(options: &CommandOptions, file: &str) -> Result<(), ModManError> {
+    /*
+        Imports a packwiz pack from its pack.toml, keeping the exact file of every mod.
+
+        Step-by-Step Workflow:
+            1. READ: Read pack.toml, its index (index.toml), and every metafile ('.pw.toml') in the index.
+            2. CONFIG: Create modman.toml, or the profile selected with '--profile', with the Minecraft version and loader
+                from [versions].
+            3. IDENTIFY: Metafiles with [update.modrinth] are looked up on Modrinth by the sha512 of their file (all in one request),
+                and metafiles with [update.curseforge] are fetched from CurseForge (by project and file id).
+                Modrinth files which can't be looked up are added from their metafile alone, with the same file.
+                Files with no Modrinth or CurseForge project are added as local mods, downloaded from their url.
+                Optional mods which are not installed by default are added disabled.
+                Metafiles whose 'filename' is not a bare file name (e.g. '../options.txt') are skipped.
+            4. FILES: Stage the other files of the index, to be copied into the game directory, adding files for the mods,
+                resourcepacks or shaderpacks folder as local mods.
+            5. TRANSACTION: Download the mods, and write the other files, modman.toml and the lockfile, as a transaction.
+
+        Downloads are checked with the hash in their metafile, which has to be sha512 or sha1.
+    */
+
+    let current_directory = match crate::utils::get_current_working_dir() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::IoError(e)),
+    };
+
+    check_incomplete_transactions(&current_directory)?;
+
+    let force = options.flags.contains(&"--force".to_string());
+
+    // (1) Read pack.toml, the index and the metafiles.
+    let pack_path = current_directory.join(file);
+    if !pack_path.is_file() {
+        return Err(ModManError::InvalidCommandArguments(format!(
+            "'{}' is not a file.",
+            pack_path.display()
+        )));
+    }
+    let pack_dir = pack_path
+        .parent()
+        .unwrap_or(&current_directory)
+        .to_path_buf();
+    let pack_toml = fs::read_to_string(&pack_path).map_err(ModManError::IoError)?;
+    let pack_file: PackwizPack =
+        toml::from_str(&pack_toml).map_err(ModManError::DeserializationError)?;
+    let game_version = match pack_file.versions.get("minecraft") {
+        Some(result) => result.clone(),
+        None => {
+            return Err(ModManError::InvalidCommandArguments(format!(
+                "'{}' has no Minecraft version in [versions]. Is it a packwiz pack?",
+                file
+            )))
+        }
+    };
+    let (game_loader, loader_version) = match loader_from_versions(&pack_file.versions) {
+        Some((game_loader, loader_version)) => (game_loader, Some(loader_version)),
+        None => (GameLoader::Vanilla, None),
+    };
+
+    let index_toml =
+        fs::read_to_string(pack_dir.join(&pack_file.index.file)).map_err(ModManError::IoError)?;
+    if !hash_matches(
+        index_toml.as_bytes(),
+        &pack_file.index.hash_format,
+        &pack_file.index.hash,
+    ) {
+        alert!("The index doesn't match the hash in pack.toml. Run 'packwiz refresh' if the pack was edited by hand.");
+    }
+    let index: PackwizIndex =
+        toml::from_str(&index_toml).map_err(ModManError::DeserializationError)?;
+    let mut metafiles: Vec<(&str, PackwizMod)> = Vec::new(); // path in the pack, metafile
+    let mut other_files: Vec<&PackwizIndexFile> = Vec::new();
+    for index_file in &index.files {
+        if !index_file.metafile && !index_file.file.ends_with(METAFILE_EXTENSION) {
+            other_files.push(index_file);
+            continue;
+        }
+        let contents =
+            fs::read_to_string(pack_dir.join(&index_file.file)).map_err(ModManError::IoError)?;
+        let packwiz_mod: PackwizMod =
+            toml::from_str(&contents).map_err(ModManError::DeserializationError)?;
+        metafiles.push((&index_file.file, packwiz_mod));
+    }
+
+    let curseforge_files: Vec<&PackwizCurseForge> = metafiles
+        .iter()
+        .filter_map(|(_, m)| m.update.as_ref()?.curseforge.as_ref())
+        .collect();
+    if !curseforge_files.is_empty() && curseforge_api_key().is_none() {
+        return Err(ModManError::InvalidCommandArguments(format!(
+            "The pack has {} CurseForge mod(s), which need a CurseForge API key. Set CURSEFORGE_API_KEY.",
+            curseforge_files.len()
+        )));
+    }
+    let pack = PackInfo {
+        name: pack_file.name.clone(),
+        version: pack_file
+            .version
+            .clone()
+            .unwrap_or_else(|| ModpackSettings::default().version),
+        summary: pack_file.description.clone(),
+        author: pack_file.author.clone(),
+        game_version,
+        game_loader,
+        loader_version,
+    };
+
+    // (2) Create modman.toml, or the profile.
+    let mut target = create_import_config(&current_directory, &pack, force)?;
+
+    // (3) Look up the metafiles on Modrinth and CurseForge.
+    let client = Arc::new(match Client::builder().user_agent(APP_USER_AGENT).build() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::ReqwestError(e)),
+    });
+    let modrinth_hashes: Vec<String> = metafiles
+        .iter()
+        .filter(|(_, m)| modrinth_sha512(m).is_some())
+        .map(|(_, m)| m.download.hash.clone())
+        .collect();
+    let mut modrinth_mods = HashMap::new();
+    if !modrinth_hashes.is_empty() {
+        info!("Looking up files on Modrinth...");
+        match modrinth_mods_from_hashes(&client, &modrinth_hashes).await {
+            Ok(result) => modrinth_mods = result,
+            Err(e) => alert!(format!(
+                "Could not look up files on Modrinth ({}). They will be added from their metafiles.",
+                e
+            )),
+        }
+    }
+    let (mut files, mut projects) = (Vec::new(), Vec::new());
+    if !curseforge_files.is_empty() {
+        info!("Looking up files on CurseForge...");
+        let file_ids: Vec<u64> = curseforge_files.iter().map(|f| f.file_id).collect();
+        let mut mod_ids: Vec<u64> = curseforge_files.iter().map(|f| f.project_id).collect();
+        mod_ids.sort();
+        mod_ids.dedup();
+        let (fetched_files, fetched_projects) = tokio::join!(
+            fetch_curseforge_files(&client, &file_ids),
+            fetch_curseforge_mods(&client, &mod_ids)
+        );
+        files = fetched_files.map_err(|e| ModManError::APIFetchError(e.to_string()))?;
+        projects = fetched_projects.map_err(|e| ModManError::APIFetchError(e.to_string()))?;
+    }
+
+    let mut lock_mods: Vec<LockMod> = Vec::new();
+    let mut skipped: usize = 0;
+    for (path, packwiz_mod) in &metafiles {
+        let Some(project_type) = project_type_of_path(path) else {
+            alert!(format!(
+                "Skipped '{}': modman only manages mods, resource packs and shaders.",
+                path
+            ));
+            skipped += 1;
+            continue;
+        };
+        let Some(file_name) = packwiz_mod.file_name() else {
+            alert!(format!(
+                "Skipped '{}': its file name '{}' could point outside its folder.",
+                packwiz_mod.name, packwiz_mod.filename
+            ));
+            skipped += 1;
+            continue;
+        };
+        let curseforge = packwiz_mod
+            .update
+            .as_ref()
+            .and_then(|u| u.curseforge.as_ref());
+        let found = match curseforge {
+            Some(ids) => {
+                let found = files
+                    .iter()
+                    .find(|f| f.id == ids.file_id)
+                    .zip(projects.iter().find(|p| p.id == ids.project_id));
+                match found {
+                    Some((cf_file, _)) if cf_file.download_url.is_none() => {
+                        alert!(format!(
+                            "Skipped '{}': its author doesn't allow downloads from other apps. Download '{}' from CurseForge by hand, then run 'modman sync'.",
+                            packwiz_mod.name, packwiz_mod.filename
+                        ));
+                        skipped += 1;
+                        continue;
+                    }
+                    Some((cf_file, project)) => {
+                        Some(convert_curseforge_to_lockmod(cf_file, project))
+                    }
+                    None => None,
+                }
+            }
+            None => modrinth_sha512(packwiz_mod).and_then(|hash| modrinth_mods.remove(hash)),
+        };
+        let mut lock_mod = match found.or_else(|| lock_mod_from_metafile(packwiz_mod)) {
+            Some(result) => result,
+            None => {
+                alert!(format!(
+                    "Skipped '{}': it could not be found, and its metafile has no url, or a hash that is not sha512 or sha1.",
+                    packwiz_mod.name
+                ));
+                skipped += 1;
+                continue;
+            }
+        };
+        lock_mod.file_name = file_name.to_string();
+        lock_mod.project_type = project_type;
+        lock_mod.disabled = packwiz_mod.is_disabled();
+        add_mod(
+            &mut target.config,
+            &lock_mod,
+            side_from_packwiz(packwiz_mod.side.as_deref()),
+        );
+        lock_mods.push(lock_mod);
+    }
+
+    // (4) Stage the other files.
+    let mut transaction = Transaction::begin(&current_directory, "import")?;
+    let staged_files = copy_pack_files(
+        &pack_dir,
+        &index,
+        &other_files,
+        &current_directory,
+        &mut transaction,
+        &target.game_directory,
+    )?;
+    add_local_files(
+        &current_directory,
+        &mut target.config,
+        &mut lock_mods,
+        &staged_files,
+    )?;
+    info!("Copied files:", staged_files.len().to_string());
+    let copied: Vec<PathBuf> = staged_files.into_iter().map(|(path, _)| path).collect();
+
+    // (5) Download the files and write the other files, modman.toml and the lockfile as a transaction.
+    install_import(
+        &current_directory,
+        &client,
+        transaction,
+        &target,
+        &mut lock_mods,
+        &copied,
+    )
+    .await?;
+
+    if skipped > 0 {
+        alert!(format!(
+            "{} file(s) of the pack could not be imported.",
+            skipped
+        ));
+    }
+    confirm!(format!(
+        "Imported '{}' {} with {} file(s).",
+        pack.name,
+        pack.version,
+        lock_mods.len()
+    ));
+    Ok(())
+}
+
+// The sha512 of a Modrinth metafile's file, which it is looked up with.
+fn modrinth_sha512(packwiz_mod: &PackwizMod) -> Option<&String> {
+    let update = packwiz_mod.update.as_ref()?;
+    (update.modrinth.is_some() && packwiz_mod.download.hash_format == "sha512")
+        .then_some(&packwiz_mod.download.hash)
+}
+
+// Stages files of the pack to be copied into the game directory, checking them against the index.
+// Returns the paths of the files in the game directory, with their staged copies.
+// Files with paths leaving the pack (e.g. '../') are skipped.
+fn copy_pack_files(
+    pack_dir: &Path,
+    index: &PackwizIndex,
+    index_files: &[&PackwizIndexFile],
+    dir: &Path,
+    transaction: &mut Transaction,
+    game_directory: &Path,
+) -> Result<Vec<(PathBuf, PathBuf)>, ModManError> {
+    let mut copied = Vec::new();
+    for index_file in index_files {
+        let relative_path = Path::new(&index_file.file);
+        if !is_relative_path(relative_path) || index_file.file.contains('\\') {
+            alert!(format!(
+                "Skipped '{}': it is outside the pack.",
+                index_file.file
+            ));
+            continue;
+        }
+        let bytes = match fs::read(pack_dir.join(relative_path)) {
+            Ok(result) => result,
+            Err(e) => {
+                alert!(format!("Skipped '{}': {}", index_file.file, e));
+                continue;
+            }
+        };
+        let hash_format = index_file
+            .hash_format
+            .as_deref()
+            .unwrap_or(&index.hash_format);
+        if !hash_matches(&bytes, hash_format, &index_file.hash) {
+            alert!(format!(
+                "Skipped '{}': it doesn't match the hash in the index. Run 'packwiz refresh' if the pack was edited by hand.",
+                index_file.file
+            ));
+            continue;
+        }
+        let dest = game_directory.join(relative_path);
+        let staged = transaction.plan_write_file(dir, dest.clone(), &bytes)?;
+        copied.push((dest, staged));
+    }
+    Ok(copied)
+}
+
+fn open_pack(dir: &Path, file: &str) -> Result<ZipArchive<File>, ModManError> {
+    let pack_path = dir.join(file);
+    match open_zip(&pack_path) {
+        Ok(result) => Ok(result),
+        Err(ModManError::FileNotFound) => Err(ModManError::InvalidCommandArguments(format!(
+            "'{}' is not a file.",
+            pack_path.display()
+        ))),
+        Err(e) => Err(e),
+    }
+}
+
+// Creates the config of the pack: a new modman.toml, or a new profile (with its own folders) if one is selected.
+// Nothing is written yet, see install_import.
+fn create_import_config(
+    dir: &Path,
+    pack: &PackInfo,
+    force: bool,
+) -> Result<ImportTarget, ModManError> {
+    actionheader!("Import");
+    info!("Modpack:", format!("{} {}", pack.name, pack.version));
+    info!("Game version:", pack.game_version.clone());
+    let loader = match &pack.loader_version {
+        Some(version) => format!("{} {}", pack.game_loader, version),
+        None => pack.game_loader.to_string(),
+    };
+    info!("Loader:", loader);
+
+    let name = match active_profile() {
+        Some(result) => result,
+        None => {
+            if dir.join("modman.toml").exists() && !force {
+                return Err(ModManError::ConfigAlreadyExists);
+            }
+            let config = Config {
+                game_version: pack.game_version.clone(),
+                game_loader: pack.game_loader.clone(),
+                loader_version: pack.loader_version.clone(),
+                allowed_release_types: vec![
+                    ReleaseTypes::Alpha,
+                    ReleaseTypes::Beta,
+                    ReleaseTypes::Release,
+                ],
+                mods_folder: PathBuf::from("./mods"),
+                resourcepacks_folder: default_resourcepacks_folder(),
+                shaderpacks_folder: default_shaderpacks_folder(),
+                datapacks_folder: default_datapacks_folder(),
+                downloads: DownloadSettings::default(),
+                side: Side::default(),
+                modpack: ModpackSettings {
+                    name: Some(pack.name.clone()),
+                    version: pack.version.clone(),
+                    summary: pack.summary.clone(),
+                    author: pack.author.clone(),
+                    ..ModpackSettings::default()
+                },
+                instance: None,
+                mods: Vec::new(),
+                profiles: Default::default(),
+            };
+            return Ok(ImportTarget {
+                config,
+                game_directory: dir.to_path_buf(),
+                file: None,
+            });
+        }
+    };
+
+    if !dir.join("modman.toml").exists() {
+        return Err(ModManError::InvalidCommandArguments(
+            "There is no modman.toml to add the profile to. Run 'modman init' first, or import without '--profile'.".to_string(),
+        ));
+    }
+    let folder = PathBuf::from("./profiles").join(name);
+    let profile = Profile {
+        inherit: false,
+        game_version: Some(pack.game_version.clone()),
+        game_loader: Some(pack.game_loader.clone()),
+        loader_version: pack.loader_version.clone(),
+        mods_folder: Some(folder.join("mods")),
+        resourcepacks_folder: Some(folder.join("resourcepacks")),
+        shaderpacks_folder: Some(folder.join("shaderpacks")),
+        datapacks_folder: Some(folder.join("world/datapacks")),
+        side: None,
+        instance: None,
+        lockfile: None,
+        remove: Vec::new(),
+        add: Vec::new(),
+    };
+    let mut file = read_config_file(dir)?;
+    add_profile(&mut file, name, profile, force)?;
+    info!("Profile:", name.to_string());
+    Ok(ImportTarget {
+        config: apply_profile(file.clone(), name)?,
+        game_directory: dir.join(folder),
+        file: Some(file),
+    })
+}
+
+// Stages the overrides folders of the pack to be written into the game directory, adding local mods (see add_local_files).
+// Returns the paths of the files in the game directory.
+fn extract_overrides(
+    zip: &mut ZipArchive<File>,
+    folders: &[&str],
+    dir: &Path,
+    transaction: &mut Transaction,
+    target: &mut ImportTarget,
+    lock_mods: &mut Vec<LockMod>,
+) -> Result<Vec<PathBuf>, ModManError> {
+    let mut extracted = Vec::new();
+    for folder in folders {
+        for (relative_path, bytes) in read_zip_folder(zip, folder)? {
+            let path = target.game_directory.join(relative_path);
+            let staged = transaction.plan_write_file(dir, path.clone(), &bytes)?;
+            extracted.push((path, staged));
+        }
+    }
+    add_local_files(dir, &mut target.config, lock_mods, &extracted)?;
+    info!("Extracted overrides:", extracted.len().to_string());
+    Ok(extracted.into_iter().map(|(path, _)| path).collect())
+}
+
+// Adds the files copied into the folder of a project type (e.g. a jar in the mods folder) as local mods,
+// replacing any pack file with the same name. Files are read from their staged copies, as (path, staged copy).
+fn add_local_files(
+    dir: &Path,
+    config: &mut Config,
+    lock_mods: &mut Vec<LockMod>,
+    files: &[(PathBuf, PathBuf)],
+) -> Result<(), ModManError> {
+    for (path, staged) in files {
+        let Some(project_type) = extracted_project_type(dir, config, path) else {
+            continue;
+        };
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let checksum = calculate_sha512(staged).map_err(ModManError::IoError)?;
+        lock_mods.retain(|m| !(m.project_type == project_type && m.file_name == file_name));
+        let metadata = match project_type {
+            ProjectType::Mod => read_metadata(staged),
+            _ => None,
+        };
+        let lock_mod = local_lock_mod(
+            staged,
+            &file_name,
+            &checksum,
+            false,
+            project_type,
+            metadata,
+            |id| lock_mods.iter().any(|m| m.id == id),
+        )?;
+        add_mod(config, &lock_mod, None);
+        info!("Added as a local mod:", lock_mod.name.clone());
+        lock_mods.push(lock_mod);
+    }
+    Ok(())
+}
+
+// Downloads the pack's files (except the ones extracted from the overrides), then writes the overrides, modman.toml and the lockfile.
+// Every write is planned before the downloads start, so a resumed transaction still writes them.
+// Files without a sha512 (from CurseForge) or a size (from packwiz metafiles) get them from the downloaded file
+// when the lockfile is written.
+async fn install_import(
+    dir: &Path,
+    client: &Arc<Client>,
+    mut transaction: Transaction,
+    target: &ImportTarget,
+    lock_mods: &mut [LockMod],
+    extracted: &[PathBuf],
+) -> Result<(), ModManError> {
+    let config = &target.config;
+    let to_download: Vec<LockMod> = lock_mods
+        .iter()
+        .filter(|m| config.wants(m) && !extracted.contains(&config.installed_path(dir, m)))
+        .cloned()
+        .collect();
+    if !to_download.is_empty() {
+        info!("Total download size: ", calculate_total_size(&to_download));
+    }
+
+    println!();
+    actionheader!("Transaction");
+    for download in convert_lock_mods_to_downloads(dir, config, to_download, &[]) {
+        transaction.plan_download(dir, download)?;
+    }
+    // The new profile is not in modman.toml yet, so its contents and lockfile name come from the staged modman.toml.
+    let (config_data, lockfile) = match (&target.file, active_profile()) {
+        (Some(file), Some(name)) => (
+            profile_config_contents(file.clone(), config, name)?,
+            profile_lockfile_name(file, name)?,
+        ),
+        _ => (config_contents(dir, config)?, lockfile_name(dir)?),
+    };
+    transaction.plan_write_file(dir, dir.join("modman.toml"), config_data.as_bytes())?;
+    let fill_in: Vec<(usize, PathBuf)> = lock_mods
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.sha512.is_empty() || m.size == 0)
+        .map(|(index, m)| (index, config.installed_path(dir, m)))
+        .collect();
+    transaction.plan_write_lockfile_to(dir, dir.join(lockfile), lock_mods, fill_in)?;
+
+    let report = transaction.download(dir, client).await?;
+    if !report.failed().is_empty() {
+        report.print_failures();
+        alert!("Transaction failed. Run 'modman transactions' to resume or roll it back.");
+        return Err(ModManError::IncompleteTransaction(transaction.id.clone()));
+    }
+
+    if let Err(e) = transaction.finish(dir) {
+        alert!("Transaction failed. Run 'modman transactions' to resume or roll it back.");
+        return Err(e);
+    }
+    Ok(())
+}
+
+// Adds a lockfile entry to the config, unless it is already there.
+fn add_mod(config: &mut Config, lock_mod: &LockMod, side: Option<Side>) {
+    if config.mods.iter().any(|m| m.id == lock_mod.id) {
+        return;
+    }
+    config.mods.push(Mod {
+        source: lock_mod.source.clone(),
+        id: lock_mod.id.clone(),
+        name: lock_mod.name.clone(),
+        project_type: lock_mod.project_type.clone(),
+        enabled: !lock_mod.disabled,
+        side,
+    });
+}
+
+// Project type of an extracted file, if it was extracted into the folder of one (e.g. a jar in the mods folder).
+fn extracted_project_type(dir: &Path, config: &Config, path: &Path) -> Option<ProjectType> {
+    let folder = path.parent()?;
+    let file_name = path.file_name()?.to_string_lossy().to_string();
+    ProjectType::ALL.into_iter().find(|project_type| {
+        config.project_path(dir, project_type) == folder
+            && file_name.ends_with(&format!(".{}", project_type.file_extension()))
+    })
+}
@@ -1,22 +1,67 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::io::{self, Write};
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use colored::Colorize;
 use crossterm::cursor::MoveToPreviousLine;
 use crossterm::execute;
 use crossterm::terminal::{Clear, ClearType};
+use reqwest::Client;
 
-use crate::datatypes::{Config, GameLoader, ReleaseTypes};
+use crate::commands::command_structs::CommandOptions;
+use crate::commands::init_tools::from_folder::scan_folder;
+use crate::datatypes::{
+    default_datapacks_folder, default_resourcepacks_folder, default_shaderpacks_folder, Config,
+    DownloadSettings, GameLoader, Mod, ModpackSettings, ReleaseTypes, Side,
+};
 use crate::errors::ModManError;
+use crate::instance::read_instance;
+use crate::tags::GameTags;
 use crate::utils::get_current_working_dir;
-use crate::{alert, confirm, info, request, requestconfirm};
+use crate::{alert, confirm, info, request, requestconfirm, APP_USER_AGENT};
 
-pub fn command_init() -> Result<(), ModManError> {
-    let mut game_version = String::new();
-    let mut game_loader: Option<GameLoader> = None;
-    let mut allowed_release_types: Vec<ReleaseTypes> = Vec::new();
-    let mut mods_folder = String::new();
+pub async fn command_init(options: &CommandOptions) -> Result<(), ModManError> {
+    /*
+        The flags are as follows for 'init' command:
+        --game-version <version>    - Version of Minecraft.                         (env: MODMAN_GAME_VERSION)
+        --loader <loader>           - Loader of Minecraft (fabric, quilt, etc.).    (env: MODMAN_LOADER)
+        --release-types <types>     - Allowed release types, seperated by comma.    (env: MODMAN_RELEASE_TYPES)
+        --mods-folder <path>        - Mods folder.                                  (env: MODMAN_MODS_FOLDER)
+        --yes                       - Don't ask anything. Values not given use their default.   (env: MODMAN_YES)
+        --force                     - Overwrite an existing modman.toml.            (env: MODMAN_FORCE)
+        --from-folder <path>        - Start from an existing mods folder, which is used as the mods folder.
+        --instance <path>           - Start from a Prism Launcher or MultiMC instance.
+
+        Flags take priority over environment variables. Values that are given are not asked for.
+        With '--yes', '--loader' is required, the game version defaults to the latest release,
+        release types to 'alpha, beta, release' and the mods folder to './mods'.
+        Without '--force', '--yes' refuses to overwrite an existing (or broken) modman.toml.
+
+        The game version and loader are checked against Modrinth's lists of versions and loaders
+        (cached for when Modrinth can't be reached), suggesting close matches for mistyped ones.
+
+        With '--from-folder', the jars in the folder are identified (on Modrinth by their hash, on CurseForge by their
+        fingerprint if CURSEFORGE_API_KEY is set, or from their metadata), and written to modman.toml and modman.lock.
+        Jars that can't be identified are added as local mods, and listed.
+        Unless given, the game version and loader are the ones supported by the most jars.
+
+        With '--instance', the game version and loader (and its version) are read from the instance's mmc-pack.json,
+        and the mods, resourcepacks and shaderpacks folders are the ones in its '.minecraft' folder. Its mods folder is
+        scanned the same way as with '--from-folder'. '--game-version', '--loader' and '--mods-folder' (or their
+        environment variables) can't be given with '--instance'. 'modman upgrade' keeps mmc-pack.json in sync with the game version.
+    */
+
+    // Profiles are added to an existing modman.toml by hand, so init always writes the top of modman.toml.
+    if let Some(profile) = crate::config::active_profile() {
+        return Err(ModManError::InvalidCommandArguments(format!(
+            "'modman init' can't be used with a profile ('{}'). Add profiles to modman.toml as [profiles.<name>] instead.",
+            profile
+        )));
+    }
+
+    let yes = options.flags.contains(&"--yes".to_string()) || env_flag("MODMAN_YES");
+    let force = options.flags.contains(&"--force".to_string()) || env_flag("MODMAN_FORCE");
 
     let current_dir = match get_current_working_dir() {
         Ok(path) => path,
@@ -26,144 +71,415 @@ pub fn command_init() -> Result<(), ModManError> {
     info!("Current directory:", current_dir.display().to_string());
 
     match crate::config::read_config(&current_dir) {
+        Ok(_) | Err(ModManError::DeserializationError(_)) if yes && !force => {
+            alert!("Found existing modman.toml file! Pass '--force' to overwrite it.");
+            return Err(ModManError::ConfigAlreadyExists);
+        }
         Ok(_) => {
-            alert!("Found existing modman.toml file! 'modman init' will ERASE modman.toml, thus removing the mods list!");
-            alert!("To prevent this, press '^C' (Ctrl + C) to exit.")
+            if !force {
+                alert!("Found existing modman.toml file! 'modman init' will ERASE modman.toml, thus removing the mods list!");
+                alert!("To prevent this, press '^C' (Ctrl + C) to exit.")
+            }
         }
         Err(ModManError::FileNotFound) => {}
         Err(ModManError::FileIsEmpty) => {}
         Err(ModManError::DeserializationError(e)) => {
-            alert!("Either config file modman.toml has incorrect information, or is corrupt.");
-            alert!("'modman init' will RESET the broken config file.");
-            alert!("It might be a good idea to create a backup of the config file if you have mods saved there.");
-            println!("   {} {}", "The error was:".bright_red(), e);
-            info!("Continue below with modman init to reset broken config...");
+            if !force {
+                alert!("Either config file modman.toml has incorrect information, or is corrupt.");
+                alert!("'modman init' will RESET the broken config file.");
+                alert!("It might be a good idea to create a backup of the config file if you have mods saved there.");
+                println!("   {} {}", "The error was:".bright_red(), e);
+                info!("Continue below with modman init to reset broken config...");
+            }
         }
         Err(e) => return Err(e),
     }
 
-    // Ask user for version
+    // An instance gives the game version, loader and folders, so these can't be given as well.
+    let instance_path = options.flag_value("--instance");
+    let instance = match instance_path {
+        Some(path) => {
+            for (flag, env_var) in [
+                ("--game-version", "MODMAN_GAME_VERSION"),
+                ("--loader", "MODMAN_LOADER"),
+                ("--mods-folder", "MODMAN_MODS_FOLDER"),
+            ] {
+                if init_value(options, flag, env_var).is_some() {
+                    return Err(ModManError::InvalidCommandArguments(format!(
+                        "'{}' ({}) can't be used with '--instance', which gives it.",
+                        flag, env_var
+                    )));
+                }
+            }
+            if options.flag_value("--from-folder").is_some() {
+                return Err(ModManError::InvalidCommandArguments(
+                    "'--from-folder' can't be used with '--instance', which gives it.".to_string(),
+                ));
+            }
+            let instance = read_instance(&current_dir.join(path))?;
+            info!("Instance:", path.to_string());
+            Some(instance)
+        }
+        None => None,
+    };
+    let instance_game_dir = instance_path
+        .zip(instance.as_ref())
+        .map(|(path, instance)| PathBuf::from(path).join(instance.game_dir_name));
+
+    let client = match Client::builder()
+        .user_agent(APP_USER_AGENT)
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+    {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::ReqwestError(e)),
+    };
+    let tags = GameTags::load(&client).await;
+    let tags = tags.as_ref();
+
+    let from_folder = match (options.flag_value("--from-folder"), &instance_game_dir) {
+        (Some(folder), _) => Some(PathBuf::from(folder)),
+        (None, Some(game_dir)) => Some(game_dir.join("mods")),
+        (None, None) => None,
+    };
+    let scan = match &from_folder {
+        Some(folder) if instance.is_none() || current_dir.join(folder).is_dir() => {
+            Some(scan_folder(&current_dir, &current_dir.join(folder), &client).await?)
+        }
+        _ => None,
+    };
+    let jar_count = scan.as_ref().map_or(0, |scan| scan.lock_mods.len());
+
+    // Values given as flags (or environment variables) are validated the same way as typed ones, but not confirmed.
+    let game_version = match (
+        &instance,
+        init_value(options, "--game-version", "MODMAN_GAME_VERSION"),
+    ) {
+        (Some(instance), _) => instance.game_version.clone(),
+        (None, Some(value)) => parse_game_version(&value, tags)
+            .map_err(|e| ModManError::InvalidCommandArguments(format!("--game-version: {}", e)))?,
+        (None, None) => match scan.as_ref().and_then(|scan| scan.vote_game_version(tags)) {
+            Some((detected, votes)) if use_detected("Version", &detected, votes, jar_count, yes) => {
+                detected
+            }
+            _ if yes => match tags.and_then(|tags| tags.latest_release()) {
+                Some(latest_release) => latest_release.to_string(),
+                None => {
+                    return Err(ModManError::InvalidCommandArguments(
+                        "'--game-version' (or MODMAN_GAME_VERSION) is required with '--yes', as the latest release is unknown.".to_string(),
+                    ))
+                }
+            },
+            _ => ask_game_version(tags)?,
+        },
+    };
+
+    let game_loader = match (&instance, init_value(options, "--loader", "MODMAN_LOADER")) {
+        (Some(instance), _) => instance.game_loader.clone(),
+        (None, Some(value)) => parse_game_loader(&value, tags)
+            .map_err(|e| ModManError::InvalidCommandArguments(format!("--loader: {}", e)))?,
+        (None, None) => match scan.as_ref().and_then(|scan| scan.vote_loader()) {
+            Some((detected, votes))
+                if use_detected("Loader", &detected.to_string(), votes, jar_count, yes) =>
+            {
+                detected
+            }
+            _ if yes => {
+                return Err(ModManError::InvalidCommandArguments(
+                    "'--loader' (or MODMAN_LOADER) is required with '--yes'.".to_string(),
+                ))
+            }
+            _ => ask_game_loader(tags)?,
+        },
+    };
+
+    let allowed_release_types = match init_value(options, "--release-types", "MODMAN_RELEASE_TYPES")
+    {
+        Some(value) => parse_release_types(&value)
+            .map_err(|e| ModManError::InvalidCommandArguments(format!("--release-types: {}", e)))?,
+        None if yes => parse_release_types(DEFAULT_RELEASE_TYPES)
+            .expect("Default release types are invalid! Please report this issue."),
+        None => ask_release_types()?,
+    };
+
+    let mods_folder = match (
+        &from_folder,
+        init_value(options, "--mods-folder", "MODMAN_MODS_FOLDER"),
+    ) {
+        (Some(folder), _) => folder.display().to_string(),
+        (None, Some(value)) => value,
+        (None, None) if yes => DEFAULT_MODS_FOLDER.to_string(),
+        (None, None) => ask_mods_folder()?,
+    };
+
+    info!("Version:", game_version.clone());
+    info!("Loader:", game_loader.to_string());
+    info!(
+        "Release types:",
+        crate::datatypes::format_release_types(&allowed_release_types)
+    );
+    info!("Mods folder:", mods_folder.clone());
+
+    confirm!(
+        "Saving configuration. These settings will be used when you run modman in this directory."
+    );
+
+    let mut config = Config {
+        game_loader,
+        loader_version: instance.and_then(|instance| instance.loader_version),
+        game_version,
+        allowed_release_types,
+        mods_folder: PathBuf::from(mods_folder),
+        resourcepacks_folder: match &instance_game_dir {
+            Some(game_dir) => game_dir.join("resourcepacks"),
+            None => default_resourcepacks_folder(),
+        },
+        shaderpacks_folder: match &instance_game_dir {
+            Some(game_dir) => game_dir.join("shaderpacks"),
+            None => default_shaderpacks_folder(),
+        },
+        datapacks_folder: default_datapacks_folder(),
+        downloads: DownloadSettings::default(),
+        side: Side::default(),
+        modpack: ModpackSettings::default(),
+        instance: instance_path.map(PathBuf::from),
+        mods: Vec::new(), // Empty mods array for now
+        profiles: BTreeMap::new(),
+    };
+
+    let scan = match scan {
+        Some(result) => result,
+        None => {
+            crate::config::save_config(&current_dir, &config)?;
+            return Ok(());
+        }
+    };
+
+    // Every jar is in the config. Jars of the same project are only added once, and reported by 'modman check'.
+    for lock_mod in &scan.lock_mods {
+        if !config.mods.iter().any(|m| m.id == lock_mod.id) {
+            config.mods.push(Mod {
+                source: lock_mod.source.clone(),
+                id: lock_mod.id.clone(),
+                name: lock_mod.name.clone(),
+                enabled: !lock_mod.disabled,
+                project_type: lock_mod.project_type.clone(),
+                side: None,
+            });
+        }
+    }
+    crate::config::save_config(&current_dir, &config)?;
+    crate::config::save_lockfile(&current_dir, &scan.lock_mods)?;
+
+    let identified = jar_count - scan.metadata_only.len() - scan.unidentified.len();
+    info!(format!(
+        "Added {} jar(s), {} of which were found on Modrinth or CurseForge.",
+        jar_count, identified
+    ));
+    for file_name in &scan.metadata_only {
+        info!(
+            "Not on Modrinth or CurseForge, added from its metadata:",
+            file_name.clone()
+        );
+    }
+    for file_name in &scan.unidentified {
+        alert!(format!(
+            "Could not identify '{}'. Added as a local mod.",
+            file_name
+        ));
+    }
+    if !scan.unidentified.is_empty() || !scan.metadata_only.is_empty() {
+        info!("Local mods are kept as they are, and are not updated by modman.");
+    }
+
+    Ok(())
+}
+
+// Offers a value inferred from the jars of '--from-folder', which is used without asking with '--yes'.
+fn use_detected(label: &str, value: &str, votes: usize, jar_count: usize, yes: bool) -> bool {
+    info!(
+        format!("{} supported by the most jars:", label),
+        format!("{} ({} of {})", value, votes, jar_count)
+    );
+    yes || confirm_input(value)
+}
+
+const DEFAULT_RELEASE_TYPES: &str = "alpha, beta, release";
+const DEFAULT_MODS_FOLDER: &str = "./mods";
+
+// Value of a flag, falling back to an environment variable. Empty values count as not given.
+fn init_value(options: &CommandOptions, flag: &str, env_var: &str) -> Option<String> {
+    let value = match options.flag_value(flag) {
+        Some(value) => value.to_string(),
+        None => std::env::var(env_var).ok()?,
+    };
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+// Boolean environment variables are set with '1', 'true' or 'yes'.
+fn env_flag(env_var: &str) -> bool {
+    std::env::var(env_var)
+        .map(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+fn parse_game_version(input: &str, tags: Option<&GameTags>) -> Result<String, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Please enter a Minecraft version.".to_string());
+    }
+    if let Some(tags) = tags {
+        tags.check_game_version(input)?;
+    }
+    Ok(input.to_string())
+}
+
+fn parse_game_loader(input: &str, tags: Option<&GameTags>) -> Result<GameLoader, String> {
+    let input = input.trim().to_lowercase();
+    let game_loader = GameLoader::from_str(&input).map_err(|_| {
+        format!(
+            "Invalid value '{}' detected. Please enter a valid Minecraft loader.{}",
+            input,
+            tags.map(|tags| tags.suggest_loaders(&input))
+                .unwrap_or_default()
+        )
+    })?;
+    if let Some(tags) = tags {
+        tags.check_loader(&game_loader)?;
+    }
+    Ok(game_loader)
+}
+
+fn parse_release_types(input: &str) -> Result<Vec<ReleaseTypes>, String> {
+    let mut release_types: Vec<ReleaseTypes> = Vec::new();
+    for value in input.split(',').map(|s| s.trim()) {
+        match value.to_lowercase().as_str() {
+            "release" => release_types.push(ReleaseTypes::Release),
+            "beta" => release_types.push(ReleaseTypes::Beta),
+            "alpha" => release_types.push(ReleaseTypes::Alpha),
+            _ => {
+                return Err(format!(
+                    "Invalid value '{}' detected. Please enter only 'release', 'beta', or 'alpha'.",
+                    value
+                ))
+            }
+        }
+    }
+
+    let unique_values: HashSet<_> = release_types.iter().collect();
+    if unique_values.len() != release_types.len() {
+        return Err(
+            "Repeated values detected. Please enter each release type only once.".to_string(),
+        );
+    }
+    Ok(release_types)
+}
+
+// Reads a line from stdin. Stops at the end of input, instead of asking again forever (e.g. when stdin isn't a terminal).
+fn read_input() -> Result<String, ModManError> {
+    let mut input = String::new();
+    if io::stdin()
+        .read_line(&mut input)
+        .map_err(ModManError::IoError)?
+        == 0
+    {
+        println!();
+        return Err(ModManError::InvalidCommandArguments(
+            "No input. Use '--yes' to run 'modman init' without asking anything.".to_string(),
+        ));
+    }
+    Ok(input)
+}
+
+fn print_invalid(message: &str) {
+    println!(" {} {}", "!".red().bold(), message);
+}
+
+// Ask user for version
+fn ask_game_version(tags: Option<&GameTags>) -> Result<String, ModManError> {
+    let latest_release = tags.and_then(|tags| tags.latest_release());
     loop {
-        request!("Version of Minecraft", "[Any valid Minecraft version]");
-        io::stdin().read_line(&mut game_version).unwrap();
-        game_version = game_version.trim().to_owned();
-
-        if confirm_input(&game_version) {
-            break;
-        } else {
-            game_version = String::new();
+        let hint = match latest_release {
+            Some(latest_release) => {
+                format!("[Default is the latest release, '{}']", latest_release)
+            }
+            None => "[Any valid Minecraft version]".to_string(),
+        };
+        request!("Version of Minecraft", hint);
+        let mut input = read_input()?.trim().to_owned();
+
+        if input.is_empty() {
+            input = latest_release.unwrap_or_default().to_string()
+        }
+
+        match parse_game_version(&input, tags) {
+            Ok(game_version) => {
+                if confirm_input(&game_version) {
+                    return Ok(game_version);
+                }
+            }
+            Err(e) => print_invalid(&e),
         }
     }
+}
 
-    // Ask user for game_loader
+// Ask user for game_loader
+fn ask_game_loader(tags: Option<&GameTags>) -> Result<GameLoader, ModManError> {
     loop {
         request!("Loader of Minecraft", "[fabric, quilt, forge, etc.]");
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        input = input.trim().to_owned().to_lowercase();
-
-        let mut is_valid: bool = true;
-        match GameLoader::from_str(&input) {
-            Ok(result) => {
-                game_loader = Some(result);
-            }
-            Err(_) => {
-                println!(
-                    " {} Invalid value '{}' detected. Please enter a valid Minecraft loader.",
-                    "!".red().bold(),
-                    input.bold()
-                );
-                is_valid = false;
+        let input = read_input()?;
+
+        match parse_game_loader(&input, tags) {
+            Ok(game_loader) => {
+                if confirm_input(&game_loader.to_string()) {
+                    return Ok(game_loader);
+                }
             }
-        };
-        if is_valid && confirm_input(&game_loader.clone().unwrap().to_string()) {
-            break;
+            Err(e) => print_invalid(&e),
         }
-        input.clear();
     }
+}
 
-    // Ask user for default allowed release types
+// Ask user for default allowed release types
+fn ask_release_types() -> Result<Vec<ReleaseTypes>, ModManError> {
     loop {
         request!(
             "Default Allowed Release Types (alpha, beta, release) (seperated by comma)",
             "[Default: 'alpha, beta, release']"
         );
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
+        let mut input = read_input()?;
         input = input.trim().to_owned();
 
         if input.is_empty() {
-            input = "alpha, beta, release".to_string()
+            input = DEFAULT_RELEASE_TYPES.to_string()
         }
 
-        let split_values: Vec<&str> = input.split(',').map(|s| s.trim()).collect();
-
-        let mut is_valid = true;
-        for value in split_values.iter() {
-            match value.to_lowercase().as_str() {
-                "release" => allowed_release_types.push(ReleaseTypes::Release),
-                "beta" => allowed_release_types.push(ReleaseTypes::Beta),
-                "alpha" => allowed_release_types.push(ReleaseTypes::Alpha),
-                _ => {
-                    println!(" {} Invalid value '{}' detected. Please enter only 'release', 'beta', or 'alpha'.", "!".red().bold(), value.bold());
-                    is_valid = false;
-                    allowed_release_types.clear();
-                    break;
+        match parse_release_types(&input) {
+            Ok(release_types) => {
+                if confirm_input(&crate::datatypes::format_release_types(&release_types)) {
+                    return Ok(release_types);
                 }
             }
-        }
-
-        if is_valid {
-            let unique_values: HashSet<_> = allowed_release_types.iter().collect();
-            if unique_values.len() != allowed_release_types.len() {
-                println!(
-                    " {} Repeated values detected. Please enter each release type only once.",
-                    "!".red().bold()
-                );
-                allowed_release_types.clear();
-            } else if confirm_input(&crate::datatypes::format_release_types(
-                &allowed_release_types,
-            )) {
-                break;
-            } else {
-                allowed_release_types.clear();
-            }
+            Err(e) => print_invalid(&e),
         }
     }
+}
 
-    // Ask user for mods folder
+// Ask user for mods folder
+fn ask_mods_folder() -> Result<String, ModManError> {
     loop {
         request!("Mods Folder", "[Default is './mods']");
-        io::stdin().read_line(&mut mods_folder).unwrap();
-        mods_folder = mods_folder.trim().to_owned();
+        let mut mods_folder = read_input()?.trim().to_owned();
 
         if mods_folder.is_empty() {
-            mods_folder = "./mods".to_string()
+            mods_folder = DEFAULT_MODS_FOLDER.to_string()
         }
 
         if confirm_input(&mods_folder) {
-            break;
-        } else {
-            mods_folder = String::new();
+            return Ok(mods_folder);
         }
     }
-
-    confirm!(
-        "Saving configuration. These settings will be used when you run modman in this directory."
-    );
-
-    let config = Config {
-        game_loader: game_loader.expect(
-            "Game Loader variable was empty somehow during init command! Please report this issue.",
-        ),
-        game_version,
-        allowed_release_types,
-        mods_folder: std::path::PathBuf::from(mods_folder),
-        mods: Vec::new(), // Empty mods array for now
-    };
-    crate::config::save_config(&current_dir, &config)?;
-
-    Ok(())
 }
 
 fn confirm_input(input: &str) -> bool {
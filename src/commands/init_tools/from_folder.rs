@@ -0,0 +1,292 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use colored::Colorize;
+use reqwest::Client;
+
+use crate::{
+    alert,
+    api::{
+        curseforge::{
+            convert_curseforge_to_lockmod, curseforge_api_key, curseforge_files_from_fingerprints,
+            curseforge_fingerprint, fetch_curseforge_mods,
+        },
+        modrinth::modrinth_mods_from_hashes,
+    },
+    config_sync::local_lock_mod,
+    datatypes::{GameLoader, LockMod, ProjectType, DISABLED_SUFFIX},
+    errors::ModManError,
+    hash_cache::HashCache,
+    info,
+    metadata::read_metadata,
+    tags::GameTags,
+};
+
+/*
+    Identifies the jars of an existing mods folder, for 'modman init --from-folder'.
+
+    Jars are looked up by their sha512 on Modrinth (all in one request), then the ones that aren't on Modrinth are looked up
+    by their fingerprint on CurseForge (if CURSEFORGE_API_KEY is set). Jars that are on neither are added as local mods,
+    filled in from the metadata in the jar if they have any.
+    The game version and loader are then inferred by majority vote across the jars.
+*/
+
+pub struct FolderScan {
+    pub lock_mods: Vec<LockMod>,
+    pub metadata_only: Vec<String>, // Not on Modrinth or CurseForge, but identified from their metadata.
+    pub unidentified: Vec<String>,  // Not on Modrinth or CurseForge, and no metadata.
+}
+
+// Loaders of shaders, resource packs and datapacks, which are never the game loader.
+const NON_GAME_LOADERS: &[GameLoader] = &[
+    GameLoader::Canvas,
+    GameLoader::Datapack,
+    GameLoader::Iris,
+    GameLoader::Minecraft,
+    GameLoader::OptiFine,
+    GameLoader::Vanilla,
+];
+
+pub async fn scan_folder(
+    current_dir: &Path,
+    folder_path: &Path,
+    client: &Client,
+) -> Result<FolderScan, ModManError> {
+    if !folder_path.is_dir() {
+        return Err(ModManError::InvalidCommandArguments(format!(
+            "'{}' is not a folder.",
+            folder_path.display()
+        )));
+    }
+
+    let mut jars: Vec<(String, bool)> = Vec::new(); // filename (without DISABLED_SUFFIX), disabled
+    for entry in fs::read_dir(folder_path).map_err(ModManError::IoError)? {
+        let entry = entry.map_err(ModManError::IoError)?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if !entry.path().is_file() {
+            continue;
+        }
+        if file_name.ends_with(".jar") {
+            jars.push((file_name, false));
+        } else if let Some(enabled_name) = file_name.strip_suffix(DISABLED_SUFFIX) {
+            if enabled_name.ends_with(".jar") && !folder_path.join(enabled_name).is_file() {
+                jars.push((enabled_name.to_string(), true));
+            }
+        }
+    }
+    jars.sort();
+    let paths: Vec<_> = jars
+        .iter()
+        .map(|(file_name, disabled)| {
+            if *disabled {
+                folder_path.join(format!("{}{}", file_name, DISABLED_SUFFIX))
+            } else {
+                folder_path.join(file_name)
+            }
+        })
+        .collect();
+
+    info!(format!("Hashing {} jar(s)...", jars.len()));
+    let mut hash_cache = HashCache::load(current_dir);
+    let checksums = hash_cache
+        .hash_files(&paths)
+        .map_err(ModManError::IoError)?;
+    hash_cache.save(current_dir)?;
+
+    info!("Looking up jars on Modrinth...");
+    let modrinth_mods = match modrinth_mods_from_hashes(client, &checksums).await {
+        Ok(result) => result,
+        Err(e) => {
+            alert!(format!(
+                "Could not look up jars on Modrinth ({}). Only their metadata will be used.",
+                e
+            ));
+            HashMap::new()
+        }
+    };
+
+    // Jars which are not on Modrinth are looked up on CurseForge by their fingerprint.
+    let mut curseforge_fingerprints: HashMap<&PathBuf, u32> = HashMap::new();
+    let mut curseforge_mods: HashMap<u32, LockMod> = HashMap::new();
+    if curseforge_api_key().is_some() {
+        for (path, checksum) in paths.iter().zip(&checksums) {
+            if !modrinth_mods.contains_key(checksum) {
+                let bytes = fs::read(path).map_err(ModManError::IoError)?;
+                curseforge_fingerprints.insert(path, curseforge_fingerprint(&bytes));
+            }
+        }
+    }
+    if !curseforge_fingerprints.is_empty() {
+        info!("Looking up jars on CurseForge...");
+        let mut fingerprints: Vec<u32> = curseforge_fingerprints.values().copied().collect();
+        fingerprints.sort();
+        fingerprints.dedup();
+        match curseforge_mods_from_fingerprints(client, &fingerprints).await {
+            Ok(result) => curseforge_mods = result,
+            Err(e) => alert!(format!(
+                "Could not look up jars on CurseForge ({}). Only their metadata will be used.",
+                e
+            )),
+        }
+    }
+
+    let mut scan = FolderScan {
+        lock_mods: Vec::new(),
+        metadata_only: Vec::new(),
+        unidentified: Vec::new(),
+    };
+    for (((file_name, disabled), path), checksum) in jars.iter().zip(&paths).zip(&checksums) {
+        // Jars with the same contents are found by the same hash, so lookups are cloned rather than taken.
+        let found = match modrinth_mods.get(checksum) {
+            Some(result) => Some(result.clone()),
+            None => curseforge_fingerprints
+                .get(path)
+                .and_then(|fingerprint| curseforge_mods.get(fingerprint))
+                .cloned(),
+        };
+        let lock_mod = match found {
+            Some(mut result) => {
+                // Files keep their names, so they don't have to be renamed.
+                result.file_name = file_name.clone();
+                result.sha512 = checksum.clone(); // CurseForge doesn't give the sha512.
+                result.disabled = *disabled;
+                result.project_type = ProjectType::Mod;
+                result
+            }
+            None => {
+                let metadata = read_metadata(path);
+                match metadata {
+                    Some(_) => scan.metadata_only.push(file_name.clone()),
+                    None => scan.unidentified.push(file_name.clone()),
+                }
+                local_lock_mod(
+                    path,
+                    file_name,
+                    checksum,
+                    *disabled,
+                    ProjectType::Mod,
+                    metadata,
+                    |id| scan.lock_mods.iter().any(|m| m.id == id),
+                )?
+            }
+        };
+        scan.lock_mods.push(lock_mod);
+    }
+    Ok(scan)
+}
+
+// Lockfile entries of the files found on CurseForge, keyed by fingerprint. Files which can only be downloaded from
+// CurseForge's website are left out, so they are added as local mods.
+async fn curseforge_mods_from_fingerprints(
+    client: &Client,
+    fingerprints: &[u32],
+) -> Result<HashMap<u32, LockMod>, Box<dyn std::error::Error + Send + Sync>> {
+    let files = curseforge_files_from_fingerprints(client, fingerprints).await?;
+    if files.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut mod_ids: Vec<u64> = files.values().map(|file| file.mod_id).collect();
+    mod_ids.sort();
+    mod_ids.dedup();
+    let projects = fetch_curseforge_mods(client, &mod_ids).await?;
+    Ok(files
+        .into_iter()
+        .filter(|(_, file)| file.download_url.is_some())
+        .filter_map(|(fingerprint, file)| {
+            let project = projects.iter().find(|p| p.id == file.mod_id)?;
+            Some((fingerprint, convert_curseforge_to_lockmod(&file, project)))
+        })
+        .collect())
+}
+
+impl FolderScan {
+    // The loader supported by the most jars, and its number of votes.
+    pub fn vote_loader(&self) -> Option<(GameLoader, usize)> {
+        let mut votes: BTreeMap<String, (GameLoader, usize)> = BTreeMap::new();
+        for lock_mod in &self.lock_mods {
+            for loader in &lock_mod.loaders {
+                if NON_GAME_LOADERS.contains(loader) {
+                    continue;
+                }
+                votes
+                    .entry(loader.to_string())
+                    .or_insert_with(|| (loader.clone(), 0))
+                    .1 += 1;
+            }
+        }
+        // Ties go to the first loader alphabetically (e.g. fabric over quilt, forge over neoforge).
+        votes.into_values().fold(
+            None,
+            |best: Option<(GameLoader, usize)>, (loader, count)| match best {
+                Some(best) if best.1 >= count => Some(best),
+                _ => Some((loader, count)),
+            },
+        )
+    }
+
+    // The game version supported by the most jars, and its number of votes.
+    // Only exact versions are counted, not ranges (e.g. '>=1.20' in a fabric.mod.json).
+    pub fn vote_game_version(&self, tags: Option<&GameTags>) -> Option<(String, usize)> {
+        let mut votes: BTreeMap<&str, usize> = BTreeMap::new();
+        for lock_mod in &self.lock_mods {
+            // Each jar votes once per version.
+            let game_versions: BTreeSet<&str> = lock_mod
+                .game_versions
+                .iter()
+                .map(|v| v.trim_start_matches('='))
+                .collect();
+            for game_version in game_versions {
+                let exact = match tags {
+                    Some(tags) => tags.game_versions.iter().any(|v| v.version == game_version),
+                    None => {
+                        game_version.starts_with(|c: char| c.is_ascii_digit())
+                            && game_version.chars().all(|c| c.is_ascii_digit() || c == '.')
+                    }
+                };
+                if exact {
+                    *votes.entry(game_version).or_default() += 1;
+                }
+            }
+        }
+        // Ties go to full releases over snapshots, then to the newest version.
+        votes
+            .into_iter()
+            .max_by(|a, b| {
+                a.1.cmp(&b.1)
+                    .then_with(|| is_release(tags, a.0).cmp(&is_release(tags, b.0)))
+                    .then_with(|| compare_versions(tags, a.0, b.0))
+            })
+            .map(|(game_version, count)| (game_version.to_string(), count))
+    }
+}
+
+fn is_release(tags: Option<&GameTags>, game_version: &str) -> bool {
+    match tags {
+        Some(tags) => tags
+            .game_versions
+            .iter()
+            .any(|v| v.version == game_version && v.version_type == "release"),
+        None => true,
+    }
+}
+
+// Orders versions oldest to newest. Modrinth's list is newest first, otherwise the numbers are compared.
+fn compare_versions(tags: Option<&GameTags>, a: &str, b: &str) -> Ordering {
+    match tags {
+        Some(tags) => {
+            let position =
+                |version: &str| tags.game_versions.iter().position(|v| v.version == version);
+            position(b).cmp(&position(a))
+        }
+        None => {
+            let numbers = |version: &str| -> Vec<u32> {
+                version.split('.').filter_map(|n| n.parse().ok()).collect()
+            };
+            numbers(a).cmp(&numbers(b))
+        }
+    }
+}
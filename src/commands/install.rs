@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use colored::Colorize;
+use reqwest::Client;
+
+use crate::{
+    actionheader, alert,
+    config::{read_config_with_alerts, read_lockfile},
+    confirm,
+    datatypes::{LockMod, ModSources, Side},
+    errors::ModManError,
+    info,
+    install::verify_file_hashes,
+    transactions::{check_incomplete_transactions, Transaction},
+    utils::{calculate_total_size, convert_lock_mods_to_downloads},
+    APP_USER_AGENT,
+};
+
+pub async fn command_install() -> Result<(), ModManError> {
+    /*
+        Installs exactly what the lockfile (modman.lock) says, without resolving anything again.
+        Meant for servers and CI, where the lockfile is checked in and the mods folder is not.
+
+        Step-by-Step Workflow:
+            1. READ: Read config and lockfile.
+            2. COMPARE: For every lockfile entry, check whether its file is installed and matches the checksum.
+                Mods for this side (see 'side' in modman.toml) that are missing or modified are downloaded.
+                Mods for the other side that are installed are removed.
+            3. TRANSACTION: Download and remove the files as a transaction.
+
+        Local mods can't be downloaded, so missing ones are reported instead.
+    */
+
+    let current_directory = match crate::utils::get_current_working_dir() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::IoError(e)),
+    };
+
+    check_incomplete_transactions(&current_directory)?;
+
+    // (1) Read config and lockfile.
+    let config = read_config_with_alerts(&current_directory)?;
+    let lockfile: Vec<LockMod> = match read_lockfile(&current_directory) {
+        Ok(result) => result,
+        Err(ModManError::FileNotFound) | Err(ModManError::FileIsEmpty) => {
+            alert!("The lockfile (modman.lock) is missing or empty. Run 'modman add' or 'modman sync' to create it.");
+            return Err(ModManError::NoMods("install".to_owned()));
+        }
+        Err(e) => return Err(e),
+    };
+
+    // (2) Compare the lockfile to the installed files.
+    actionheader!("Install");
+    let mut to_download: Vec<LockMod> = Vec::new();
+    let mut to_remove: Vec<&LockMod> = Vec::new();
+    let mut missing_local: Vec<&LockMod> = Vec::new();
+    for lock_mod in &lockfile {
+        let installed_path = config.installed_path(&current_directory, lock_mod);
+        let installed = installed_path.is_file();
+        if !config.wants(lock_mod) {
+            if installed {
+                to_remove.push(lock_mod);
+            }
+            continue;
+        }
+        if installed
+            && verify_file_hashes(&installed_path, &lock_mod.sha512, lock_mod.sha1.as_deref())
+                .map_err(ModManError::IoError)?
+        {
+            continue;
+        }
+        if lock_mod.source == ModSources::Local {
+            missing_local.push(lock_mod);
+        } else {
+            to_download.push(lock_mod.clone());
+        }
+    }
+
+    for lock_mod in &missing_local {
+        alert!(format!(
+            "'{}' is a local mod, and is missing or modified. Copy it to '{}' by hand.",
+            lock_mod.name,
+            config
+                .installed_path(&current_directory, lock_mod)
+                .display()
+        ));
+    }
+    if to_download.is_empty() && to_remove.is_empty() {
+        if !missing_local.is_empty() {
+            return Err(ModManError::DownloadsFailed(missing_local.len()));
+        }
+        let wanted = lockfile.iter().filter(|m| config.wants(m)).count();
+        match config.side {
+            Side::Both => confirm!(format!("All {} mod(s) are installed.", wanted)),
+            _ => confirm!(format!(
+                "All {} mod(s) for the {} side are installed.",
+                wanted, config.side
+            )),
+        }
+        return Ok(());
+    }
+
+    for lock_mod in &to_download {
+        info!("Install:", lock_mod.name.clone());
+    }
+    for lock_mod in &to_remove {
+        info!(
+            format!("Remove (not for the {} side):", config.side),
+            lock_mod.name.clone()
+        );
+    }
+    if !to_download.is_empty() {
+        info!("Total download size: ", calculate_total_size(&to_download));
+    }
+
+    // (3) Download and remove the files as a transaction.
+    println!();
+    actionheader!("Transaction");
+    let client = Arc::new(match Client::builder().user_agent(APP_USER_AGENT).build() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::ReqwestError(e)),
+    });
+    let mut transaction = Transaction::begin(&current_directory, "install")?;
+    for download in convert_lock_mods_to_downloads(&current_directory, &config, to_download, &[]) {
+        transaction.plan_download(&current_directory, download)?;
+    }
+    for lock_mod in &to_remove {
+        transaction.plan_delete(
+            &current_directory,
+            config.installed_path(&current_directory, lock_mod),
+        )?;
+    }
+    if let Err(e) = transaction.run(&current_directory, &client).await {
+        alert!("Transaction failed. Run 'modman transactions' to resume or roll it back.");
+        return Err(e);
+    }
+
+    if !missing_local.is_empty() {
+        alert!("Transaction finished, but some local mods are still missing.");
+        return Err(ModManError::DownloadsFailed(missing_local.len()));
+    }
+    confirm!("Transaction finished. All mods are installed.");
+    Ok(())
+}
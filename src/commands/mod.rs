@@ -1,7 +1,18 @@
 pub mod add;
 pub mod add_tools;
+pub mod check;
 pub mod command_handler;
 pub mod command_structs;
+pub mod export;
+pub mod graph;
+pub mod import;
 pub mod init;
+pub mod init_tools;
+pub mod install;
 pub mod sync;
+pub mod toggle;
+pub mod transactions;
+pub mod upgrade;
+pub mod verify;
 pub mod version;
+pub mod why;
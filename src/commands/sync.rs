@@ -1,7 +1,108 @@
-use crate::errors::ModManError;
+use std::sync::Arc;
+
+use colored::Colorize;
+use reqwest::Client;
+
+use crate::{
+    alert,
+    config::{read_config, read_lockfile},
+    config_sync::sync_files,
+    confirm,
+    conflicts::find_conflicts,
+    datatypes::ModSources,
+    errors::ModManError,
+    info,
+    transactions::check_incomplete_transactions,
+    APP_USER_AGENT,
+};
 
 use super::command_structs::CommandOptions;
 
 pub async fn command_sync(options: &CommandOptions) -> Result<(), ModManError> {
-    todo!()
+    /*
+        The flags are as follows for 'sync' command:
+        --keep-names    - Update the lockfile to the new name of renamed files, instead of renaming them back.
+
+        Synchronizes the mods folder, lockfile and config file. Mods that need to be (re)installed are reported,
+        with the 'modman add' command which installs them.
+    */
+
+    let keep_file_names = options.flags.contains(&"--keep-names".to_string());
+
+    let current_directory = match crate::utils::get_current_working_dir() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::IoError(e)),
+    };
+
+    check_incomplete_transactions(&current_directory)?;
+
+    let client = Arc::new(match Client::builder().user_agent(APP_USER_AGENT).build() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::ReqwestError(e)),
+    });
+
+    info!("Syncing files...");
+    let sync_results = sync_files(&current_directory, &client, keep_file_names).await?;
+    sync_results.print_renamed();
+    sync_results.print_toggled();
+
+    for dependency in &sync_results.missing_dependencies {
+        info!("Missing dependency:", dependency.project_id.clone());
+    }
+    for new_mod in &sync_results.new_mods {
+        info!("Not installed yet:", new_mod.name.clone());
+    }
+    for lock_mod in &sync_results.to_reinstall_bad_checksum {
+        info!("Mismatched checksum:", lock_mod.file_name.clone());
+    }
+    // 'modman add' installs these (along with the mods it is given), as it syncs first. It can't add CurseForge mods yet.
+    let needed: Vec<(&ModSources, &String)> = sync_results
+        .missing_dependencies
+        .iter()
+        .map(|d| (&d.source, &d.project_id))
+        .chain(sync_results.new_mods.iter().map(|m| (&m.source, &m.id)))
+        .chain(
+            sync_results
+                .to_reinstall_bad_checksum
+                .iter()
+                .map(|m| (&m.source, &m.id)),
+        )
+        .collect();
+    let mut to_add: Vec<String> = needed
+        .iter()
+        .filter(|(source, _)| **source == ModSources::Modrinth)
+        .map(|(_, id)| id.to_string())
+        .collect();
+    let curseforge_count = needed
+        .iter()
+        .filter(|(source, _)| **source == ModSources::CurseForge)
+        .count();
+    if curseforge_count > 0 {
+        alert!(format!(
+            "{} CurseForge mod(s) can't be installed by 'modman add' yet. Download them from CurseForge by hand.",
+            curseforge_count
+        ));
+    }
+    to_add.sort();
+    to_add.dedup();
+    if !to_add.is_empty() {
+        info!(
+            "Run this to install them:",
+            format!("modman add {}", to_add.join(" "))
+        );
+    }
+
+    // Conflicting jars are only reported here. They are resolved with 'modman check --fix'.
+    let config = read_config(&current_directory)?;
+    let lockfile = read_lockfile(&current_directory).unwrap_or_default();
+    let conflicts = find_conflicts(&current_directory, &config, &lockfile)?;
+    for conflict in &conflicts {
+        alert!(conflict.to_string());
+    }
+    if !conflicts.is_empty() {
+        info!("Run 'modman check --fix' to resolve these conflicts.");
+    }
+
+    confirm!("Files synced.");
+    Ok(())
 }
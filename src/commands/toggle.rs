@@ -0,0 +1,119 @@
+use colored::Colorize;
+
+use crate::{
+    actionheader, alert,
+    commands::{command_structs::CommandOptions, why::find_lock_mod},
+    config::{read_config_with_alerts, read_lockfile},
+    confirm,
+    datatypes::{DependencyType, LockMod},
+    errors::ModManError,
+    info,
+    transactions::{check_incomplete_transactions, Transaction},
+};
+
+pub fn command_disable(options: &CommandOptions) -> Result<(), ModManError> {
+    /*
+        The arguments are as follows for 'disable' command:
+        <package_slug / package_ID / name / file name>  - The mod in the lockfile to disable.
+
+        Renames the mod's file to '<file>.jar.disabled' (the same way launchers like Prism and MultiMC do),
+        so the game does not load it, but it stays installed and in the lockfile.
+    */
+    set_mod_enabled(options, false)
+}
+
+pub fn command_enable(options: &CommandOptions) -> Result<(), ModManError> {
+    /*
+        The arguments are as follows for 'enable' command:
+        <package_slug / package_ID / name / file name>  - The mod in the lockfile to enable.
+
+        Renames a disabled mod's file from '<file>.jar.disabled' back to '<file>.jar'.
+    */
+    set_mod_enabled(options, true)
+}
+
+fn set_mod_enabled(options: &CommandOptions, enabled: bool) -> Result<(), ModManError> {
+    let query = match options.parameters.first() {
+        Some(result) => result,
+        None => return Err(ModManError::NoArguments),
+    };
+
+    let current_directory = match crate::utils::get_current_working_dir() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::IoError(e)),
+    };
+
+    check_incomplete_transactions(&current_directory)?;
+
+    let mut config = read_config_with_alerts(&current_directory)?;
+    let mut lockfile: Vec<LockMod> = match read_lockfile(&current_directory) {
+        Ok(result) => result,
+        Err(ModManError::FileNotFound) => Vec::new(),
+        Err(ModManError::FileIsEmpty) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+
+    let target = match find_lock_mod(&lockfile, query) {
+        Some(result) => result.clone(),
+        None => {
+            alert!(format!("'{}' is not in the lockfile (modman.lock).", query));
+            return Err(ModManError::CannotFindMod(query.to_string()));
+        }
+    };
+    let action = if enabled { "enabled" } else { "disabled" };
+
+    if target.disabled != enabled {
+        confirm!(format!("'{}' is already {}.", target.name, action));
+        return Ok(());
+    }
+
+    let from = config.installed_path(&current_directory, &target);
+    if !from.is_file() {
+        alert!(format!(
+            "'{}' is missing. Run 'modman sync' first.",
+            from.display()
+        ));
+        return Err(ModManError::FileNotFound);
+    }
+
+    // Mods which need this mod will not work without it.
+    if !enabled {
+        for dependent in lockfile.iter().filter(|m| {
+            !m.disabled
+                && m.dependencies.iter().any(|dep| {
+                    dep.project_id == target.id && dep.dependency_type == DependencyType::Required
+                })
+        }) {
+            alert!(format!("'{}' requires '{}'.", dependent.name, target.name));
+        }
+    }
+
+    for lock_mod in lockfile.iter_mut().filter(|m| m.id == target.id) {
+        lock_mod.disabled = !enabled;
+    }
+    for config_mod in config.mods.iter_mut().filter(|m| m.id == target.id) {
+        config_mod.enabled = enabled;
+    }
+    let mut updated_mod = target.clone();
+    updated_mod.disabled = !enabled;
+
+    actionheader!("Transaction");
+    let mut transaction = Transaction::begin(
+        &current_directory,
+        if enabled { "enable" } else { "disable" },
+    )?;
+    transaction.plan_rename(
+        from,
+        config.installed_path(&current_directory, &updated_mod),
+    );
+    transaction.plan_write_lockfile(&current_directory, &lockfile)?;
+    transaction.plan_write_config(&current_directory, &config)?;
+    if let Err(e) = transaction.finish(&current_directory) {
+        alert!("Transaction failed. Run 'modman transactions' to resume or roll it back.");
+        return Err(e);
+    }
+
+    info!("Renamed:", updated_mod.installed_file_name());
+    confirm!(format!("'{}' is now {}.", target.name, action));
+    Ok(())
+}
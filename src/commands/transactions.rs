@@ -0,0 +1,151 @@
+use std::{
+    io::{self, Write},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use colored::Colorize;
+use reqwest::Client;
+
+use crate::{
+    actionheader, alert,
+    commands::command_structs::CommandOptions,
+    confirm,
+    errors::ModManError,
+    info, request,
+    transactions::{list_incomplete_transactions, remove_unjournaled_transactions, Transaction},
+    APP_USER_AGENT,
+};
+
+pub async fn command_transactions(options: &CommandOptions) -> Result<(), ModManError> {
+    /*
+        The arguments are as follows for 'transactions' command:
+        (none) / list               - List incomplete transactions, and their steps.
+        resume <transaction ID>     - Run the remaining steps of an incomplete transaction.
+        rollback <transaction ID>   - Undo every step of an incomplete transaction.
+
+        The transaction ID can be left out if there is only one incomplete transaction.
+        Transactions which were stopped before their journal was written changed nothing, and are removed.
+    */
+
+    let current_directory = match crate::utils::get_current_working_dir() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::IoError(e)),
+    };
+
+    let removed = remove_unjournaled_transactions(&current_directory)?;
+    if removed > 0 {
+        info!(format!(
+            "Removed {} transaction(s) which were stopped before changing anything.",
+            removed
+        ));
+    }
+    let transactions = list_incomplete_transactions(&current_directory)?;
+
+    let action = options
+        .parameters
+        .first()
+        .map(|action| action.to_lowercase())
+        .unwrap_or("list".to_string());
+
+    if action == "list" {
+        if transactions.is_empty() {
+            confirm!("No incomplete transactions.");
+            return Ok(());
+        }
+        actionheader!("Incomplete Transactions");
+        for transaction in &transactions {
+            print_transaction(transaction);
+        }
+        info!("Run 'modman transactions resume <ID>' or 'modman transactions rollback <ID>'.");
+        return Ok(());
+    }
+
+    let mut transaction = match options.parameters.get(1) {
+        Some(id) => match transactions.into_iter().find(|t| &t.id == id) {
+            Some(result) => result,
+            None => {
+                return Err(ModManError::InvalidCommandArguments(format!(
+                    "No incomplete transaction with ID '{}'",
+                    id
+                )))
+            }
+        },
+        None => {
+            if transactions.len() > 1 {
+                alert!("There is more than one incomplete transaction. Please specify a transaction ID.");
+                return Err(ModManError::NoArguments);
+            }
+            match transactions.into_iter().next() {
+                Some(result) => result,
+                None => {
+                    confirm!("No incomplete transactions.");
+                    return Ok(());
+                }
+            }
+        }
+    };
+
+    print_transaction(&transaction);
+
+    match action.as_str() {
+        "resume" => {
+            request!("Resume this transaction?", "[Y/n]");
+            if !read_confirmation() {
+                confirm!("Cancelled. Exiting...");
+                return Ok(());
+            }
+            let client = Arc::new(match Client::builder().user_agent(APP_USER_AGENT).build() {
+                Ok(result) => result,
+                Err(e) => return Err(ModManError::ReqwestError(e)),
+            });
+            transaction.run(&current_directory, &client).await?;
+            confirm!("Transaction resumed and finished.");
+        }
+        "rollback" => {
+            request!("Roll back this transaction?", "[Y/n]");
+            if !read_confirmation() {
+                confirm!("Cancelled. Exiting...");
+                return Ok(());
+            }
+            transaction.rollback(&current_directory)?;
+            confirm!("Transaction rolled back.");
+        }
+        _ => return Err(ModManError::InvalidCommandArguments(action)),
+    }
+
+    Ok(())
+}
+
+fn print_transaction(transaction: &Transaction) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let done = transaction.steps.iter().filter(|step| step.done).count();
+    info!(
+        format!("Transaction {}:", transaction.id),
+        format!(
+            "'modman {}', started {} min ago, {}/{} steps done",
+            transaction.command,
+            now.saturating_sub(transaction.started) / 60,
+            done,
+            transaction.steps.len()
+        )
+    );
+    for step in &transaction.steps {
+        let status = if step.done {
+            "done".green()
+        } else {
+            "pending".yellow()
+        };
+        println!("    [{}] {}", status, step.action);
+    }
+}
+
+fn read_confirmation() -> bool {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let input = input.trim().to_lowercase();
+    input != "n" && input != "no"
+}
@@ -0,0 +1,523 @@
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use colored::Colorize;
+use reqwest::Client;
+
+use crate::{
+    actionheader, alert,
+    api::modrinth::fetch_modrinth_mod,
+    commands::{add_tools::dependencies::handle_dependencies, command_structs::CommandOptions},
+    config::{read_config_with_alerts, read_lockfile},
+    confirm,
+    datatypes::{DependencyType, GameLoader, LockMod, ModSources},
+    errors::ModManError,
+    info,
+    instance::{is_instance, read_instance, synced_pack, Instance, PACK_FILE_NAME},
+    request,
+    tags::GameTags,
+    transactions::{check_incomplete_transactions, Transaction},
+    utils::{calculate_total_size, convert_lock_mods_to_downloads},
+    APP_USER_AGENT,
+};
+
+pub async fn command_upgrade(options: &CommandOptions) -> Result<(), ModManError> {
+    /*
+        The arguments are as follows for 'upgrade' command:
+        <game version>                  - The Minecraft version to upgrade to.
+
+        Flags:
+        --loader-version <version>      - Also change the version of the loader. Forge and NeoForge versions are made for
+                                            one Minecraft version, so these usually need it. Required if 'instance' is
+                                            set in modman.toml and the instance has a loader, as it is kept in mmc-pack.json.
+        --force                         - Upgrade even if some mods have no version for the new game version.
+                                            They are kept at their current version.
+
+        Step-by-Step Workflow:
+            0. READ TRANSACTIONS: If there was a failed transaction (or more), error out and request user to go to `modman transactions`.
+            1. READ: Read config and lockfile. The game version is checked against Modrinth's list of versions.
+            2. FETCH: Fetch the version of every Modrinth mod in the lockfile for the new game version, and any new required
+                dependencies. CurseForge and local mods are kept as they are. Dependencies of the old versions which
+                nothing needs anymore are removed.
+                If some mods have no version for the new game version, they are listed, and nothing is changed (unless '--force').
+            3. TRANSACTION: Download the new versions (replacing the old ones) and delete the removed dependencies, then write
+                the lockfile, modman.toml (with the new game version) and the instance's mmc-pack.json (if 'instance' is set
+                in modman.toml), as a transaction.
+    */
+
+    let game_version = match options.parameters.first() {
+        Some(result) => result.trim().to_string(),
+        None => return Err(ModManError::NoArguments),
+    };
+    let loader_version = options.flag_value("--loader-version").map(str::to_string);
+    let force = options.flags.contains(&"--force".to_string());
+
+    let current_directory = match crate::utils::get_current_working_dir() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::IoError(e)),
+    };
+
+    // (0) Read transactions
+    check_incomplete_transactions(&current_directory)?;
+
+    // (1) Read config and lockfile
+    let mut config = read_config_with_alerts(&current_directory)?;
+    let current_lockfile: Vec<LockMod> = match read_lockfile(&current_directory) {
+        Ok(result) => result,
+        Err(ModManError::FileNotFound) | Err(ModManError::FileIsEmpty) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    if game_version == config.game_version
+        && (loader_version.is_none() || loader_version == config.loader_version)
+    {
+        confirm!(format!("Already on Minecraft {}.", game_version));
+        return Ok(());
+    }
+
+    let instance_path = config
+        .instance
+        .as_ref()
+        .map(|path| current_directory.join(path));
+    if let Some(path) = &instance_path {
+        if !is_instance(path) {
+            return Err(ModManError::InvalidCommandArguments(format!(
+                "'instance' in modman.toml is not a Prism Launcher or MultiMC instance: '{}'",
+                path.display()
+            )));
+        }
+        check_instance_loader(
+            &read_instance(path)?,
+            &config.game_version,
+            &game_version,
+            loader_version.as_deref(),
+        )?;
+    }
+
+    let client = Arc::new(match Client::builder().user_agent(APP_USER_AGENT).build() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::ReqwestError(e)),
+    });
+    if let Some(tags) = GameTags::load(&client).await {
+        tags.check_game_version(&game_version)
+            .map_err(ModManError::InvalidCommandArguments)?;
+    }
+
+    actionheader!("Upgrade");
+    info!(
+        "Game version:",
+        format!("{} -> {}", config.game_version, game_version)
+    );
+    if let Some(loader_version) = &loader_version {
+        let current = config.loader_version.as_deref().unwrap_or("Unknown");
+        info!(
+            "Loader version:",
+            format!("{} -> {}", current, loader_version)
+        );
+    } else if matches!(config.game_loader, GameLoader::Forge | GameLoader::NeoForge)
+        && config.loader_version.is_some()
+    {
+        alert!(format!(
+            "{} versions are made for one Minecraft version. Pass '--loader-version <version>' to change it too.",
+            config.game_loader
+        ));
+    }
+
+    // (2) Fetch the new versions of the Modrinth mods.
+    let modrinth_mods: Vec<&LockMod> = current_lockfile
+        .iter()
+        .filter(|m| m.source == ModSources::Modrinth)
+        .collect();
+    let mut tasks = Vec::new();
+    for lock_mod in &modrinth_mods {
+        let client = Arc::clone(&client);
+        let id = lock_mod.id.clone();
+        let game_version = game_version.clone();
+        let game_loader = config.game_loader.clone();
+        let project_type = lock_mod.project_type.clone();
+        tasks.push(tokio::spawn(async move {
+            fetch_modrinth_mod(
+                &client,
+                &id,
+                &game_version,
+                &game_loader,
+                Some(project_type),
+            )
+            .await
+            .map_err(|e| e.to_string())
+        }));
+    }
+    let results = futures::future::join_all(tasks).await;
+
+    let mut upgraded: Vec<LockMod> = Vec::new();
+    let mut not_upgradable: usize = 0;
+    for (lock_mod, result) in modrinth_mods.into_iter().zip(results) {
+        match result {
+            Ok(Ok(new_mod)) => {
+                if new_mod.sha512 != lock_mod.sha512 {
+                    info!(
+                        format!("{}:", lock_mod.name),
+                        format!("{} -> {}", lock_mod.version, new_mod.version)
+                    );
+                }
+                upgraded.push(new_mod);
+            }
+            Ok(Err(e)) => {
+                alert!(format!(
+                    "'{}' can't be upgraded to {}: {}",
+                    lock_mod.name, game_version, e
+                ));
+                not_upgradable += 1;
+            }
+            Err(join_error) => {
+                return Err(ModManError::APIFetchError(format!(
+                    "Task failed: {:?}",
+                    join_error
+                )))
+            }
+        }
+    }
+    for lock_mod in current_lockfile
+        .iter()
+        .filter(|m| m.source != ModSources::Modrinth)
+    {
+        info!(
+            format!("Kept as it is ({}):", lock_mod.source),
+            lock_mod.name.clone()
+        );
+    }
+    if not_upgradable > 0 && !force {
+        info!("Remove them, or pass '--force' to keep them at their current versions.");
+        return Err(ModManError::NotUpgradable(not_upgradable));
+    }
+
+    // New versions may need mods which are not in the lockfile yet, and no longer need others.
+    let mut new_lockfile = upgraded_lockfile(&current_lockfile, &upgraded);
+    for dependencies in upgraded.iter().map(|m| m.dependencies.clone()) {
+        if let Err(e) = handle_dependencies(
+            &client,
+            &mut new_lockfile,
+            &dependencies,
+            &game_version,
+            &config.game_loader,
+        )
+        .await
+        {
+            alert!(format!("Cannot find dependency: '{}'", e));
+        }
+    }
+    for lock_mod in &new_lockfile {
+        if !current_lockfile.iter().any(|m| m.id == lock_mod.id) {
+            info!("New dependency:", lock_mod.name.clone());
+        }
+    }
+    let explicit_ids: Vec<&str> = config.mods.iter().map(|m| m.id.as_str()).collect();
+    let unneeded = prune_dependencies(&mut new_lockfile, &current_lockfile, &explicit_ids);
+    for lock_mod in &unneeded {
+        info!("No longer needed:", lock_mod.name.clone());
+    }
+
+    // Only changed files are downloaded. Mods for the other side (see 'side' in modman.toml) are only locked.
+    let to_download: Vec<LockMod> = new_lockfile
+        .iter()
+        .filter(|m| {
+            config.wants(m)
+                && !current_lockfile
+                    .iter()
+                    .any(|c| c.id == m.id && c.sha512 == m.sha512)
+        })
+        .cloned()
+        .collect();
+
+    println!();
+    actionheader!("Upgrade Transaction");
+    info!("Files to download:", to_download.len().to_string());
+    info!("Total download size: ", calculate_total_size(&to_download));
+    if let Some(path) = &instance_path {
+        info!("Instance to sync:", path.display().to_string());
+    }
+    request!("Begin download/transaction?", "[Y/n]");
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    input = input.trim().to_owned();
+    if input == "n" || input == "no" {
+        confirm!("Cancelled transaction. Exiting...");
+        return Ok(());
+    }
+    println!();
+    actionheader!("Transaction");
+
+    // (3) Journal every step of the transaction before acting on it.
+    let downloads =
+        convert_lock_mods_to_downloads(&current_directory, &config, to_download, &current_lockfile);
+    config.game_version = game_version.clone();
+    if loader_version.is_some() {
+        config.loader_version = loader_version.clone();
+    }
+    let mut transaction = Transaction::begin(&current_directory, "upgrade")?;
+    for download in downloads {
+        transaction.plan_download(&current_directory, download)?;
+    }
+    for lock_mod in &unneeded {
+        let path = config.installed_path(&current_directory, lock_mod);
+        if path.is_file() {
+            transaction.plan_delete(&current_directory, path)?;
+        }
+    }
+    transaction.plan_write_lockfile(&current_directory, &new_lockfile)?;
+    transaction.plan_write_config(&current_directory, &config)?;
+    if let Some(path) = &instance_path {
+        let contents = synced_pack(
+            path,
+            &config.game_loader,
+            &config.game_version,
+            loader_version.as_deref(),
+        )?;
+        transaction.plan_write_file(
+            &current_directory,
+            path.join(PACK_FILE_NAME),
+            contents.as_bytes(),
+        )?;
+    }
+
+    let report = match transaction.download(&current_directory, &client).await {
+        Ok(result) => result,
+        Err(e) => {
+            alert!("Transaction failed. Run 'modman transactions' to resume or roll it back.");
+            return Err(e);
+        }
+    };
+    if !report.failed().is_empty() {
+        report.print_failures();
+        alert!("Transaction failed. Run 'modman transactions' to resume or roll it back.");
+        return Err(ModManError::IncompleteTransaction(transaction.id.clone()));
+    }
+    if let Err(e) = transaction.finish(&current_directory) {
+        alert!("Transaction failed. Run 'modman transactions' to resume or roll it back.");
+        return Err(e);
+    }
+
+    confirm!(format!("Upgraded to Minecraft {}.", game_version));
+    if instance_path.is_some() {
+        confirm!("The instance's mmc-pack.json was updated to match.");
+    }
+    Ok(())
+}
+
+// The loader in mmc-pack.json is made for the old game version, so an instance with a loader needs its new version too.
+fn check_instance_loader(
+    instance: &Instance,
+    current_game_version: &str,
+    game_version: &str,
+    loader_version: Option<&str>,
+) -> Result<(), ModManError> {
+    match &instance.loader_version {
+        Some(current) if loader_version.is_none() && game_version != current_game_version => {
+            Err(ModManError::InvalidCommandArguments(format!(
+                "The instance uses {} {}, which may not support Minecraft {}. Pass '--loader-version <version>' to change it too.",
+                instance.game_loader, current, game_version
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+// The lockfile with the new versions in place of the old ones. Mods with no new version (kept with '--force'), and
+// CurseForge and local mods, stay at their current versions. Mods that are disabled stay disabled.
+fn upgraded_lockfile(current: &[LockMod], upgraded: &[LockMod]) -> Vec<LockMod> {
+    current
+        .iter()
+        .map(
+            |lock_mod| match upgraded.iter().find(|u| u.id == lock_mod.id) {
+                Some(new_mod) => LockMod {
+                    disabled: lock_mod.disabled,
+                    ..new_mod.clone()
+                },
+                None => lock_mod.clone(),
+            },
+        )
+        .chain(
+            upgraded
+                .iter()
+                .filter(|u| !current.iter().any(|m| m.id == u.id))
+                .cloned(),
+        )
+        .collect()
+}
+
+// Removes the dependencies of the old versions which nothing needs anymore: they are not in the config, and no mod
+// left in the lockfile has them as a required or optional dependency. Returns the removed mods.
+fn prune_dependencies(
+    lockfile: &mut Vec<LockMod>,
+    previous: &[LockMod],
+    explicit_ids: &[&str],
+) -> Vec<LockMod> {
+    let was_dependency = |id: &str| {
+        previous
+            .iter()
+            .any(|m| m.dependencies.iter().any(|d| d.project_id == id))
+    };
+    let mut removed: Vec<LockMod> = Vec::new();
+    // Removing a mod can leave its own dependencies unneeded, so this repeats until nothing is removed.
+    while let Some(index) = lockfile.iter().position(|lock_mod| {
+        !explicit_ids.contains(&lock_mod.id.as_str())
+            && was_dependency(&lock_mod.id)
+            && !lockfile.iter().any(|other| {
+                other.id != lock_mod.id
+                    && other.dependencies.iter().any(|d| {
+                        d.project_id == lock_mod.id
+                            && matches!(
+                                d.dependency_type,
+                                DependencyType::Required | DependencyType::Optional
+                            )
+                    })
+            })
+    }) {
+        removed.push(lockfile.remove(index));
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::{LockDependency, SideSupport};
+
+    fn lock_mod(id: &str, version: &str, dependencies: &[&str]) -> LockMod {
+        LockMod {
+            name: id.to_string(),
+            source: ModSources::Modrinth,
+            id: id.to_string(),
+            version: version.to_string(),
+            file_name: format!("{}-{}.jar", id, version),
+            release_date: "Unknown".to_string(),
+            sha512: format!("{}-{}", id, version),
+            sha1: None,
+            download_url: "Unknown".to_string(),
+            file_id: None,
+            dependencies: dependencies
+                .iter()
+                .map(|project_id| LockDependency {
+                    source: ModSources::Modrinth,
+                    project_id: project_id.to_string(),
+                    dependency_type: DependencyType::Required,
+                })
+                .collect(),
+            size: 1,
+            disabled: false,
+            project_type: Default::default(),
+            loaders: vec![],
+            game_versions: vec![],
+            client_side: SideSupport::Unknown,
+            server_side: SideSupport::Unknown,
+        }
+    }
+
+    fn ids(lockfile: &[LockMod]) -> Vec<&str> {
+        lockfile.iter().map(|m| m.id.as_str()).collect()
+    }
+
+    fn fabric_instance(loader_version: Option<&str>) -> Instance {
+        Instance {
+            game_version: "1.20.1".to_string(),
+            game_loader: GameLoader::Fabric,
+            loader_version: loader_version.map(str::to_string),
+            game_dir_name: ".minecraft",
+        }
+    }
+
+    #[test]
+    fn new_versions_replace_old_ones() {
+        let mut sodium = lock_mod("sodium", "1.0", &[]);
+        sodium.disabled = true;
+        let current = vec![sodium, lock_mod("lithium", "1.0", &[])];
+        let upgraded = vec![
+            lock_mod("sodium", "2.0", &[]),
+            lock_mod("lithium", "2.0", &[]),
+        ];
+
+        let lockfile = upgraded_lockfile(&current, &upgraded);
+
+        assert_eq!(ids(&lockfile), vec!["sodium", "lithium"]);
+        assert_eq!(lockfile[0].version, "2.0");
+        assert_eq!(lockfile[1].version, "2.0");
+        assert!(lockfile[0].disabled);
+        assert!(!lockfile[1].disabled);
+    }
+
+    #[test]
+    fn mods_without_a_new_version_are_kept_when_forced() {
+        let mut jei = lock_mod("jei", "1.0", &[]);
+        jei.source = ModSources::CurseForge;
+        let current = vec![
+            lock_mod("sodium", "1.0", &[]),
+            lock_mod("iris", "1.0", &[]),
+            jei,
+        ];
+        // Iris has no version for the new game version.
+        let upgraded = vec![lock_mod("sodium", "2.0", &[])];
+
+        let lockfile = upgraded_lockfile(&current, &upgraded);
+
+        assert_eq!(ids(&lockfile), vec!["sodium", "iris", "jei"]);
+        assert_eq!(lockfile[0].version, "2.0");
+        assert_eq!(lockfile[1].version, "1.0");
+        assert_eq!(lockfile[2].version, "1.0");
+    }
+
+    #[test]
+    fn dependencies_no_longer_needed_are_pruned() {
+        // The old Sodium needed indium, which needed fabric-api. The new Sodium needs neither.
+        let previous = vec![
+            lock_mod("sodium", "1.0", &["indium"]),
+            lock_mod("indium", "1.0", &["fabric-api"]),
+            lock_mod("fabric-api", "1.0", &[]),
+            lock_mod("lithium", "1.0", &[]),
+        ];
+        let mut lockfile = upgraded_lockfile(&previous, &[lock_mod("sodium", "2.0", &[])]);
+
+        let removed = prune_dependencies(&mut lockfile, &previous, &["sodium", "lithium"]);
+
+        assert_eq!(ids(&lockfile), vec!["sodium", "lithium"]);
+        assert_eq!(ids(&removed), vec!["indium", "fabric-api"]);
+    }
+
+    #[test]
+    fn dependencies_still_needed_or_explicit_are_kept() {
+        let previous = vec![
+            lock_mod("sodium", "1.0", &["fabric-api"]),
+            lock_mod("iris", "1.0", &["fabric-api", "indium"]),
+            lock_mod("fabric-api", "1.0", &[]),
+            lock_mod("indium", "1.0", &[]),
+        ];
+        let upgraded = vec![lock_mod("sodium", "2.0", &[]), lock_mod("iris", "2.0", &[])];
+        let mut lockfile = upgraded_lockfile(&previous, &upgraded);
+        // Lithium (not upgraded) still needs fabric-api, and indium is in the config.
+        lockfile.push(lock_mod("lithium", "1.0", &["fabric-api"]));
+
+        let removed = prune_dependencies(&mut lockfile, &previous, &["sodium", "iris", "indium"]);
+
+        assert!(removed.is_empty());
+        assert_eq!(lockfile.len(), 5);
+    }
+
+    #[test]
+    fn instance_loader_needs_a_new_version() {
+        let instance = fabric_instance(Some("0.15.0"));
+
+        assert!(matches!(
+            check_instance_loader(&instance, "1.20.1", "1.21.1", None),
+            Err(ModManError::InvalidCommandArguments(_))
+        ));
+        assert!(check_instance_loader(&instance, "1.20.1", "1.21.1", Some("0.16.0")).is_ok());
+        // Only changing the loader version is fine.
+        assert!(check_instance_loader(&instance, "1.20.1", "1.20.1", None).is_ok());
+    }
+
+    #[test]
+    fn vanilla_instance_needs_no_loader_version() {
+        let mut instance = fabric_instance(None);
+        instance.game_loader = GameLoader::Vanilla;
+
+        assert!(check_instance_loader(&instance, "1.20.1", "1.21.1", None).is_ok());
+    }
+}
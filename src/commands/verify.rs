@@ -0,0 +1,372 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use colored::Colorize;
+
+use crate::{
+    actionheader, alert,
+    config::{read_config_with_alerts, read_lockfile},
+    confirm,
+    datatypes::{Config, LockMod, ProjectType, DISABLED_SUFFIX},
+    errors::ModManError,
+    hash_cache::hash_files_uncached,
+    info,
+    install::file_matches,
+    transactions::check_incomplete_transactions,
+};
+
+pub fn command_verify() -> Result<(), ModManError> {
+    /*
+        Checks every installed file against the lockfile (modman.lock), without changing anything.
+        Every file is hashed again, rather than trusting the hash cache.
+
+        Reports:
+            Missing     - A lockfile entry with no file.
+            Modified    - A file whose size or hash (sha512, or sha1 for CurseForge) doesn't match its lockfile entry.
+            Mislocated  - A lockfile entry whose file is somewhere else (another name, folder, or enabled/disabled state).
+            Extra       - A file that isn't in the lockfile.
+
+        Fails if there are any of these, or if a transaction is incomplete, so it can be run before starting a server.
+    */
+
+    let current_directory = match crate::utils::get_current_working_dir() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::IoError(e)),
+    };
+
+    check_incomplete_transactions(&current_directory)?;
+
+    let config = read_config_with_alerts(&current_directory)?;
+    let lockfile: Vec<LockMod> = match read_lockfile(&current_directory) {
+        Ok(result) => result,
+        Err(ModManError::FileNotFound) => Vec::new(),
+        Err(ModManError::FileIsEmpty) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+
+    let file_paths = scan_files(&current_directory, &config)?;
+
+    actionheader!("Verify");
+    info!(format!("Hashing {} file(s)...", file_paths.len()));
+    let discrepancies = find_discrepancies(&current_directory, &config, &lockfile, &file_paths)?;
+
+    for discrepancy in &discrepancies {
+        let message = match discrepancy {
+            Discrepancy::Missing { path, name } => format!(
+                "Missing:    {} ({})",
+                display_path(&current_directory, path),
+                name
+            ),
+            Discrepancy::Modified { path, name } => format!(
+                "Modified:   {} ({})",
+                display_path(&current_directory, path),
+                name
+            ),
+            Discrepancy::Mislocated {
+                path,
+                expected_path,
+                name,
+            } => format!(
+                "Mislocated: {} should be at {} ({})",
+                display_path(&current_directory, path),
+                display_path(&current_directory, expected_path),
+                name
+            ),
+            Discrepancy::Extra { path } => {
+                format!("Extra:      {}", display_path(&current_directory, path))
+            }
+        };
+        alert!(message);
+    }
+
+    if !discrepancies.is_empty() {
+        info!("Run 'modman sync' to update the lockfile, or 'modman add' to reinstall mods.");
+        return Err(ModManError::VerificationFailed(discrepancies.len()));
+    }
+
+    confirm!(format!(
+        "All {} file(s) match the lockfile.",
+        file_paths.len()
+    ));
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum Discrepancy {
+    Missing {
+        path: PathBuf,
+        name: String,
+    },
+    Modified {
+        path: PathBuf,
+        name: String,
+    },
+    Mislocated {
+        path: PathBuf,
+        expected_path: PathBuf,
+        name: String,
+    },
+    Extra {
+        path: PathBuf,
+    },
+}
+
+// Scans the folder of every project type, the same way sync does.
+fn scan_files(dir: &Path, config: &Config) -> Result<Vec<PathBuf>, ModManError> {
+    let mut file_paths: Vec<PathBuf> = Vec::new();
+    for project_type in ProjectType::ALL {
+        let folder_path = config.project_path(dir, &project_type);
+        if !folder_path.is_dir() {
+            continue;
+        }
+        let extension = format!(".{}", project_type.file_extension());
+        for entry in fs::read_dir(&folder_path).map_err(ModManError::IoError)? {
+            let entry = entry.map_err(ModManError::IoError)?;
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let enabled_name = file_name
+                .strip_suffix(DISABLED_SUFFIX)
+                .unwrap_or(&file_name);
+            if path.is_file() && enabled_name.ends_with(&extension) {
+                file_paths.push(path);
+            }
+        }
+    }
+    file_paths.sort();
+    Ok(file_paths)
+}
+
+// Hashes every file in `file_paths`, and checks them against the lockfile.
+fn find_discrepancies(
+    dir: &Path,
+    config: &Config,
+    lockfile: &[LockMod],
+    file_paths: &[PathBuf],
+) -> Result<Vec<Discrepancy>, ModManError> {
+    let checksums = hash_files_uncached(file_paths).map_err(ModManError::IoError)?;
+
+    let expected_paths: HashSet<PathBuf> = lockfile
+        .iter()
+        .map(|lock_mod| config.installed_path(dir, lock_mod))
+        .collect();
+    let mut claimed: HashSet<PathBuf> = HashSet::new(); // Unexpected files matched to a mislocated lockfile entry.
+    let mut discrepancies: Vec<Discrepancy> = Vec::new();
+
+    for lock_mod in lockfile {
+        let expected_path = config.installed_path(dir, lock_mod);
+        // Mods for the other side are not installed, so they are only checked if their file is there.
+        if !config.wants(lock_mod) && !file_paths.contains(&expected_path) {
+            continue;
+        }
+        match file_paths.iter().position(|path| *path == expected_path) {
+            Some(index) => {
+                let size = fs::metadata(&expected_path)
+                    .map_err(ModManError::IoError)?
+                    .len();
+                let matches = file_matches(&expected_path, &checksums[index], lock_mod)
+                    .map_err(ModManError::IoError)?;
+                if size != lock_mod.size || !matches {
+                    discrepancies.push(Discrepancy::Modified {
+                        path: expected_path,
+                        name: lock_mod.name.clone(),
+                    });
+                }
+            }
+            None => {
+                let mut found: Option<&PathBuf> = None;
+                for (path, checksum) in file_paths.iter().zip(&checksums) {
+                    if !expected_paths.contains(path)
+                        && !claimed.contains(path)
+                        && file_matches(path, checksum, lock_mod).map_err(ModManError::IoError)?
+                    {
+                        found = Some(path);
+                        break;
+                    }
+                }
+                match found {
+                    Some(path) => {
+                        claimed.insert(path.clone());
+                        discrepancies.push(Discrepancy::Mislocated {
+                            path: path.clone(),
+                            expected_path,
+                            name: lock_mod.name.clone(),
+                        });
+                    }
+                    None => discrepancies.push(Discrepancy::Missing {
+                        path: expected_path,
+                        name: lock_mod.name.clone(),
+                    }),
+                }
+            }
+        }
+    }
+
+    for path in file_paths {
+        if !expected_paths.contains(path) && !claimed.contains(path) {
+            discrepancies.push(Discrepancy::Extra { path: path.clone() });
+        }
+    }
+    Ok(discrepancies)
+}
+
+// Paths are shown relative to the project directory, if they are inside it.
+fn display_path(dir: &Path, path: &Path) -> String {
+    path.strip_prefix(dir).unwrap_or(path).display().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        datatypes::{
+            DownloadSettings, GameLoader, ModSources, ModpackSettings, ReleaseTypes, Side,
+            SideSupport,
+        },
+        install::{calculate_sha1, calculate_sha512},
+    };
+
+    use super::*;
+
+    fn config(side: Side) -> Config {
+        Config {
+            game_version: "1.20.1".to_string(),
+            game_loader: GameLoader::Fabric,
+            loader_version: None,
+            allowed_release_types: vec![ReleaseTypes::Release],
+            mods_folder: PathBuf::from("mods"),
+            resourcepacks_folder: PathBuf::from("resourcepacks"),
+            shaderpacks_folder: PathBuf::from("shaderpacks"),
+            datapacks_folder: PathBuf::from("world/datapacks"),
+            downloads: DownloadSettings::default(),
+            side,
+            modpack: ModpackSettings::default(),
+            instance: None,
+            mods: vec![],
+            profiles: Default::default(),
+        }
+    }
+
+    fn write_file(dir: &Path, folder: &str, file_name: &str, contents: &[u8]) -> PathBuf {
+        fs::create_dir_all(dir.join(folder)).unwrap();
+        let path = dir.join(folder).join(file_name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    // A lockfile entry for the file at `path`.
+    fn lock_mod(id: &str, path: &Path) -> LockMod {
+        LockMod {
+            name: id.to_string(),
+            source: ModSources::Modrinth,
+            id: id.to_string(),
+            version: "1.0.0".to_string(),
+            file_name: path.file_name().unwrap().to_string_lossy().to_string(),
+            release_date: "Unknown".to_string(),
+            sha512: calculate_sha512(path).unwrap(),
+            sha1: None,
+            download_url: "Unknown".to_string(),
+            file_id: None,
+            dependencies: vec![],
+            size: fs::metadata(path).unwrap().len(),
+            disabled: false,
+            project_type: ProjectType::Mod,
+            loaders: vec![],
+            game_versions: vec![],
+            client_side: SideSupport::Required,
+            server_side: SideSupport::Required,
+        }
+    }
+
+    fn verify(dir: &Path, config: &Config, lockfile: &[LockMod]) -> Vec<Discrepancy> {
+        let file_paths = scan_files(dir, config).unwrap();
+        find_discrepancies(dir, config, lockfile, &file_paths).unwrap()
+    }
+
+    #[test]
+    fn matching_files_have_no_discrepancies() {
+        let dir = tempfile::tempdir().unwrap();
+        let sodium = write_file(dir.path(), "mods", "sodium.jar", b"sodium");
+        let faithful = write_file(dir.path(), "resourcepacks", "faithful.zip", b"faithful");
+        // CurseForge files have no sha512 until they are downloaded, and are checked with their sha1.
+        let jei = write_file(dir.path(), "mods", "jei.jar", b"jei");
+        let mut faithful_mod = lock_mod("faithful", &faithful);
+        faithful_mod.project_type = ProjectType::ResourcePack;
+        let mut jei_mod = lock_mod("jei", &jei);
+        jei_mod.source = ModSources::CurseForge;
+        jei_mod.sha512 = String::new();
+        jei_mod.sha1 = Some(calculate_sha1(&jei).unwrap());
+        // Files which are not projects are not checked.
+        write_file(dir.path(), "mods", "notes.txt", b"notes");
+
+        let lockfile = vec![lock_mod("sodium", &sodium), faithful_mod, jei_mod];
+        assert_eq!(verify(dir.path(), &config(Side::Both), &lockfile), vec![]);
+    }
+
+    #[test]
+    fn modified_missing_and_extra_files_are_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let sodium = write_file(dir.path(), "mods", "sodium.jar", b"sodium");
+        let lithium = write_file(dir.path(), "mods", "lithium.jar", b"lithium");
+        let lockfile = vec![lock_mod("sodium", &sodium), lock_mod("lithium", &lithium)];
+        fs::write(&sodium, b"sodium, patched").unwrap();
+        fs::remove_file(&lithium).unwrap();
+        let iris = write_file(dir.path(), "mods", "iris.jar", b"iris");
+
+        assert_eq!(
+            verify(dir.path(), &config(Side::Both), &lockfile),
+            vec![
+                Discrepancy::Modified {
+                    path: sodium,
+                    name: "sodium".to_string()
+                },
+                Discrepancy::Missing {
+                    path: lithium,
+                    name: "lithium".to_string()
+                },
+                Discrepancy::Extra { path: iris },
+            ]
+        );
+    }
+
+    #[test]
+    fn moved_files_are_mislocated_not_extra() {
+        let dir = tempfile::tempdir().unwrap();
+        let sodium = write_file(dir.path(), "mods", "sodium.jar", b"sodium");
+        let lockfile = vec![lock_mod("sodium", &sodium)];
+        let disabled = dir.path().join("mods").join("sodium.jar.disabled");
+        fs::rename(&sodium, &disabled).unwrap();
+
+        assert_eq!(
+            verify(dir.path(), &config(Side::Both), &lockfile),
+            vec![Discrepancy::Mislocated {
+                path: disabled,
+                expected_path: sodium,
+                name: "sodium".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn mods_for_the_other_side_are_only_checked_if_installed() {
+        let dir = tempfile::tempdir().unwrap();
+        let sodium = write_file(dir.path(), "mods", "sodium.jar", b"sodium");
+        let mut sodium_mod = lock_mod("sodium", &sodium);
+        sodium_mod.server_side = SideSupport::Unsupported;
+        let lockfile = vec![sodium_mod];
+        let config = config(Side::Server);
+
+        fs::write(&sodium, b"sodium, patched").unwrap();
+        assert_eq!(
+            verify(dir.path(), &config, &lockfile),
+            vec![Discrepancy::Modified {
+                path: sodium.clone(),
+                name: "sodium".to_string()
+            }]
+        );
+
+        fs::remove_file(&sodium).unwrap();
+        assert_eq!(verify(dir.path(), &config, &lockfile), vec![]);
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use colored::Colorize;
+
+use crate::{
+    actionheader, alert,
+    commands::command_structs::CommandOptions,
+    config::{read_config_with_alerts, read_lockfile},
+    datatypes::{DependencyType, LockMod},
+    errors::ModManError,
+    info,
+};
+
+// A single edge in a dependency path: (parent mod id, dependency type of the edge to the next mod).
+type PathEdge = (String, DependencyType);
+
+pub fn command_why(options: &CommandOptions) -> Result<(), ModManError> {
+    /*
+        The arguments are as follows for 'why' command:
+        <package_slug / package_ID / name / file name>  - The mod in the lockfile to explain.
+
+        Walks the dependency graph of modman.lock backwards, starting at the queried mod, and prints every path
+        from an explicit mod (listed in modman.toml) to the queried mod, including the dependency type of each edge.
+    */
+
+    let query = match options.parameters.first() {
+        Some(result) => result,
+        None => return Err(ModManError::NoArguments),
+    };
+
+    let current_directory = match crate::utils::get_current_working_dir() {
+        Ok(result) => result,
+        Err(e) => return Err(ModManError::IoError(e)),
+    };
+
+    let config = read_config_with_alerts(&current_directory)?;
+    let lockfile: Vec<LockMod> = match read_lockfile(&current_directory) {
+        Ok(result) => result,
+        Err(ModManError::FileNotFound) => Vec::new(),
+        Err(ModManError::FileIsEmpty) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+
+    let target = match find_lock_mod(&lockfile, query) {
+        Some(result) => result,
+        None => {
+            alert!(format!("'{}' is not in the lockfile (modman.lock).", query));
+            return Err(ModManError::CannotFindMod(query.to_string()));
+        }
+    };
+
+    let explicit_ids: Vec<&str> = config.mods.iter().map(|m| m.id.as_str()).collect();
+    let names: HashMap<&str, &str> = lockfile
+        .iter()
+        .map(|m| (m.id.as_str(), m.name.as_str()))
+        .collect();
+
+    actionheader!(format!("Why '{}'", target.name));
+
+    let is_explicit = explicit_ids.contains(&target.id.as_str());
+    if is_explicit {
+        info!(format!(
+            "'{}' is listed explicitly in the config file (modman.toml).",
+            target.name
+        ));
+    }
+
+    let paths = find_dependency_paths(&lockfile, &explicit_ids, &target.id);
+
+    if paths.is_empty() {
+        if !is_explicit {
+            alert!(format!(
+                "'{}' is orphaned: it is in the lockfile (modman.lock), but is required by nothing.",
+                target.name
+            ));
+        }
+        return Ok(());
+    }
+
+    info!(format!(
+        "'{}' is depended on through {} path(s):",
+        target.name,
+        paths.len()
+    ));
+    for path in paths {
+        let mut line = String::new();
+        for (parent_id, dependency_type) in &path {
+            let parent_name = names.get(parent_id.as_str()).copied().unwrap_or(parent_id);
+            line.push_str(&format!(
+                "{} {} ",
+                parent_name,
+                format!("--({})-->", dependency_type).bright_black()
+            ));
+        }
+        line.push_str(&target.name);
+        println!("    {}", line);
+    }
+
+    Ok(())
+}
+
+pub fn find_lock_mod<'a>(lockfile: &'a [LockMod], query: &str) -> Option<&'a LockMod> {
+    lockfile
+        .iter()
+        .find(|m| m.id == query)
+        .or_else(|| lockfile.iter().find(|m| m.name.eq_ignore_ascii_case(query)))
+        .or_else(|| lockfile.iter().find(|m| m.file_name == query))
+}
+
+fn find_dependency_paths(
+    lockfile: &[LockMod],
+    explicit_ids: &[&str],
+    target_id: &str,
+) -> Vec<Vec<PathEdge>> {
+    // Reverse the graph: dependency id -> mods (and edge type) that depend on it.
+    let mut dependents: HashMap<&str, Vec<(&str, &DependencyType)>> = HashMap::new();
+    for lock_mod in lockfile {
+        for dep in &lock_mod.dependencies {
+            dependents
+                .entry(dep.project_id.as_str())
+                .or_default()
+                .push((lock_mod.id.as_str(), &dep.dependency_type));
+        }
+    }
+
+    let mut paths: Vec<Vec<PathEdge>> = Vec::new();
+    let mut current: Vec<PathEdge> = Vec::new();
+    let mut visited: Vec<&str> = vec![target_id];
+    walk_dependents(
+        target_id,
+        &dependents,
+        explicit_ids,
+        &mut visited,
+        &mut current,
+        &mut paths,
+    );
+    paths
+}
+
+fn walk_dependents<'a>(
+    mod_id: &'a str,
+    dependents: &HashMap<&'a str, Vec<(&'a str, &'a DependencyType)>>,
+    explicit_ids: &[&str],
+    visited: &mut Vec<&'a str>,
+    current: &mut Vec<PathEdge>,
+    paths: &mut Vec<Vec<PathEdge>>,
+) {
+    let Some(parents) = dependents.get(mod_id) else {
+        return;
+    };
+
+    for (parent_id, dependency_type) in parents {
+        // Skip cycles.
+        if visited.contains(parent_id) {
+            continue;
+        }
+
+        current.push((parent_id.to_string(), (*dependency_type).clone()));
+        visited.push(parent_id);
+
+        if explicit_ids.contains(parent_id) {
+            // The path is collected from the queried mod backwards, so reverse it for display.
+            paths.push(current.iter().rev().cloned().collect());
+        }
+        walk_dependents(parent_id, dependents, explicit_ids, visited, current, paths);
+
+        visited.pop();
+        current.pop();
+    }
+}
@@ -1,21 +1,78 @@
-use std::{fs, path::Path};
+use std::{fs, path::Path, sync::OnceLock};
 
+use colored::Colorize;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    datatypes::{Config, LockMod},
+    alert,
+    datatypes::{Config, LockMod, Mod, Profile},
     errors::ModManError,
 };
 
+/*
+    Profiles ([profiles.<name>] tables in modman.toml) are selected for the whole run with '--profile <name>' (or MODMAN_PROFILE).
+
+    With a profile selected, read_config returns the config as seen by that profile: its settings replace the ones at the top
+    of modman.toml, and its mods are the inherited ones, minus 'remove', plus 'add'. save_config does the reverse, so commands
+    don't need to know about profiles: changes to settings and mods are saved to the profile, and the top of modman.toml is
+    left as it is. The profile's lockfile is used instead of modman.lock.
+*/
+
+static ACTIVE_PROFILE: OnceLock<String> = OnceLock::new();
+
+pub fn set_active_profile(name: &str) {
+    let _ = ACTIVE_PROFILE.set(name.to_string());
+}
+
+pub fn active_profile() -> Option<&'static str> {
+    ACTIVE_PROFILE.get().map(|name| name.as_str())
+}
+
+fn profile_not_found(config: &Config, name: &str) -> ModManError {
+    let names: Vec<&str> = config.profiles.keys().map(|n| n.as_str()).collect();
+    ModManError::InvalidCommandArguments(format!(
+        "Profile '{}' is not in modman.toml. Profiles: [{}]",
+        name,
+        names.join(", ")
+    ))
+}
+
 // modman.toml
 
 pub fn save_config(dir: &Path, config: &Config) -> Result<(), ModManError> {
     let config_path = dir.join("modman.toml");
-    let config_data = toml::to_string_pretty(config).map_err(ModManError::SerializationError)?;
+    let config_data = config_contents(dir, config)?;
     fs::write(config_path, config_data).map_err(ModManError::IoError)
 }
 
+// Contents of modman.toml for `config`. With a profile selected, the rest of modman.toml in `dir` is kept.
+pub fn config_contents(dir: &Path, config: &Config) -> Result<String, ModManError> {
+    match active_profile() {
+        Some(name) => profile_config_contents(read_config_file(dir)?, config, name),
+        None => toml::to_string_pretty(config).map_err(ModManError::SerializationError),
+    }
+}
+
+// Contents of modman.toml for `config` (as seen by profile `name`), saved to the profile in `file`, the config as it is in modman.toml.
+pub fn profile_config_contents(
+    file: Config,
+    config: &Config,
+    name: &str,
+) -> Result<String, ModManError> {
+    let file = unapply_profile(file, config, name)?;
+    toml::to_string_pretty(&file).map_err(ModManError::SerializationError)
+}
+
 pub fn read_config(dir: &Path) -> Result<Config, ModManError> {
+    let config = read_config_file(dir)?;
+    match active_profile() {
+        Some(name) => apply_profile(config, name),
+        None => Ok(config),
+    }
+}
+
+// Reads modman.toml as it is, without applying the selected profile.
+pub fn read_config_file(dir: &Path) -> Result<Config, ModManError> {
     let config_path = dir.join("modman.toml");
 
     if !config_path.exists() {
@@ -34,6 +91,132 @@ pub fn read_config(dir: &Path) -> Result<Config, ModManError> {
     Ok(config)
 }
 
+pub fn apply_profile(mut config: Config, name: &str) -> Result<Config, ModManError> {
+    let profile = match config.profiles.get(name) {
+        Some(result) => result.clone(),
+        None => return Err(profile_not_found(&config, name)),
+    };
+    if let Some(game_version) = profile.game_version {
+        config.game_version = game_version;
+    }
+    if let Some(game_loader) = profile.game_loader {
+        config.game_loader = game_loader;
+    }
+    if let Some(loader_version) = profile.loader_version {
+        config.loader_version = Some(loader_version);
+    }
+    if let Some(mods_folder) = profile.mods_folder {
+        config.mods_folder = mods_folder;
+    }
+    if let Some(resourcepacks_folder) = profile.resourcepacks_folder {
+        config.resourcepacks_folder = resourcepacks_folder;
+    }
+    if let Some(shaderpacks_folder) = profile.shaderpacks_folder {
+        config.shaderpacks_folder = shaderpacks_folder;
+    }
+    if let Some(datapacks_folder) = profile.datapacks_folder {
+        config.datapacks_folder = datapacks_folder;
+    }
+    if let Some(side) = profile.side {
+        config.side = side;
+    }
+    if let Some(instance) = profile.instance {
+        config.instance = Some(instance);
+    }
+
+    if !profile.inherit {
+        config.mods.clear();
+    }
+    config.mods.retain(|m| !profile.remove.contains(&m.id));
+    for added_mod in profile.add {
+        match config.mods.iter_mut().find(|m| m.id == added_mod.id) {
+            Some(inherited_mod) => *inherited_mod = added_mod,
+            None => config.mods.push(added_mod),
+        }
+    }
+    Ok(config)
+}
+
+// Adds a profile to `file` (the config as it is in modman.toml), so it can be selected and saved to.
+// An existing profile is only replaced if `replace` is set.
+pub fn add_profile(
+    file: &mut Config,
+    name: &str,
+    profile: Profile,
+    replace: bool,
+) -> Result<(), ModManError> {
+    if file.profiles.contains_key(name) && !replace {
+        return Err(ModManError::InvalidCommandArguments(format!(
+            "Profile '{}' is already in modman.toml. Use '--force' to replace it.",
+            name
+        )));
+    }
+    file.profiles.insert(name.to_string(), profile);
+    Ok(())
+}
+
+// Saves the settings and mods of `config` (as seen by the profile) to the profile in `file`, the config as it is in modman.toml.
+fn unapply_profile(mut file: Config, config: &Config, name: &str) -> Result<Config, ModManError> {
+    let inherited: Vec<Mod> = match file.profiles.get(name) {
+        Some(profile) if profile.inherit => file.mods.clone(),
+        Some(_) => Vec::new(),
+        None => return Err(profile_not_found(&file, name)),
+    };
+    let profile = file.profiles.get_mut(name).unwrap();
+
+    profile.game_version = override_of(&config.game_version, &file.game_version);
+    profile.game_loader = override_of(&config.game_loader, &file.game_loader);
+    profile.loader_version = override_of(&config.loader_version, &file.loader_version).flatten();
+    profile.mods_folder = override_of(&config.mods_folder, &file.mods_folder);
+    profile.resourcepacks_folder =
+        override_of(&config.resourcepacks_folder, &file.resourcepacks_folder);
+    profile.shaderpacks_folder = override_of(&config.shaderpacks_folder, &file.shaderpacks_folder);
+    profile.datapacks_folder = override_of(&config.datapacks_folder, &file.datapacks_folder);
+    profile.side = override_of(&config.side, &file.side);
+    profile.instance = override_of(&config.instance, &file.instance).flatten();
+
+    profile.remove = inherited
+        .iter()
+        .filter(|m| !config.mods.iter().any(|c| c.id == m.id))
+        .map(|m| m.id.clone())
+        .collect();
+    profile.add = config
+        .mods
+        .iter()
+        .filter(|m| !inherited.contains(m))
+        .cloned()
+        .collect();
+    Ok(file)
+}
+
+// A setting is only kept in the profile if it differs from the top of modman.toml.
+fn override_of<T: PartialEq + Clone>(value: &T, file_value: &T) -> Option<T> {
+    (value != file_value).then(|| value.clone())
+}
+
+// Same as read_config, but tells the user how to fix a missing or broken config file.
+pub fn read_config_with_alerts(dir: &Path) -> Result<Config, ModManError> {
+    match read_config(dir) {
+        Ok(result) => Ok(result),
+        Err(ModManError::FileNotFound) => {
+            alert!("No config file (modman.toml) found for this directory!");
+            alert!("Please run 'modman init' to generate a config file.");
+            Err(ModManError::FileNotFound)
+        }
+        Err(ModManError::FileIsEmpty) => {
+            alert!("Config file (modman.toml) is empty!");
+            alert!("Please run 'modman init' to generate a config file.");
+            Err(ModManError::FileIsEmpty)
+        }
+        Err(ModManError::DeserializationError(e)) => {
+            alert!("Either config file modman.toml has incorrect information, or is corrupt. Please modify modman.toml, or");
+            alert!("delete it to reset the configuration.");
+            Err(ModManError::DeserializationError(e))
+        }
+        Err(e) => Err(e),
+    }
+}
+
 // modman.lock
 
 // This container solves toml serialization and deserialization errors.
@@ -42,18 +225,47 @@ struct LockModContainer {
     lockmod: Vec<LockMod>,
 }
 
-pub fn save_lockfile(dir: &Path, lockmod: &Vec<LockMod>) -> Result<(), ModManError> {
-    let lockfile_path = dir.join("modman.lock");
+// File name of the lockfile: modman.lock, or the lockfile of the selected profile.
+pub fn lockfile_name(dir: &Path) -> Result<String, ModManError> {
+    let name = match active_profile() {
+        Some(name) => name,
+        None => return Ok("modman.lock".to_string()),
+    };
+    profile_lockfile_name(&read_config_file(dir)?, name)
+}
+
+// File name of the lockfile of profile `name` in `file`, the config as it is in modman.toml.
+pub fn profile_lockfile_name(file: &Config, name: &str) -> Result<String, ModManError> {
+    let lockfile = match file.profiles.get(name) {
+        Some(profile) => profile
+            .lockfile
+            .clone()
+            .unwrap_or_else(|| format!("modman.{}.lock", name)),
+        None => return Err(profile_not_found(file, name)),
+    };
+    if Path::new(&lockfile).file_name() != Some(lockfile.as_ref()) {
+        return Err(ModManError::InvalidCommandArguments(format!(
+            "The lockfile of profile '{}' must be a file name, not a path: '{}'",
+            name, lockfile
+        )));
+    }
+    Ok(lockfile)
+}
+
+pub fn save_lockfile(dir: &Path, lockmod: &[LockMod]) -> Result<(), ModManError> {
+    let lockfile_path = dir.join(lockfile_name(dir)?);
+    fs::write(lockfile_path, lockfile_contents(lockmod)?).map_err(ModManError::IoError)
+}
+
+pub fn lockfile_contents(lockmod: &[LockMod]) -> Result<String, ModManError> {
     let lockmod_container = LockModContainer {
         lockmod: lockmod.to_owned(),
     };
-    let lockfile_data =
-        toml::to_string_pretty(&lockmod_container).map_err(ModManError::SerializationError)?;
-    fs::write(lockfile_path, lockfile_data).map_err(ModManError::IoError)
+    toml::to_string_pretty(&lockmod_container).map_err(ModManError::SerializationError)
 }
 
 pub fn read_lockfile(dir: &Path) -> Result<Vec<LockMod>, ModManError> {
-    let lockfile_path = dir.join("modman.lock");
+    let lockfile_path = dir.join(lockfile_name(dir)?);
 
     if !lockfile_path.exists() {
         return Err(ModManError::FileNotFound);
@@ -65,10 +277,131 @@ pub fn read_lockfile(dir: &Path) -> Result<Vec<LockMod>, ModManError> {
         return Err(ModManError::FileIsEmpty);
     }
 
+    parse_lockfile(&toml_content)
+}
+
+pub fn parse_lockfile(toml_content: &str) -> Result<Vec<LockMod>, ModManError> {
     let lockmod_container: LockModContainer =
-        toml::from_str(&toml_content).map_err(ModManError::DeserializationError)?;
+        toml::from_str(toml_content).map_err(ModManError::DeserializationError)?;
+
+    Ok(lockmod_container.lockmod)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+game_version = "1.20.1"
+game_loader = "Fabric"
+allowed_release_types = ["Release"]
+mods_folder = "mods"
+
+[[mods]]
+source = "Modrinth"
+id = "sodium"
+name = "Sodium"
+enabled = true
+
+[[mods]]
+source = "Modrinth"
+id = "iris"
+name = "Iris"
+enabled = true
+
+[profiles.server]
+game_version = "1.21.1"
+mods_folder = "server/mods"
+side = "server"
+remove = ["sodium"]
+
+[[profiles.server.add]]
+source = "Modrinth"
+id = "lithium"
+name = "Lithium"
+enabled = true
 
-    let lockmod = lockmod_container.lockmod;
+[profiles.fresh]
+inherit = false
+lockfile = "fresh.lock"
+"#;
 
-    Ok(lockmod)
+    fn file() -> Config {
+        toml::from_str(CONFIG).unwrap()
+    }
+
+    fn mod_ids(config: &Config) -> Vec<&str> {
+        config.mods.iter().map(|m| m.id.as_str()).collect()
+    }
+
+    #[test]
+    fn profile_settings_and_mods_are_applied() {
+        let config = apply_profile(file(), "server").unwrap();
+
+        assert_eq!(config.game_version, "1.21.1");
+        assert_eq!(config.mods_folder, Path::new("server/mods"));
+        assert_eq!(config.game_loader, crate::datatypes::GameLoader::Fabric);
+        assert_eq!(mod_ids(&config), vec!["iris", "lithium"]);
+        assert!(mod_ids(&apply_profile(file(), "fresh").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn applying_and_unapplying_a_profile_gives_back_the_config() {
+        let expected = toml::to_string_pretty(&file()).unwrap();
+        for name in ["server", "fresh"] {
+            let config = apply_profile(file(), name).unwrap();
+
+            let contents = profile_config_contents(file(), &config, name).unwrap();
+
+            assert_eq!(contents, expected);
+        }
+    }
+
+    #[test]
+    fn changes_through_a_profile_are_saved_to_the_profile() {
+        let mut config = apply_profile(file(), "server").unwrap();
+        config.game_version = "1.21.4".to_string();
+        config.mods.retain(|m| m.id != "iris");
+
+        let contents = profile_config_contents(file(), &config, "server").unwrap();
+        let saved: Config = toml::from_str(&contents).unwrap();
+
+        assert_eq!(saved.game_version, "1.20.1");
+        assert_eq!(mod_ids(&saved), vec!["sodium", "iris"]);
+        let profile = &saved.profiles["server"];
+        assert_eq!(profile.game_version.as_deref(), Some("1.21.4"));
+        assert_eq!(profile.remove, vec!["sodium", "iris"]);
+        assert_eq!(
+            mod_ids(&apply_profile(saved, "server").unwrap()),
+            vec!["lithium"]
+        );
+    }
+
+    #[test]
+    fn unknown_profile_is_an_error() {
+        assert!(matches!(
+            apply_profile(file(), "client"),
+            Err(ModManError::InvalidCommandArguments(_))
+        ));
+    }
+
+    #[test]
+    fn profiles_have_their_own_lockfile() {
+        assert_eq!(
+            profile_lockfile_name(&file(), "server").unwrap(),
+            "modman.server.lock"
+        );
+        assert_eq!(
+            profile_lockfile_name(&file(), "fresh").unwrap(),
+            "fresh.lock"
+        );
+    }
+
+    #[test]
+    fn profile_lockfile_must_be_a_file_name() {
+        let mut file = file();
+        file.profiles.get_mut("fresh").unwrap().lockfile = Some("../modman.lock".to_string());
+
+        assert!(profile_lockfile_name(&file, "fresh").is_err());
+    }
 }
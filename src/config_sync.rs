@@ -1,35 +1,145 @@
 use colored::Colorize;
 use reqwest::Client;
-use std::{collections::HashMap, fs, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use crate::{
     alert,
     api::modrinth::modrinth_mod_from_hash,
-    config::{read_lockfile, save_config, save_lockfile},
-    datatypes::{DependencyType, LockDependency, LockMod, Mod, ModSources},
+    config::{read_config_with_alerts, read_lockfile},
+    datatypes::{
+        DependencyType, LockDependency, LockMod, Mod, ModSources, ProjectType, SideSupport,
+        DISABLED_SUFFIX,
+    },
     errors::ModManError,
-    install::calculate_sha512,
+    hash_cache::HashCache,
+    info,
+    install::calculate_sha1,
+    metadata::{read_metadata, ModMetadata},
+    transactions::Transaction,
 };
 
 pub struct SyncFilesReturn {
     pub missing_dependencies: Vec<LockDependency>,
     pub new_mods: Vec<Mod>,
     pub to_reinstall_bad_checksum: Vec<LockMod>,
+    pub renamed: Vec<RenamedFile>,
+    pub toggled: Vec<ToggledMod>,
+}
+
+impl SyncFilesReturn {
+    pub fn print_renamed(&self) {
+        for renamed_file in &self.renamed {
+            if renamed_file.restored {
+                info!(format!(
+                    "Renamed '{}' back to '{}' ({}).",
+                    renamed_file.file_name, renamed_file.locked_file_name, renamed_file.name
+                ));
+            } else {
+                info!(format!(
+                    "Lockfile updated: '{}' was renamed to '{}' ({}).",
+                    renamed_file.locked_file_name, renamed_file.file_name, renamed_file.name
+                ));
+            }
+        }
+    }
+
+    pub fn print_toggled(&self) {
+        for toggled_mod in &self.toggled {
+            if toggled_mod.enabled {
+                info!(format!("'{}' was enabled.", toggled_mod.name));
+            } else {
+                info!(format!("'{}' was disabled.", toggled_mod.name));
+            }
+        }
+    }
+}
+
+// A file that matched a lockfile entry by content, but not by file name.
+pub struct RenamedFile {
+    pub name: String,
+    pub file_name: String,        // Name of the file in the mods folder.
+    pub locked_file_name: String, // Name of the file in the lockfile.
+    pub restored: bool, // True if the file was renamed back to its locked name, false if the lockfile was updated instead.
+}
+
+// A mod that was enabled or disabled outside of modman (e.g. by a launcher).
+pub struct ToggledMod {
+    pub name: String,
+    pub enabled: bool,
+}
+
+// Lockfile entry for a file that doesn't match any source, filled in from the metadata in the jar if it has any.
+// Ids must be unique, so the file name is kept as the id if `id_taken` says another mod already uses the jar's mod id.
+pub fn local_lock_mod(
+    path: &Path,
+    file_name: &str,
+    checksum: &str,
+    disabled: bool,
+    project_type: ProjectType,
+    metadata: Option<ModMetadata>,
+    id_taken: impl Fn(&str) -> bool,
+) -> Result<LockMod, ModManError> {
+    let mut local_mod = LockMod {
+        name: file_name.to_string(),
+        source: ModSources::Local,
+        id: file_name.to_string(),
+        version: "0".to_string(),
+        file_name: file_name.to_string(),
+        release_date: "Unknown".to_string(),
+        sha512: checksum.to_string(),
+        sha1: None,
+        download_url: "Unknown".to_string(),
+        file_id: None,
+        dependencies: vec![],
+        size: fs::metadata(path).map_err(ModManError::IoError)?.len(),
+        disabled,
+        project_type,
+        loaders: vec![],
+        game_versions: vec![],
+        client_side: SideSupport::Unknown,
+        server_side: SideSupport::Unknown,
+    };
+    if let Some(metadata) = metadata {
+        if !id_taken(&metadata.id) {
+            local_mod.id = metadata.id;
+        }
+        local_mod.name = metadata.name;
+        if !metadata.version.is_empty() {
+            local_mod.version = metadata.version;
+        }
+        local_mod.loaders = metadata.loaders;
+        local_mod.game_versions = metadata.game_versions;
+        local_mod.dependencies = metadata.dependencies;
+        local_mod.client_side = metadata.client_side;
+        local_mod.server_side = metadata.server_side;
+    }
+    Ok(local_mod)
 }
 
 pub async fn sync_files(
     current_directory: &Path,
-    client: &Client,
+    client: &Arc<Client>,
+    keep_file_names: bool,
 ) -> Result<SyncFilesReturn, ModManError> {
     /*
         fn sync_files() compares installed mods, the lockfile, and the config file, then makes updates to synchronize all of these files.
 
         Step-by-Step Workflow:
             1. READ: Read config and lockfile.
-            2. SCAN: Scan mods folder its checksum and filename.
-            3. COMPARE: Verify its existence in the lockfile and config.
+            2. SCAN: Scan mods folder (.jar), and resourcepacks, shaderpacks and datapacks folders (.zip) its checksum and filename.
+                Disabled files ('.disabled') are matched under their enabled name.
+            3. COMPARE: Verify its existence in the lockfile and config. Files are matched by checksum first, then by file name.
+                Renamed file (checksum in lockfile under another name)? Rename it back to its locked name, or if `keep_file_names` is set
+                (or the locked name is taken), update the lockfile to the new name.
+                Replaced file (file name in lockfile with another checksum)? Reinstall it.
                 New mod that doesn't exist in lockfile? Add it (maybe try to match before returning source: local ?)
                 Lockfile entry that points to a non-existent mod in the folder? Remove it. If it is a dependency to any other mod, add to vec of missing dependencies.
+                Mod enabled or disabled by a launcher? Follow the file, and update the lockfile and config.
             4. SYNC (modfiles to lockfile): (Try to) remove invalid entries, and add new entries (trying to match it to a mod with a source).
             5. SYNC (config to lockfile): Remove mods and its no longer used dependencies if config does not include the mod. Return list of mods to download if config file
             contains a mod that the lockfile doesn't.
@@ -38,34 +148,20 @@ pub async fn sync_files(
             Vec of of missing mod IDs and source that other mods are dependent on.
             Vec of mod IDs and source to be fetched and resolved.
             Vec of lockmods that have incorrect checksums, and need to be re-installed.
+            Vec of files that were renamed, and whether they were restored to their locked name.
+            Vec of mods that were enabled or disabled.
     */
 
-    let mut mod_files: Vec<(String, String)> = Vec::new(); // filename, sha512
     let mut to_reinstall_bad_checksum: Vec<LockMod> = Vec::new();
     let mut missing_dependencies: Vec<LockDependency> = Vec::new();
     let mut new_mods: Vec<Mod> = Vec::new();
+    let mut renames: Vec<(PathBuf, PathBuf)> = Vec::new(); // Done as part of the transaction at the end.
+    let mut renamed: Vec<RenamedFile> = Vec::new();
+    let mut toggled: Vec<ToggledMod> = Vec::new();
 
     // (1) Read config and lockfile.
     // Load config
-    let mut config = match crate::config::read_config(current_directory) {
-        Ok(result) => result,
-        Err(ModManError::FileNotFound) => {
-            alert!("No config file (modman.toml) found for this directory!");
-            alert!("Please run 'modman init' to generate a config file.");
-            return Err(ModManError::FileNotFound);
-        }
-        Err(ModManError::FileIsEmpty) => {
-            alert!("Config file (modman.toml) is empty!");
-            alert!("Please run 'modman init' to generate a config file.");
-            return Err(ModManError::FileIsEmpty);
-        }
-        Err(ModManError::DeserializationError(e)) => {
-            alert!("Either config file modman.toml has incorrect information, or is corrupt. Please modify modman.toml, or");
-            alert!("delete it to reset the configuration.");
-            return Err(ModManError::DeserializationError(e));
-        }
-        Err(e) => return Err(e),
-    };
+    let mut config = read_config_with_alerts(current_directory)?;
 
     // Load lockfile
     let mut current_lockfile: Vec<LockMod> = match read_lockfile(current_directory) {
@@ -75,11 +171,25 @@ pub async fn sync_files(
         Err(e) => return Err(e),
     };
 
-    // (2) Scan mods folder its checksum and filename.
-    for entry in fs::read_dir(&config.mods_folder).map_err(ModManError::IoError)? {
-        let entry = entry.map_err(ModManError::IoError)?;
-        let path = entry.path();
-        if path.is_file() && path.extension().and_then(|ext| ext.to_str()) == Some("jar") {
+    // (2) Scan the folder of each project type (mods folder, resourcepacks, ...) its checksum and filename.
+    // All file paths are rooted at these folders, so sync does not depend on the working directory.
+    // Hashes of unchanged files are reused from the hash cache.
+    let mut scanned_files: Vec<(ProjectType, String, bool)> = Vec::new(); // project type, filename (without DISABLED_SUFFIX), disabled
+    let mut file_paths: Vec<PathBuf> = Vec::new();
+    for project_type in ProjectType::ALL {
+        let folder_path = config.project_path(current_directory, &project_type);
+        if project_type == ProjectType::Mod {
+            fs::create_dir_all(&folder_path).map_err(ModManError::IoError)?;
+        } else if !folder_path.is_dir() {
+            continue;
+        }
+        let extension = format!(".{}", project_type.file_extension());
+        for entry in fs::read_dir(&folder_path).map_err(ModManError::IoError)? {
+            let entry = entry.map_err(ModManError::IoError)?;
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
             let file_name = match path.file_name() {
                 Some(result) => result,
                 None => return Err(ModManError::FileNotFound),
@@ -87,74 +197,263 @@ pub async fn sync_files(
             .to_os_string()
             .into_string()
             .unwrap();
-            let checksum = calculate_sha512(&path).map_err(ModManError::IoError)?;
-            mod_files.push((file_name, checksum));
+            if file_name.ends_with(&extension) {
+                scanned_files.push((project_type.clone(), file_name, false));
+                file_paths.push(path);
+            } else if let Some(enabled_name) = file_name.strip_suffix(DISABLED_SUFFIX) {
+                if !enabled_name.ends_with(&extension) {
+                    continue;
+                }
+                // A file with both an enabled and a disabled copy is treated as enabled.
+                if folder_path.join(enabled_name).is_file() {
+                    alert!(format!(
+                        "Both '{}' and '{}' exist. Ignoring the disabled copy.",
+                        enabled_name, file_name
+                    ));
+                    continue;
+                }
+                scanned_files.push((project_type.clone(), enabled_name.to_string(), true));
+                file_paths.push(path);
+            }
         }
     }
+    let mut hash_cache = HashCache::load(current_directory);
+    let checksums = hash_cache
+        .hash_files(&file_paths)
+        .map_err(ModManError::IoError)?;
+    hash_cache.save(current_directory)?;
 
     // (3) Verify its existence in the lockfile and config.
     // (4) (modfiles to lockfile): (Try to) remove invalid entries, and add new entries (trying to match it to a mod with a source).
-    // Match mod_files to lockfile (lockfile is temporarily as a hashmap here).
-    let mut lockfile_map: HashMap<String, LockMod> = current_lockfile
-        .iter()
-        .cloned()
-        .map(|m| (m.file_name.clone(), m.clone()))
-        .collect();
-    for (file_path, checksum) in &mod_files {
-        let key = lockfile_map.get_key_value(file_path);
-        // If mod is not found in lockfile, add it to lockfile and config.
-        if key.is_none() {
-            // Mod does not exist.
-            // Try to match file to hash from source
-            if let Ok(result) = modrinth_mod_from_hash(client, checksum).await {
-                // Try Modrinth:
-                fs::rename(file_path.clone(), result.clone().file_name)
-                    .map_err(ModManError::IoError)?;
-                current_lockfile.push(result.clone());
-
-                config.mods.push(Mod {
-                    source: result.source,
-                    id: result.id,
-                    name: result.name,
+    // Each project type is matched on its own, as the same file name can be in more than one folder.
+    let mut missing_lock_mods: Vec<LockMod> = Vec::new(); // Lockfile entries with no file in their folder.
+    for project_type in ProjectType::ALL {
+        let folder_path = config.project_path(current_directory, &project_type);
+        let mut mod_files: Vec<(String, String)> = Vec::new(); // filename (without DISABLED_SUFFIX), sha512
+        let mut disabled_files: HashSet<String> = HashSet::new(); // Filenames (without DISABLED_SUFFIX) of disabled files.
+        for ((file_type, file_name, disabled), checksum) in scanned_files.iter().zip(&checksums) {
+            if *file_type != project_type {
+                continue;
+            }
+            if *disabled {
+                disabled_files.insert(file_name.clone());
+            }
+            mod_files.push((file_name.clone(), checksum.clone()));
+        }
+
+        // CurseForge only gives the sha1, so the sha512 of their files is recorded once it is found here.
+        for lock_mod in current_lockfile.iter_mut() {
+            if lock_mod.project_type != project_type || !lock_mod.sha512.is_empty() {
+                continue;
+            }
+            let (Some(sha1), Some((file_name, checksum))) = (
+                &lock_mod.sha1,
+                mod_files
+                    .iter()
+                    .find(|(name, _)| *name == lock_mod.file_name),
+            ) else {
+                continue;
+            };
+            let suffix = if disabled_files.contains(file_name) {
+                DISABLED_SUFFIX
+            } else {
+                ""
+            };
+            let path = folder_path.join(format!("{}{}", file_name, suffix));
+            if calculate_sha1(&path).map_err(ModManError::IoError)? == *sha1 {
+                lock_mod.sha512 = checksum.clone();
+            }
+        }
+
+        // Match mod_files to lockfile (lockfile is temporarily as a hashmap here).
+        let mut lockfile_map: HashMap<String, LockMod> = current_lockfile
+            .iter()
+            .filter(|m| m.project_type == project_type)
+            .map(|m| (m.file_name.clone(), m.clone()))
+            .collect();
+
+        // Match by checksum first, to find files that were renamed (e.g. by a launcher).
+        let scanned_file_names: HashSet<String> = mod_files
+            .iter()
+            .map(|(file_name, _)| file_name.clone())
+            .collect();
+        let exact_matches: HashSet<String> = mod_files
+            .iter()
+            .filter(|(file_name, checksum)| {
+                lockfile_map
+                    .get(file_name)
+                    .is_some_and(|m| m.sha512 == *checksum)
+            })
+            .map(|(file_name, _)| file_name.clone())
+            .collect();
+        let mut claimed: HashSet<String> = HashSet::new(); // Locked file names already matched to a renamed file.
+        for (file_name, checksum) in mod_files.iter_mut() {
+            if exact_matches.contains(file_name) {
+                continue;
+            }
+            // A locked mod with the same checksum, whose own file is not there with that checksum.
+            let locked_mod = match lockfile_map.values().find(|m| {
+                m.sha512 == *checksum
+                    && m.file_name != *file_name
+                    && !exact_matches.contains(&m.file_name)
+                    && !claimed.contains(&m.file_name)
+            }) {
+                Some(result) => result.clone(),
+                None => continue,
+            };
+            claimed.insert(locked_mod.file_name.clone());
+
+            if !keep_file_names && !scanned_file_names.contains(&locked_mod.file_name) {
+                // Restore the locked name, keeping the file disabled if it is.
+                let suffix = if disabled_files.remove(&*file_name) {
+                    disabled_files.insert(locked_mod.file_name.clone());
+                    DISABLED_SUFFIX
+                } else {
+                    ""
+                };
+                renames.push((
+                    folder_path.join(format!("{}{}", file_name, suffix)),
+                    folder_path.join(format!("{}{}", locked_mod.file_name, suffix)),
+                ));
+                renamed.push(RenamedFile {
+                    name: locked_mod.name.clone(),
+                    file_name: file_name.clone(),
+                    locked_file_name: locked_mod.file_name.clone(),
+                    restored: true,
                 });
-                // TODO: Add implementation for curseforge.
+                *file_name = locked_mod.file_name.clone();
             } else {
-                // No matches to a source. Add as local instead...:
-                current_lockfile.push(LockMod {
-                    name: file_path.clone(),
-                    source: ModSources::Local,
-                    id: file_path.clone(),
-                    version: "0".to_string(),
-                    file_name: file_path.clone(),
-                    release_date: "Unknown".to_string(),
-                    sha512: checksum.clone(),
-                    download_url: "Unknown".to_string(),
-                    dependencies: vec![],
-                    size: fs::metadata(file_path).map_err(ModManError::IoError)?.len(),
+                // Update the lockfile to the new name.
+                lockfile_map.remove(&locked_mod.file_name);
+                for lock_mod in current_lockfile.iter_mut() {
+                    if lock_mod.id == locked_mod.id && lock_mod.file_name == locked_mod.file_name {
+                        lock_mod.file_name = file_name.clone();
+                    }
+                }
+                let mut updated_mod = locked_mod.clone();
+                updated_mod.file_name = file_name.clone();
+                lockfile_map.insert(file_name.clone(), updated_mod);
+                renamed.push(RenamedFile {
+                    name: locked_mod.name.clone(),
+                    file_name: file_name.clone(),
+                    locked_file_name: locked_mod.file_name.clone(),
+                    restored: false,
                 });
+            }
+        }
 
-                config.mods.push(Mod {
-                    source: ModSources::Local,
-                    id: file_path.clone(),
-                    name: file_path.clone(),
+        // Follow mods that were enabled or disabled by a launcher, rather than renaming them back.
+        for (file_name, _) in &mod_files {
+            let disabled = disabled_files.contains(file_name);
+            let lock_mod = match current_lockfile
+                .iter_mut()
+                .find(|m| m.project_type == project_type && m.file_name == *file_name)
+            {
+                Some(result) => result,
+                None => continue,
+            };
+            let mut changed = lock_mod.disabled != disabled;
+            lock_mod.disabled = disabled;
+            if let Some(map_mod) = lockfile_map.get_mut(file_name) {
+                map_mod.disabled = disabled;
+            }
+            if let Some(config_mod) = config.mods.iter_mut().find(|m| m.id == lock_mod.id) {
+                changed |= config_mod.enabled == disabled;
+                config_mod.enabled = !disabled;
+            }
+            if changed {
+                toggled.push(ToggledMod {
+                    name: lock_mod.name.clone(),
+                    enabled: !disabled,
                 });
             }
-            // Now that we added to the actual lockfile, we remove it from the map.
-            lockfile_map.remove(file_path);
-        } else if key.unwrap().1.sha512 == *checksum {
-            // Mod already exists and checksum matches. Ignore and remove from map.
-            lockfile_map.remove(file_path);
-        } else {
-            // Mod failed integrity check despite having same filename.
-            // Transfer the value into the return vec to_reinstall_bad_checksum
-            to_reinstall_bad_checksum.push(lockfile_map.remove(file_path).unwrap())
         }
+
+        for (file_path, checksum) in &mod_files {
+            let disabled = disabled_files.contains(file_path);
+            let installed_path = if disabled {
+                folder_path.join(format!("{}{}", file_path, DISABLED_SUFFIX))
+            } else {
+                folder_path.join(file_path)
+            };
+            let locked_checksum = lockfile_map.get(file_path).map(|m| m.sha512.clone());
+            // If mod is not found in lockfile, add it to lockfile and config.
+            if locked_checksum.is_none() {
+                // Mod does not exist.
+                // Try to match file to hash from source
+                if let Ok(mut result) = modrinth_mod_from_hash(client, checksum).await {
+                    // Try Modrinth:
+                    result.disabled = disabled;
+                    result.project_type = project_type.clone(); // The folder decides the project type.
+                    renames.push((
+                        installed_path,
+                        folder_path.join(result.installed_file_name()),
+                    ));
+                    current_lockfile.push(result.clone());
+
+                    // Another jar of the same project may already be in the config. These are reported by 'modman check'.
+                    if !config.mods.iter().any(|m| m.id == result.id) {
+                        config.mods.push(Mod {
+                            source: result.source,
+                            id: result.id,
+                            name: result.name,
+                            enabled: !disabled,
+                            project_type: result.project_type,
+                            side: None,
+                        });
+                    }
+                    // TODO: Add implementation for curseforge.
+                } else {
+                    // No matches to a source. Add as local instead, filled in from the metadata in the jar if it has any.
+                    let metadata = match project_type {
+                        ProjectType::Mod => read_metadata(&installed_path),
+                        _ => None,
+                    };
+                    let local_mod = local_lock_mod(
+                        &installed_path,
+                        file_path,
+                        checksum,
+                        disabled,
+                        project_type.clone(),
+                        metadata,
+                        |id| config.mods.iter().any(|m| m.id == id),
+                    )?;
+
+                    // Local mods can already be in the config (e.g. written by hand, or added by a profile).
+                    if !config.mods.iter().any(|m| m.id == local_mod.id) {
+                        config.mods.push(Mod {
+                            source: ModSources::Local,
+                            id: local_mod.id.clone(),
+                            name: local_mod.name.clone(),
+                            enabled: !disabled,
+                            project_type: project_type.clone(),
+                            side: None,
+                        });
+                    }
+                    current_lockfile.push(local_mod);
+                }
+                // Now that we added to the actual lockfile, we remove it from the map.
+                lockfile_map.remove(file_path);
+            } else if locked_checksum.as_ref() == Some(checksum) {
+                // Mod already exists and checksum matches. Ignore and remove from map.
+                lockfile_map.remove(file_path);
+            } else {
+                // Mod failed integrity check despite having same filename.
+                // Transfer the value into the return vec to_reinstall_bad_checksum
+                to_reinstall_bad_checksum.push(lockfile_map.remove(file_path).unwrap())
+            }
+        }
+        missing_lock_mods.extend(lockfile_map.into_values());
     }
-    // lockfile_map is now left with mods which do not exist in the mods folder.
+    // missing_lock_mods is now left with mods which do not exist in their folder.
     // Check if the mod is a dependency or not. If so, add to missing_dependencies vec.
     // Otherwise, we check the dependencies of the mods. Check if each dependency is being used by other mods,
     // then safely remove these dependencies along with the missing mod entry.
-    for (_, mod_entry) in lockfile_map {
+    for mod_entry in missing_lock_mods {
+        // Mods for the other side (see 'side' in modman.toml) are not installed, so they stay in the lockfile without a file.
+        if !config.wants(&mod_entry) {
+            continue;
+        }
         // Check if the mod is a dependency of any other mods
         let is_dependency = current_lockfile.iter().any(|per_mod| {
             per_mod
@@ -162,7 +461,13 @@ pub async fn sync_files(
                 .iter()
                 .any(|dep| dep.project_id == mod_entry.id)
         });
-        if is_dependency {
+        if is_dependency && mod_entry.source == ModSources::Local {
+            // Local mods can't be downloaded again, so they are only reported.
+            alert!(format!(
+                "'{}' is needed by other mods, but its file '{}' is missing. Copy it back into its folder.",
+                mod_entry.name, mod_entry.file_name
+            ));
+        } else if is_dependency {
             // Add to missing_dependencies if it's a dependency
             missing_dependencies.push(LockDependency {
                 source: mod_entry.source.clone(),
@@ -228,13 +533,442 @@ pub async fn sync_files(
         }
     }
 
-    // Finally, save config and lockfile.
-    save_config(current_directory, &config)?;
-    save_lockfile(current_directory, &current_lockfile)?;
+    // Finally, rename matched files and save config and lockfile, as a transaction.
+    let mut transaction = Transaction::begin(current_directory, "sync")?;
+    for (from, to) in renames {
+        transaction.plan_rename(from, to);
+    }
+    transaction.plan_write_config(current_directory, &config)?;
+    transaction.plan_write_lockfile(current_directory, &current_lockfile)?;
+    transaction.run(current_directory, client).await?;
 
     Ok(SyncFilesReturn {
         missing_dependencies,
         new_mods,
         to_reinstall_bad_checksum,
+        renamed,
+        toggled,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        commands::{add::fetch_package, add_tools::package::Package},
+        config::{read_config, save_config, save_lockfile},
+        datatypes::{Config, DownloadSettings, GameLoader, ModpackSettings, ReleaseTypes, Side},
+        install::calculate_sha512,
+    };
+
+    // Modrinth lookups go through an unreachable proxy, so unknown files fall back to local mods.
+    fn offline_client() -> Arc<Client> {
+        Arc::new(
+            Client::builder()
+                .proxy(reqwest::Proxy::all("http://127.0.0.1:9").unwrap())
+                .build()
+                .unwrap(),
+        )
+    }
+
+    fn write_project(dir: &Path, mods_folder: PathBuf, mods: Vec<Mod>, lockfile: &[LockMod]) {
+        let config = Config {
+            game_version: "1.20.1".to_string(),
+            game_loader: GameLoader::Fabric,
+            loader_version: None,
+            allowed_release_types: vec![ReleaseTypes::Release],
+            mods_folder,
+            resourcepacks_folder: PathBuf::from("instance/resourcepacks"),
+            shaderpacks_folder: PathBuf::from("instance/shaderpacks"),
+            datapacks_folder: PathBuf::from("instance/world/datapacks"),
+            downloads: DownloadSettings::default(),
+            side: Side::Both,
+            modpack: ModpackSettings::default(),
+            instance: None,
+            mods,
+            profiles: Default::default(),
+        };
+        save_config(dir, &config).unwrap();
+        save_lockfile(dir, lockfile).unwrap();
+    }
+
+    fn write_jar(mods_path: &Path, file_name: &str, contents: &[u8]) -> String {
+        fs::create_dir_all(mods_path).unwrap();
+        let path = mods_path.join(file_name);
+        fs::write(&path, contents).unwrap();
+        calculate_sha512(&path).unwrap()
+    }
+
+    fn lock_mod(id: &str, file_name: &str, sha512: String, size: u64) -> LockMod {
+        LockMod {
+            name: id.to_string(),
+            source: ModSources::Modrinth,
+            id: id.to_string(),
+            version: "1.0.0".to_string(),
+            file_name: file_name.to_string(),
+            release_date: "Unknown".to_string(),
+            sha512,
+            sha1: None,
+            download_url: "Unknown".to_string(),
+            file_id: None,
+            dependencies: vec![],
+            size,
+            disabled: false,
+            project_type: ProjectType::Mod,
+            loaders: vec![],
+            game_versions: vec![],
+            client_side: SideSupport::Unknown,
+            server_side: SideSupport::Unknown,
+        }
+    }
+
+    fn config_mod(id: &str) -> Mod {
+        Mod {
+            source: ModSources::Modrinth,
+            id: id.to_string(),
+            name: id.to_string(),
+            enabled: true,
+            project_type: ProjectType::Mod,
+            side: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn matching_files_are_left_alone() {
+        let project = tempfile::tempdir().unwrap();
+        let mods_path = project.path().join("instance/mods");
+        let sha512 = write_jar(&mods_path, "sodium.jar", b"sodium");
+        let lockfile = vec![lock_mod("sodium", "sodium.jar", sha512, 6)];
+        write_project(
+            project.path(),
+            PathBuf::from("instance/mods"),
+            vec![config_mod("sodium")],
+            &lockfile,
+        );
+
+        let result = sync_files(project.path(), &offline_client(), false)
+            .await
+            .unwrap();
+
+        assert!(result.new_mods.is_empty());
+        assert!(result.missing_dependencies.is_empty());
+        assert!(result.to_reinstall_bad_checksum.is_empty());
+        assert!(result.renamed.is_empty());
+        assert_eq!(read_lockfile(project.path()).unwrap().len(), 1);
+        assert!(mods_path.join("sodium.jar").exists());
+    }
+
+    #[tokio::test]
+    async fn curseforge_file_is_matched_by_sha1() {
+        let project = tempfile::tempdir().unwrap();
+        let mods_path = project.path().join("instance/mods");
+        let sha512 = write_jar(&mods_path, "jei.jar", b"jei");
+        let mut jei = lock_mod("jei", "jei.jar", String::new(), 3);
+        jei.source = ModSources::CurseForge;
+        jei.sha1 = Some(calculate_sha1(&mods_path.join("jei.jar")).unwrap());
+        write_project(
+            project.path(),
+            PathBuf::from("instance/mods"),
+            vec![config_mod("jei")],
+            &[jei],
+        );
+
+        let result = sync_files(project.path(), &offline_client(), false)
+            .await
+            .unwrap();
+
+        assert!(result.to_reinstall_bad_checksum.is_empty());
+        assert_eq!(read_lockfile(project.path()).unwrap()[0].sha512, sha512);
+    }
+
+    #[tokio::test]
+    async fn missing_curseforge_file_is_not_fetched() {
+        let project = tempfile::tempdir().unwrap();
+        let mut jei = lock_mod("jei", "jei.jar", String::new(), 3);
+        jei.source = ModSources::CurseForge;
+        jei.sha1 = Some("0".repeat(40));
+        let mut jei_mod = config_mod("jei");
+        jei_mod.source = ModSources::CurseForge;
+        write_project(
+            project.path(),
+            PathBuf::from("instance/mods"),
+            vec![jei_mod],
+            &[jei],
+        );
+        let client = offline_client();
+
+        let result = sync_files(project.path(), &client, false).await.unwrap();
+
+        // 'modman add' installs these, and reports that it can't fetch CurseForge mods instead of panicking.
+        assert_eq!(result.new_mods.len(), 1);
+        let package = Package {
+            search_term: result.new_mods[0].id.clone(),
+            source: result.new_mods[0].source.clone(),
+            project_type: None,
+        };
+        let fetched =
+            fetch_package(&client, package, &"1.20.1".to_string(), &GameLoader::Fabric).await;
+        assert!(matches!(
+            fetched,
+            Err(ModManError::CurseForgeNotSupported(id)) if id == "jei"
+        ));
+    }
+
+    #[tokio::test]
+    async fn renamed_file_is_restored_inside_mods_folder() {
+        let project = tempfile::tempdir().unwrap();
+        let mods_path = project.path().join("instance/mods");
+        let sha512 = write_jar(&mods_path, "sodium (1).jar", b"sodium");
+        let lockfile = vec![lock_mod("sodium", "sodium.jar", sha512, 6)];
+        write_project(
+            project.path(),
+            PathBuf::from("instance/mods"),
+            vec![config_mod("sodium")],
+            &lockfile,
+        );
+
+        let result = sync_files(project.path(), &offline_client(), false)
+            .await
+            .unwrap();
+
+        assert_eq!(result.renamed.len(), 1);
+        assert!(result.renamed[0].restored);
+        assert!(mods_path.join("sodium.jar").exists());
+        assert!(!mods_path.join("sodium (1).jar").exists());
+    }
+
+    #[tokio::test]
+    async fn renamed_file_updates_lockfile_when_keeping_names() {
+        let project = tempfile::tempdir().unwrap();
+        let mods_path = project.path().join("instance/mods");
+        let sha512 = write_jar(&mods_path, "sodium (1).jar", b"sodium");
+        let lockfile = vec![lock_mod("sodium", "sodium.jar", sha512, 6)];
+        write_project(
+            project.path(),
+            PathBuf::from("instance/mods"),
+            vec![config_mod("sodium")],
+            &lockfile,
+        );
+
+        let result = sync_files(project.path(), &offline_client(), true)
+            .await
+            .unwrap();
+
+        assert_eq!(result.renamed.len(), 1);
+        assert!(!result.renamed[0].restored);
+        assert!(mods_path.join("sodium (1).jar").exists());
+        let lockfile = read_lockfile(project.path()).unwrap();
+        assert_eq!(lockfile[0].file_name, "sodium (1).jar");
+    }
+
+    #[tokio::test]
+    async fn unknown_file_is_added_as_local_mod() {
+        let project = tempfile::tempdir().unwrap();
+        let mods_path = project.path().join("instance/mods");
+        let sha512 = write_jar(&mods_path, "custom.jar", b"custom mod");
+        write_project(project.path(), PathBuf::from("instance/mods"), vec![], &[]);
+
+        sync_files(project.path(), &offline_client(), false)
+            .await
+            .unwrap();
+
+        let lockfile = read_lockfile(project.path()).unwrap();
+        assert_eq!(lockfile.len(), 1);
+        assert_eq!(lockfile[0].source, ModSources::Local);
+        assert_eq!(lockfile[0].file_name, "custom.jar");
+        assert_eq!(lockfile[0].sha512, sha512);
+        assert_eq!(lockfile[0].size, 10);
+        let config = read_config(project.path()).unwrap();
+        assert_eq!(config.mods.len(), 1);
+        assert_eq!(config.mods[0].id, "custom.jar");
+    }
+
+    #[tokio::test]
+    async fn disabled_file_is_kept_and_marked_disabled() {
+        let project = tempfile::tempdir().unwrap();
+        let mods_path = project.path().join("instance/mods");
+        let sha512 = write_jar(&mods_path, "sodium.jar.disabled", b"sodium");
+        let lockfile = vec![lock_mod("sodium", "sodium.jar", sha512, 6)];
+        write_project(
+            project.path(),
+            PathBuf::from("instance/mods"),
+            vec![config_mod("sodium")],
+            &lockfile,
+        );
+
+        let result = sync_files(project.path(), &offline_client(), false)
+            .await
+            .unwrap();
+
+        assert!(result.new_mods.is_empty());
+        assert!(result.to_reinstall_bad_checksum.is_empty());
+        assert_eq!(result.toggled.len(), 1);
+        assert!(!result.toggled[0].enabled);
+        assert!(mods_path.join("sodium.jar.disabled").exists());
+        assert!(read_lockfile(project.path()).unwrap()[0].disabled);
+        assert!(!read_config(project.path()).unwrap().mods[0].enabled);
+    }
+
+    #[tokio::test]
+    async fn resource_packs_are_matched_in_their_own_folder() {
+        let project = tempfile::tempdir().unwrap();
+        let mods_path = project.path().join("instance/mods");
+        let resourcepacks_path = project.path().join("instance/resourcepacks");
+        let mod_sha512 = write_jar(&mods_path, "faithful.jar", b"mod");
+        let pack_sha512 = write_jar(&resourcepacks_path, "faithful.zip", b"pack");
+        let mut pack = lock_mod("faithful-pack", "faithful.zip", pack_sha512, 4);
+        pack.project_type = ProjectType::ResourcePack;
+        let lockfile = vec![lock_mod("faithful", "faithful.jar", mod_sha512, 3), pack];
+        let mut pack_mod = config_mod("faithful-pack");
+        pack_mod.project_type = ProjectType::ResourcePack;
+        write_project(
+            project.path(),
+            PathBuf::from("instance/mods"),
+            vec![config_mod("faithful"), pack_mod],
+            &lockfile,
+        );
+        // A zip in the mods folder is not a mod.
+        write_jar(&mods_path, "notes.zip", b"notes");
+
+        let result = sync_files(project.path(), &offline_client(), false)
+            .await
+            .unwrap();
+
+        assert!(result.new_mods.is_empty());
+        assert!(result.to_reinstall_bad_checksum.is_empty());
+        assert!(result.renamed.is_empty());
+        assert_eq!(read_lockfile(project.path()).unwrap().len(), 2);
+        assert_eq!(read_config(project.path()).unwrap().mods.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn missing_local_dependency_is_not_queued_for_download() {
+        let project = tempfile::tempdir().unwrap();
+        let mods_path = project.path().join("instance/mods");
+        let sha512 = write_jar(&mods_path, "custom.jar", b"custom");
+        let mut custom = lock_mod("custom", "custom.jar", sha512, 6);
+        custom.dependencies = vec![LockDependency {
+            source: ModSources::Local,
+            project_id: "library".to_string(),
+            dependency_type: DependencyType::Required,
+        }];
+        let mut library = lock_mod("library", "library.jar", "0".repeat(128), 7);
+        library.source = ModSources::Local;
+        write_project(
+            project.path(),
+            PathBuf::from("instance/mods"),
+            vec![config_mod("custom"), config_mod("library")],
+            &[custom, library],
+        );
+
+        let result = sync_files(project.path(), &offline_client(), false)
+            .await
+            .unwrap();
+
+        assert!(result.missing_dependencies.is_empty());
+        assert_eq!(read_lockfile(project.path()).unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn local_mod_is_filled_in_from_jar_metadata() {
+        let project = tempfile::tempdir().unwrap();
+        let mods_path = project.path().join("instance/mods");
+        fs::create_dir_all(&mods_path).unwrap();
+        let mut jar = zip::ZipWriter::new(fs::File::create(mods_path.join("custom.jar")).unwrap());
+        jar.start_file("fabric.mod.json", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        std::io::Write::write_all(
+            &mut jar,
+            br#"{"id": "custom", "name": "Custom Mod", "version": "1.2.3",
+                "depends": {"minecraft": ">=1.20", "fabricloader": "*", "fabric-api": "*"}}"#,
+        )
+        .unwrap();
+        jar.finish().unwrap();
+        write_project(project.path(), PathBuf::from("instance/mods"), vec![], &[]);
+
+        sync_files(project.path(), &offline_client(), false)
+            .await
+            .unwrap();
+
+        let lockfile = read_lockfile(project.path()).unwrap();
+        assert_eq!(lockfile[0].id, "custom");
+        assert_eq!(lockfile[0].name, "Custom Mod");
+        assert_eq!(lockfile[0].version, "1.2.3");
+        assert_eq!(lockfile[0].file_name, "custom.jar");
+        assert_eq!(lockfile[0].loaders, vec![GameLoader::Fabric]);
+        assert_eq!(lockfile[0].game_versions, vec![">=1.20".to_string()]);
+        assert_eq!(lockfile[0].dependencies.len(), 1);
+        assert_eq!(lockfile[0].dependencies[0].project_id, "fabric-api");
+        assert_eq!(read_config(project.path()).unwrap().mods[0].id, "custom");
+    }
+
+    #[tokio::test]
+    async fn absolute_mods_folder_is_used_as_is() {
+        let project = tempfile::tempdir().unwrap();
+        let instance = tempfile::tempdir().unwrap();
+        let sha512 = write_jar(instance.path(), "sodium (1).jar", b"sodium");
+        let lockfile = vec![lock_mod("sodium", "sodium.jar", sha512, 6)];
+        write_project(
+            project.path(),
+            instance.path().to_path_buf(),
+            vec![config_mod("sodium")],
+            &lockfile,
+        );
+
+        sync_files(project.path(), &offline_client(), false)
+            .await
+            .unwrap();
+
+        assert!(instance.path().join("sodium.jar").exists());
+        assert!(!project.path().join("sodium.jar").exists());
+    }
+
+    #[tokio::test]
+    async fn missing_files_are_removed_from_lockfile() {
+        let project = tempfile::tempdir().unwrap();
+        let lockfile = vec![lock_mod("sodium", "sodium.jar", "0".repeat(128), 6)];
+        write_project(
+            project.path(),
+            PathBuf::from("instance/mods"),
+            vec![config_mod("sodium")],
+            &lockfile,
+        );
+
+        let result = sync_files(project.path(), &offline_client(), false)
+            .await
+            .unwrap();
+
+        assert!(project.path().join("instance/mods").is_dir());
+        assert!(read_lockfile(project.path()).unwrap_or_default().is_empty());
+        assert_eq!(result.new_mods.len(), 1);
+        assert_eq!(result.new_mods[0].id, "sodium");
+    }
+
+    #[tokio::test]
+    async fn mods_for_the_other_side_stay_in_lockfile() {
+        let project = tempfile::tempdir().unwrap();
+        let mut iris = lock_mod("iris", "iris.jar", "0".repeat(128), 4);
+        iris.server_side = SideSupport::Unsupported;
+        let lockfile = vec![iris, lock_mod("lithium", "lithium.jar", "1".repeat(128), 7)];
+        write_project(
+            project.path(),
+            PathBuf::from("instance/mods"),
+            vec![config_mod("iris"), config_mod("lithium")],
+            &lockfile,
+        );
+        let mut config = read_config(project.path()).unwrap();
+        config.side = Side::Server;
+        save_config(project.path(), &config).unwrap();
+
+        let result = sync_files(project.path(), &offline_client(), false)
+            .await
+            .unwrap();
+
+        // Iris is client only, so it is not installed on a server. Lithium should be, so it is missing.
+        let lockfile = read_lockfile(project.path()).unwrap();
+        assert_eq!(lockfile.len(), 1);
+        assert_eq!(lockfile[0].id, "iris");
+        assert_eq!(result.new_mods.len(), 1);
+        assert_eq!(result.new_mods[0].id, "lithium");
+    }
+}
@@ -0,0 +1,356 @@
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+
+use crate::{
+    datatypes::{Config, GameLoader, LockMod, ProjectType},
+    errors::ModManError,
+    metadata::read_metadata,
+};
+
+/*
+    Finds mods in the mods folder that would stop the game from launching:
+        - More than one jar of the same project (e.g. two versions of a mod).
+        - More than one jar declaring the same internal mod id.
+        - Jars whose declared loaders don't include the loader in the config.
+
+    Only enabled jars are checked, as disabled ('.jar.disabled') ones are not loaded by the game.
+    Internal mod ids and loaders come from the metadata in each jar, falling back to the lockfile for the loaders.
+*/
+
+pub enum Conflict {
+    DuplicateProject {
+        name: String,
+        file_names: Vec<String>,
+    },
+    DuplicateModId {
+        mod_id: String,
+        file_names: Vec<String>,
+    },
+    LoaderMismatch {
+        file_name: String,
+        loaders: Vec<GameLoader>,
+        game_loader: GameLoader,
+    },
+}
+
+impl Conflict {
+    pub fn file_names(&self) -> Vec<String> {
+        match self {
+            Conflict::DuplicateProject { file_names, .. } => file_names.clone(),
+            Conflict::DuplicateModId { file_names, .. } => file_names.clone(),
+            Conflict::LoaderMismatch { file_name, .. } => vec![file_name.clone()],
+        }
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conflict::DuplicateProject { name, file_names } => write!(
+                f,
+                "'{}' is installed more than once: {}",
+                name,
+                file_names.join(", ")
+            ),
+            Conflict::DuplicateModId { mod_id, file_names } => write!(
+                f,
+                "Mod id '{}' is used by more than one jar: {}",
+                mod_id,
+                file_names.join(", ")
+            ),
+            Conflict::LoaderMismatch {
+                file_name,
+                loaders,
+                game_loader,
+            } => {
+                let loaders: Vec<String> = loaders.iter().map(|l| l.to_string()).collect();
+                write!(
+                    f,
+                    "'{}' is for {}, not {}",
+                    file_name,
+                    loaders.join(", "),
+                    game_loader
+                )
+            }
+        }
+    }
+}
+
+// Whether a jar declaring `loaders` can be loaded by `game_loader`.
+// Some loaders also load files made for another one (e.g. Quilt loads Fabric mods, Paper loads Spigot plugins).
+pub fn loader_supports(game_loader: &GameLoader, loaders: &[GameLoader]) -> bool {
+    let compatible: &[GameLoader] = match game_loader {
+        GameLoader::Quilt => &[GameLoader::Quilt, GameLoader::Fabric],
+        GameLoader::Spigot => &[GameLoader::Spigot, GameLoader::Bukkit],
+        GameLoader::Paper => &[GameLoader::Paper, GameLoader::Spigot, GameLoader::Bukkit],
+        GameLoader::Purpur => &[
+            GameLoader::Purpur,
+            GameLoader::Paper,
+            GameLoader::Spigot,
+            GameLoader::Bukkit,
+        ],
+        _ => std::slice::from_ref(game_loader),
+    };
+    loaders.iter().any(|loader| compatible.contains(loader))
+}
+
+pub fn find_conflicts(
+    dir: &Path,
+    config: &Config,
+    lockfile: &[LockMod],
+) -> Result<Vec<Conflict>, ModManError> {
+    let mods_path = config.project_path(dir, &ProjectType::Mod);
+    if !mods_path.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut file_names: Vec<String> = Vec::new();
+    for entry in fs::read_dir(&mods_path).map_err(ModManError::IoError)? {
+        let entry = entry.map_err(ModManError::IoError)?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if entry.path().is_file() && file_name.ends_with(".jar") {
+            file_names.push(file_name);
+        }
+    }
+    file_names.sort();
+
+    let mut projects: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new(); // project id -> name, files
+    let mut mod_ids: BTreeMap<String, Vec<String>> = BTreeMap::new(); // internal mod id -> files
+    let mut project_of_file: BTreeMap<String, String> = BTreeMap::new(); // file -> project id
+    let mut conflicts: Vec<Conflict> = Vec::new();
+
+    for file_name in &file_names {
+        let lock_mod = lockfile.iter().find(|m| {
+            m.project_type == ProjectType::Mod && !m.disabled && m.file_name == *file_name
+        });
+        let metadata = read_metadata(&mods_path.join(file_name));
+
+        if let Some(lock_mod) = lock_mod {
+            projects
+                .entry(lock_mod.id.clone())
+                .or_insert_with(|| (lock_mod.name.clone(), Vec::new()))
+                .1
+                .push(file_name.clone());
+            project_of_file.insert(file_name.clone(), lock_mod.id.clone());
+        }
+
+        let mut loaders = Vec::new();
+        if let Some(metadata) = metadata {
+            mod_ids
+                .entry(metadata.id)
+                .or_default()
+                .push(file_name.clone());
+            loaders = metadata.loaders;
+        }
+        if loaders.is_empty() {
+            loaders = lock_mod.map(|m| m.loaders.clone()).unwrap_or_default();
+        }
+        if !loaders.is_empty() && !loader_supports(&config.game_loader, &loaders) {
+            conflicts.push(Conflict::LoaderMismatch {
+                file_name: file_name.clone(),
+                loaders,
+                game_loader: config.game_loader.clone(),
+            });
+        }
+    }
+
+    for (name, project_files) in projects.into_values() {
+        if project_files.len() > 1 {
+            conflicts.push(Conflict::DuplicateProject {
+                name,
+                file_names: project_files,
+            });
+        }
+    }
+    for (mod_id, mod_files) in mod_ids {
+        // Jars of the same project are already reported as a duplicate project.
+        let same_project = mod_files.iter().all(|f| {
+            project_of_file.contains_key(f)
+                && project_of_file.get(f) == project_of_file.get(&mod_files[0])
+        });
+        if mod_files.len() > 1 && !same_project {
+            conflicts.push(Conflict::DuplicateModId {
+                mod_id,
+                file_names: mod_files,
+            });
+        }
+    }
+
+    Ok(conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Cursor, Write},
+        path::PathBuf,
+    };
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::*;
+    use crate::datatypes::{DownloadSettings, ModSources, ModpackSettings, ReleaseTypes, Side};
+
+    fn config(game_loader: GameLoader) -> Config {
+        Config {
+            game_version: "1.20.1".to_string(),
+            game_loader,
+            loader_version: None,
+            allowed_release_types: vec![ReleaseTypes::Release],
+            mods_folder: PathBuf::from("mods"),
+            resourcepacks_folder: PathBuf::from("resourcepacks"),
+            shaderpacks_folder: PathBuf::from("shaderpacks"),
+            datapacks_folder: PathBuf::from("world/datapacks"),
+            downloads: DownloadSettings::default(),
+            side: Side::Both,
+            modpack: ModpackSettings::default(),
+            instance: None,
+            mods: vec![],
+            profiles: Default::default(),
+        }
+    }
+
+    // A jar in the mods folder, with a fabric.mod.json or quilt.mod.json if `metadata` is given.
+    fn write_jar(dir: &Path, file_name: &str, metadata: Option<(&str, &str)>) {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        if let Some((name, contents)) = metadata {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        let bytes = zip.finish().unwrap().into_inner();
+        fs::create_dir_all(dir.join("mods")).unwrap();
+        fs::write(dir.join("mods").join(file_name), bytes).unwrap();
+    }
+
+    fn fabric_mod_json(id: &str) -> String {
+        format!(
+            r#"{{"schemaVersion": 1, "id": "{}", "version": "1.0"}}"#,
+            id
+        )
+    }
+
+    fn lock_mod(id: &str, file_name: &str, loaders: Vec<GameLoader>) -> LockMod {
+        LockMod {
+            name: id.to_string(),
+            source: ModSources::Modrinth,
+            id: id.to_string(),
+            version: "1.0.0".to_string(),
+            file_name: file_name.to_string(),
+            release_date: "Unknown".to_string(),
+            sha512: String::new(),
+            sha1: None,
+            download_url: "Unknown".to_string(),
+            file_id: None,
+            dependencies: vec![],
+            size: 0,
+            disabled: false,
+            project_type: ProjectType::Mod,
+            loaders,
+            game_versions: vec![],
+            client_side: Default::default(),
+            server_side: Default::default(),
+        }
+    }
+
+    #[test]
+    fn two_versions_of_a_project_are_one_duplicate() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = fabric_mod_json("sodium");
+        write_jar(
+            dir.path(),
+            "sodium-0.5.jar",
+            Some(("fabric.mod.json", &json)),
+        );
+        write_jar(
+            dir.path(),
+            "sodium-0.6.jar",
+            Some(("fabric.mod.json", &json)),
+        );
+        let lockfile = vec![
+            lock_mod("AANobbMI", "sodium-0.5.jar", vec![GameLoader::Fabric]),
+            lock_mod("AANobbMI", "sodium-0.6.jar", vec![GameLoader::Fabric]),
+        ];
+
+        let conflicts = find_conflicts(dir.path(), &config(GameLoader::Fabric), &lockfile).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert!(matches!(
+            &conflicts[0],
+            Conflict::DuplicateProject { name, file_names }
+                if name == "AANobbMI" && file_names == &["sodium-0.5.jar", "sodium-0.6.jar"]
+        ));
+    }
+
+    #[test]
+    fn jars_of_different_projects_with_the_same_mod_id_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = fabric_mod_json("sodium");
+        write_jar(dir.path(), "sodium.jar", Some(("fabric.mod.json", &json)));
+        write_jar(
+            dir.path(),
+            "sodium-fork.jar",
+            Some(("fabric.mod.json", &json)),
+        );
+        let lockfile = vec![lock_mod("AANobbMI", "sodium.jar", vec![GameLoader::Fabric])];
+
+        let conflicts = find_conflicts(dir.path(), &config(GameLoader::Fabric), &lockfile).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert!(matches!(
+            &conflicts[0],
+            Conflict::DuplicateModId { mod_id, file_names }
+                if mod_id == "sodium" && file_names == &["sodium-fork.jar", "sodium.jar"]
+        ));
+        assert_eq!(
+            conflicts[0].to_string(),
+            "Mod id 'sodium' is used by more than one jar: sodium-fork.jar, sodium.jar"
+        );
+    }
+
+    #[test]
+    fn jars_for_other_loaders_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let quilt_json = r#"{"quilt_loader": {"id": "qsl", "version": "1.0"}}"#;
+        write_jar(dir.path(), "qsl.jar", Some(("quilt.mod.json", quilt_json)));
+        // No metadata in the jar, so the loaders in the lockfile are used.
+        write_jar(dir.path(), "jei.jar", None);
+        write_jar(dir.path(), "unknown.jar", None);
+        let lockfile = vec![lock_mod("jei", "jei.jar", vec![GameLoader::Forge])];
+
+        let conflicts = find_conflicts(dir.path(), &config(GameLoader::Fabric), &lockfile).unwrap();
+        let file_names: Vec<Vec<String>> = conflicts.iter().map(|c| c.file_names()).collect();
+        assert_eq!(file_names, vec![vec!["jei.jar"], vec!["qsl.jar"]]);
+        assert!(conflicts
+            .iter()
+            .all(|c| matches!(c, Conflict::LoaderMismatch { .. })));
+        assert_eq!(
+            conflicts[0].to_string(),
+            "'jei.jar' is for forge, not fabric"
+        );
+    }
+
+    #[test]
+    fn loaders_which_load_other_loaders_mods_do_not_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = fabric_mod_json("sodium");
+        write_jar(dir.path(), "sodium.jar", Some(("fabric.mod.json", &json)));
+
+        let conflicts = find_conflicts(dir.path(), &config(GameLoader::Quilt), &[]).unwrap();
+        assert!(conflicts.is_empty());
+        assert!(loader_supports(&GameLoader::Paper, &[GameLoader::Bukkit]));
+        assert!(!loader_supports(&GameLoader::Fabric, &[GameLoader::Quilt]));
+    }
+
+    #[test]
+    fn disabled_jars_and_missing_mods_folders_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(GameLoader::Fabric);
+        assert!(find_conflicts(dir.path(), &config, &[]).unwrap().is_empty());
+
+        let json = fabric_mod_json("sodium");
+        write_jar(dir.path(), "sodium.jar", Some(("fabric.mod.json", &json)));
+        write_jar(
+            dir.path(),
+            "sodium-old.jar.disabled",
+            Some(("fabric.mod.json", &json)),
+        );
+        assert!(find_conflicts(dir.path(), &config, &[]).unwrap().is_empty());
+    }
+}
@@ -1,19 +1,193 @@
-use std::{fmt, str::FromStr};
+use std::{collections::BTreeMap, fmt, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 
 // Config File struct
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
     pub game_version: String,
     pub game_loader: GameLoader,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader_version: Option<String>, // Version of the loader itself, used in exported modpacks.
     pub allowed_release_types: Vec<ReleaseTypes>,
     pub mods_folder: std::path::PathBuf,
+    #[serde(default = "default_resourcepacks_folder")]
+    pub resourcepacks_folder: std::path::PathBuf,
+    #[serde(default = "default_shaderpacks_folder")]
+    pub shaderpacks_folder: std::path::PathBuf,
+    #[serde(default = "default_datapacks_folder")]
+    pub datapacks_folder: std::path::PathBuf,
+    #[serde(default, skip_serializing_if = "DownloadSettings::is_default")]
+    pub downloads: DownloadSettings,
+    #[serde(default, skip_serializing_if = "Side::is_both")]
+    pub side: Side, // Only mods for this side are installed.
+    #[serde(default, skip_serializing_if = "ModpackSettings::is_default")]
+    pub modpack: ModpackSettings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<std::path::PathBuf>, // Prism Launcher or MultiMC instance, whose mmc-pack.json follows game_version.
     pub mods: Vec<Mod>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+// A named profile ([profiles.<name>] table in modman.toml), selected with '--profile <name>'.
+// Settings that are not given are taken from the top of modman.toml.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Profile {
+    #[serde(default = "default_inherit")]
+    pub inherit: bool, // Start from the mods at the top of modman.toml, rather than from no mods.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_loader: Option<GameLoader>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mods_folder: Option<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resourcepacks_folder: Option<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shaderpacks_folder: Option<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datapacks_folder: Option<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub side: Option<Side>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lockfile: Option<String>, // File name of the lockfile. Default is 'modman.<name>.lock'.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove: Vec<String>, // Ids of inherited mods that are not in this profile.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub add: Vec<Mod>, // Mods only in this profile. These replace inherited mods with the same id.
+}
+
+fn default_inherit() -> bool {
+    true
+}
+
+pub fn default_resourcepacks_folder() -> std::path::PathBuf {
+    std::path::PathBuf::from("./resourcepacks")
+}
+
+pub fn default_shaderpacks_folder() -> std::path::PathBuf {
+    std::path::PathBuf::from("./shaderpacks")
+}
+
+pub fn default_datapacks_folder() -> std::path::PathBuf {
+    std::path::PathBuf::from("./world/datapacks")
+}
+
+impl Config {
+    // Full path of the folder that projects of this type are installed to.
+    // Relative folders are relative to the project directory, not the working directory.
+    pub fn project_path(
+        &self,
+        dir: &std::path::Path,
+        project_type: &ProjectType,
+    ) -> std::path::PathBuf {
+        match project_type {
+            ProjectType::Mod => dir.join(&self.mods_folder),
+            ProjectType::ResourcePack => dir.join(&self.resourcepacks_folder),
+            ProjectType::Shader => dir.join(&self.shaderpacks_folder),
+            ProjectType::Datapack => dir.join(&self.datapacks_folder),
+        }
+    }
+
+    // Whether a lockfile entry is installed for the side of this config.
+    // The side set on the mod in the config comes first, then the sides the project supports.
+    pub fn wants(&self, lock_mod: &LockMod) -> bool {
+        let mod_side = self
+            .mods
+            .iter()
+            .find(|m| m.id == lock_mod.id)
+            .and_then(|m| m.side.clone());
+        match (&self.side, mod_side) {
+            (Side::Both, _) => true,
+            (side, Some(mod_side)) => mod_side == Side::Both || mod_side == *side,
+            (Side::Client, None) => lock_mod.client_side != SideSupport::Unsupported,
+            (Side::Server, None) => lock_mod.server_side != SideSupport::Unsupported,
+        }
+    }
+
+    // Full path of the installed file of a lockfile entry.
+    pub fn installed_path(&self, dir: &std::path::Path, lock_mod: &LockMod) -> std::path::PathBuf {
+        self.project_path(dir, &lock_mod.project_type)
+            .join(lock_mod.installed_file_name())
+    }
+}
+
+// Download settings ([downloads] table in modman.toml)
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct DownloadSettings {
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed_limit_kib: Option<u64>, // KiB/s, shared by all downloads. No limit if not set.
+}
+
+fn default_max_concurrent() -> usize {
+    4
+}
+
+impl Default for DownloadSettings {
+    fn default() -> Self {
+        DownloadSettings {
+            max_concurrent: default_max_concurrent(),
+            speed_limit_kib: None,
+        }
+    }
+}
+
+impl DownloadSettings {
+    pub fn is_default(&self) -> bool {
+        *self == DownloadSettings::default()
+    }
+}
+
+// Modpack settings ([modpack] table in modman.toml), used by 'modman export'.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct ModpackSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>, // Name of the project directory if not set.
+    #[serde(default = "default_modpack_version")]
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default = "default_overrides_folder")]
+    pub overrides_folder: std::path::PathBuf, // Copied as-is into the game directory (e.g. config files).
+}
+
+fn default_modpack_version() -> String {
+    "1.0.0".to_string()
+}
+
+pub fn default_overrides_folder() -> std::path::PathBuf {
+    std::path::PathBuf::from("./overrides")
+}
+
+impl Default for ModpackSettings {
+    fn default() -> Self {
+        ModpackSettings {
+            name: None,
+            version: default_modpack_version(),
+            summary: None,
+            author: None,
+            overrides_folder: default_overrides_folder(),
+        }
+    }
+}
+
+impl ModpackSettings {
+    pub fn is_default(&self) -> bool {
+        *self == ModpackSettings::default()
+    }
 }
 
 // Game loader enums
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub enum GameLoader {
     Bukkit,
     BungeeCord,
@@ -100,14 +274,164 @@ impl std::str::FromStr for GameLoader {
     }
 }
 
+// Project types, each installed to its own folder.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+pub enum ProjectType {
+    #[default]
+    Mod,
+    ResourcePack,
+    Shader,
+    Datapack,
+}
+
+impl ProjectType {
+    pub const ALL: [ProjectType; 4] = [
+        ProjectType::Mod,
+        ProjectType::ResourcePack,
+        ProjectType::Shader,
+        ProjectType::Datapack,
+    ];
+
+    // Extension of the files of this project type.
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            ProjectType::Mod => "jar",
+            ProjectType::ResourcePack | ProjectType::Shader | ProjectType::Datapack => "zip",
+        }
+    }
+
+    // Loaders to search for on Modrinth. Only mods depend on the game loader.
+    pub fn loaders(&self, game_loader: &GameLoader) -> Vec<GameLoader> {
+        match self {
+            ProjectType::Mod => vec![game_loader.clone()],
+            ProjectType::ResourcePack => vec![GameLoader::Minecraft],
+            ProjectType::Shader => match game_loader {
+                GameLoader::Fabric | GameLoader::Quilt => {
+                    vec![GameLoader::Iris, GameLoader::Canvas, GameLoader::OptiFine]
+                }
+                _ => vec![GameLoader::Iris, GameLoader::OptiFine],
+            },
+            ProjectType::Datapack => vec![GameLoader::Datapack],
+        }
+    }
+}
+
+impl fmt::Display for ProjectType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectType::Mod => write!(f, "mod"),
+            ProjectType::ResourcePack => write!(f, "resourcepack"),
+            ProjectType::Shader => write!(f, "shader"),
+            ProjectType::Datapack => write!(f, "datapack"),
+        }
+    }
+}
+
+impl std::str::FromStr for ProjectType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mod" => Ok(ProjectType::Mod),
+            "resourcepack" => Ok(ProjectType::ResourcePack),
+            "shader" => Ok(ProjectType::Shader),
+            "datapack" => Ok(ProjectType::Datapack),
+            _ => Err(format!("Invalid project type: {}", s)),
+        }
+    }
+}
+
 // Mods struct
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Mod {
     pub source: ModSources,
     pub id: String,
     pub name: String,
+    #[serde(default)]
+    pub project_type: ProjectType,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool, // Disabled mods stay installed, as '<file>.jar.disabled'.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub side: Option<Side>, // Overrides the sides the project supports.
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// Side a config, profile or mod is for.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Client,
+    Server,
+    #[default]
+    Both,
+}
+
+impl Side {
+    pub fn is_both(&self) -> bool {
+        *self == Side::Both
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Client => write!(f, "client"),
+            Side::Server => write!(f, "server"),
+            Side::Both => write!(f, "both"),
+        }
+    }
 }
 
+impl std::str::FromStr for Side {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "client" => Ok(Side::Client),
+            "server" => Ok(Side::Server),
+            "both" => Ok(Side::Both),
+            _ => Err(format!("Invalid side: {}", s)),
+        }
+    }
+}
+
+// Whether a project runs on one side (Modrinth's client_side and server_side).
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum SideSupport {
+    Required,
+    Optional,
+    Unsupported,
+    #[default]
+    Unknown,
+}
+
+impl SideSupport {
+    pub fn is_unknown(&self) -> bool {
+        *self == SideSupport::Unknown
+    }
+}
+
+impl std::str::FromStr for SideSupport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "required" => Ok(SideSupport::Required),
+            "optional" => Ok(SideSupport::Optional),
+            "unsupported" => Ok(SideSupport::Unsupported),
+            "unknown" => Ok(SideSupport::Unknown),
+            _ => Err(format!("Invalid side support: {}", s)),
+        }
+    }
+}
+
+// Suffix launchers (Prism, MultiMC, ...) add to the file name of disabled mods.
+pub const DISABLED_SUFFIX: &str = ".disabled";
+
 // Mod sources
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub enum ModSources {
@@ -178,9 +502,36 @@ pub struct LockMod {
     pub file_name: String,
     pub release_date: String,
     pub sha512: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
     pub download_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<u64>, // CurseForge file id, for CurseForge mods.
     pub dependencies: Vec<LockDependency>,
     pub size: u64,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub disabled: bool,
+    #[serde(default)]
+    pub project_type: ProjectType,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub loaders: Vec<GameLoader>, // Loaders the file supports, if known.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub game_versions: Vec<String>, // Game versions the file supports, if known.
+    #[serde(default, skip_serializing_if = "SideSupport::is_unknown")]
+    pub client_side: SideSupport,
+    #[serde(default, skip_serializing_if = "SideSupport::is_unknown")]
+    pub server_side: SideSupport,
+}
+
+impl LockMod {
+    // Name of the file in the mods folder, which has DISABLED_SUFFIX if the mod is disabled.
+    pub fn installed_file_name(&self) -> String {
+        if self.disabled {
+            format!("{}{}", self.file_name, DISABLED_SUFFIX)
+        } else {
+            self.file_name.clone()
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
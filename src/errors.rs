@@ -12,8 +12,16 @@ pub enum ModManError {
     CannotFindMod(String),
     IncompatibleDependency(Box<dyn std::error::Error + Send + Sync>),
     NoMods(String),
-    TransactionDownloadError(Box<dyn std::error::Error + Send>),
+    DownloadsFailed(usize),
     FileIsEmpty,
+    JsonError(serde_json::Error),
+    IncompleteTransaction(String),
+    ConflictsFound(usize),
+    VerificationFailed(usize),
+    ConfigAlreadyExists,
+    NotOnCurseForge(usize),
+    NotUpgradable(usize),
+    CurseForgeNotSupported(String),
 }
 
 impl std::fmt::Display for ModManError {
@@ -35,10 +43,51 @@ impl std::fmt::Display for ModManError {
                 write!(f, "Incompatible mods:       '{}'", err)
             }
             ModManError::NoMods(action) => write!(f, "No mods to {}.", action),
-            ModManError::TransactionDownloadError(err) => {
-                write!(f, "Transaction/download error: {}", err)
+            ModManError::DownloadsFailed(count) => {
+                write!(
+                    f,
+                    "Transaction/download error: {} mod(s) failed to download.",
+                    count
+                )
             }
             ModManError::FileIsEmpty => write!(f, "File is empty."),
+            ModManError::JsonError(err) => write!(f, "JSON error: {}", err),
+            ModManError::IncompleteTransaction(id) => {
+                write!(f, "Transaction '{}' is incomplete.", id)
+            }
+            ModManError::ConflictsFound(count) => {
+                write!(f, "Found {} conflict(s) in the mods folder.", count)
+            }
+            ModManError::VerificationFailed(count) => {
+                write!(
+                    f,
+                    "Verification failed: {} file(s) do not match the lockfile.",
+                    count
+                )
+            }
+            ModManError::ConfigAlreadyExists => {
+                write!(
+                    f,
+                    "modman.toml already exists. Use '--force' to overwrite it."
+                )
+            }
+            ModManError::NotOnCurseForge(count) => {
+                write!(f, "{} mod(s) have no CurseForge equivalent.", count)
+            }
+            ModManError::NotUpgradable(count) => {
+                write!(
+                    f,
+                    "{} mod(s) have no version for the new game version.",
+                    count
+                )
+            }
+            ModManError::CurseForgeNotSupported(name) => {
+                write!(
+                    f,
+                    "'{}' is a CurseForge mod. Adding CurseForge mods is not supported yet.",
+                    name
+                )
+            }
         }
     }
 }
@@ -67,8 +116,16 @@ impl ModManError {
             ModManError::CannotFindMod(_) => 10,
             ModManError::IncompatibleDependency(_) => 11,
             ModManError::NoMods(_) => 12,
-            ModManError::TransactionDownloadError(_) => 13,
+            ModManError::DownloadsFailed(_) => 13,
             ModManError::FileIsEmpty => 14,
+            ModManError::JsonError(_) => 15,
+            ModManError::IncompleteTransaction(_) => 16,
+            ModManError::ConflictsFound(_) => 17,
+            ModManError::VerificationFailed(_) => 18,
+            ModManError::ConfigAlreadyExists => 19,
+            ModManError::NotOnCurseForge(_) => 20,
+            ModManError::NotUpgradable(_) => 21,
+            ModManError::CurseForgeNotSupported(_) => 22,
         }
     }
 }
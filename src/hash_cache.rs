@@ -0,0 +1,271 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::ModManError, install::calculate_sha512, utils::modman_dir};
+
+/*
+    Persistent index of file hashes (.modman/hash_cache.toml), so unchanged files are not hashed again on every sync.
+
+    A cached hash is reused as long as the file's path, size and modification time are unchanged.
+    Files that did change are hashed in parallel, across all available cores.
+*/
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct HashCache {
+    files: HashMap<String, HashCacheEntry>, // Keyed by path
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct HashCacheEntry {
+    size: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+    sha512: String,
+}
+
+fn cache_path(dir: &Path) -> PathBuf {
+    modman_dir(dir).join("hash_cache.toml")
+}
+
+impl HashCache {
+    // A missing or unreadable cache is not an error, everything is just hashed again.
+    pub fn load(dir: &Path) -> HashCache {
+        fs::read_to_string(cache_path(dir))
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), ModManError> {
+        fs::create_dir_all(modman_dir(dir)).map_err(ModManError::IoError)?;
+        let cache_data = toml::to_string(self).map_err(ModManError::SerializationError)?;
+        fs::write(cache_path(dir), cache_data).map_err(ModManError::IoError)
+    }
+
+    // Returns the sha512 of each file, in the same order as `paths`.
+    // Entries of other files are kept (e.g. the mods folders of other profiles), unless the file no longer exists.
+    pub fn hash_files(&mut self, paths: &[PathBuf]) -> Result<Vec<String>, io::Error> {
+        let mut entries: Vec<HashCacheEntry> = Vec::with_capacity(paths.len());
+        let mut to_hash: Vec<usize> = Vec::new();
+
+        for (index, path) in paths.iter().enumerate() {
+            let metadata = fs::metadata(path)?;
+            let modified = metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let key = path.to_string_lossy().to_string();
+
+            match self.files.get(&key) {
+                Some(entry)
+                    if entry.size == metadata.len()
+                        && entry.modified_secs == modified.as_secs()
+                        && entry.modified_nanos == modified.subsec_nanos() =>
+                {
+                    entries.push(entry.clone());
+                }
+                _ => {
+                    entries.push(HashCacheEntry {
+                        size: metadata.len(),
+                        modified_secs: modified.as_secs(),
+                        modified_nanos: modified.subsec_nanos(),
+                        sha512: String::new(), // Filled in below.
+                    });
+                    to_hash.push(index);
+                }
+            }
+        }
+
+        let hashes = hash_in_parallel(paths, &to_hash)?;
+        for (index, sha512) in to_hash.into_iter().zip(hashes) {
+            entries[index].sha512 = sha512;
+        }
+
+        self.files.retain(|key, _| Path::new(key).exists());
+        let mut result: Vec<String> = Vec::with_capacity(paths.len());
+        for (path, entry) in paths.iter().zip(entries) {
+            result.push(entry.sha512.clone());
+            self.files.insert(path.to_string_lossy().to_string(), entry);
+        }
+        Ok(result)
+    }
+}
+
+// Hashes every file in `paths` without the cache, for when cached hashes can't be trusted (e.g. `modman verify`).
+pub fn hash_files_uncached(paths: &[PathBuf]) -> Result<Vec<String>, io::Error> {
+    let indices: Vec<usize> = (0..paths.len()).collect();
+    hash_in_parallel(paths, &indices)
+}
+
+// Hashes `paths[i]` for every i in `indices`, spreading the work over all cores. Returns hashes in the order of `indices`.
+fn hash_in_parallel(paths: &[PathBuf], indices: &[usize]) -> Result<Vec<String>, io::Error> {
+    if indices.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(indices.len());
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<String, io::Error>>>> =
+        Mutex::new((0..indices.len()).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let position = next.fetch_add(1, Ordering::SeqCst);
+                if position >= indices.len() {
+                    break;
+                }
+                let hash = calculate_sha512(&paths[indices[position]]);
+                if let Ok(mut results) = results.lock() {
+                    results[position] = Some(hash);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .map_err(|_| io::Error::other("A hashing thread panicked"))?
+        .into_iter()
+        .map(|hash| hash.unwrap_or_else(|| Err(io::Error::other("File was not hashed"))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs::File, time::Duration};
+
+    fn write_file(dir: &Path, name: &str, contents: &[u8]) -> PathBuf {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn set_modified(path: &Path, modified: std::time::SystemTime) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    // Replaces the cached hash of `path`, so tests can tell whether it was reused or the file was hashed again.
+    fn tamper(cache: &mut HashCache, path: &Path) {
+        let key = path.to_string_lossy().to_string();
+        cache.files.get_mut(&key).unwrap().sha512 = "stale".to_string();
+    }
+
+    #[test]
+    fn unchanged_files_are_not_hashed_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let sodium = write_file(dir.path(), "sodium.jar", b"sodium");
+        let mut cache = HashCache::default();
+        let hashes = cache.hash_files(std::slice::from_ref(&sodium)).unwrap();
+        assert_eq!(hashes, vec![calculate_sha512(&sodium).unwrap()]);
+
+        tamper(&mut cache, &sodium);
+        let hashes = cache.hash_files(std::slice::from_ref(&sodium)).unwrap();
+        assert_eq!(hashes, vec!["stale".to_string()]);
+    }
+
+    #[test]
+    fn files_are_hashed_again_when_their_size_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let sodium = write_file(dir.path(), "sodium.jar", b"sodium");
+        let modified = fs::metadata(&sodium).unwrap().modified().unwrap();
+        let mut cache = HashCache::default();
+        cache.hash_files(std::slice::from_ref(&sodium)).unwrap();
+        tamper(&mut cache, &sodium);
+
+        // Same modification time, so only the size tells the file changed.
+        fs::write(&sodium, b"sodium 0.6").unwrap();
+        set_modified(&sodium, modified);
+        let hashes = cache.hash_files(std::slice::from_ref(&sodium)).unwrap();
+        assert_eq!(hashes, vec![calculate_sha512(&sodium).unwrap()]);
+    }
+
+    #[test]
+    fn files_are_hashed_again_when_their_modification_time_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let sodium = write_file(dir.path(), "sodium.jar", b"sodium");
+        let modified = fs::metadata(&sodium).unwrap().modified().unwrap();
+        let mut cache = HashCache::default();
+        cache.hash_files(std::slice::from_ref(&sodium)).unwrap();
+        tamper(&mut cache, &sodium);
+
+        // Same size, different contents.
+        fs::write(&sodium, b"SODIUM").unwrap();
+        set_modified(&sodium, modified + Duration::from_secs(1));
+        let hashes = cache.hash_files(std::slice::from_ref(&sodium)).unwrap();
+        assert_eq!(hashes, vec![calculate_sha512(&sodium).unwrap()]);
+    }
+
+    #[test]
+    fn saved_caches_are_loaded_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let sodium = write_file(&dir.path().join("mods"), "sodium.jar", b"sodium");
+        let mut cache = HashCache::default();
+        cache.hash_files(std::slice::from_ref(&sodium)).unwrap();
+        tamper(&mut cache, &sodium);
+        cache.save(dir.path()).unwrap();
+
+        let mut loaded = HashCache::load(dir.path());
+        let hashes = loaded.hash_files(std::slice::from_ref(&sodium)).unwrap();
+        assert_eq!(hashes, vec!["stale".to_string()]);
+
+        // An unreadable cache is dropped, and files are hashed again.
+        fs::write(cache_path(dir.path()), "not a cache").unwrap();
+        let mut loaded = HashCache::load(dir.path());
+        let hashes = loaded.hash_files(std::slice::from_ref(&sodium)).unwrap();
+        assert_eq!(hashes, vec![calculate_sha512(&sodium).unwrap()]);
+    }
+
+    #[test]
+    fn uncached_hashes_are_in_the_order_of_the_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<PathBuf> = (0..20)
+            .map(|i| write_file(dir.path(), &format!("{}.jar", i), i.to_string().as_bytes()))
+            .collect();
+        let expected: Vec<String> = paths.iter().map(|p| calculate_sha512(p).unwrap()).collect();
+
+        assert_eq!(hash_files_uncached(&paths).unwrap(), expected);
+        assert!(hash_files_uncached(&[dir.path().join("missing.jar")]).is_err());
+    }
+
+    #[test]
+    fn entries_of_other_folders_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let sodium = write_file(&dir.path().join("mods"), "sodium.jar", b"sodium");
+        let lithium = write_file(&dir.path().join("server/mods"), "lithium.jar", b"lithium");
+        let iris = write_file(&dir.path().join("server/mods"), "iris.jar", b"iris");
+        let mut cache = HashCache::default();
+        cache.hash_files(&[lithium.clone(), iris.clone()]).unwrap();
+
+        // Another profile only hashes its own mods folder. Files that are gone are dropped.
+        fs::remove_file(&iris).unwrap();
+        cache.hash_files(std::slice::from_ref(&sodium)).unwrap();
+
+        let key = |path: &PathBuf| path.to_string_lossy().to_string();
+        assert!(cache.files.contains_key(&key(&sodium)));
+        assert!(cache.files.contains_key(&key(&lithium)));
+        assert!(!cache.files.contains_key(&key(&iris)));
+    }
+}
@@ -1,15 +1,28 @@
+use colored::Colorize;
 use futures::StreamExt;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use reqwest::Client;
+use reqwest::{header::RANGE, Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
 use sha2::{Digest, Sha512};
 use std::{
     error::Error,
-    fs::{self, File},
+    fs::{self, File, OpenOptions},
     io::{self, Read, Write},
-    path::PathBuf,
-    sync::Arc,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{Mutex, Semaphore};
+
+use crate::{
+    alert,
+    datatypes::{DownloadSettings, LockMod},
+    info,
 };
-use terminal_size::{terminal_size, Width};
 
 // Solve issue with returning string errors
 #[derive(Debug)]
@@ -25,44 +38,120 @@ impl<'a> std::fmt::Display for StrError<'a> {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DownloadTask {
+    pub name: String,
+    pub url: String,
+    pub dest: PathBuf,
+    pub sha512: String, // Empty if not known yet (CurseForge only gives sha1), in which case sha1 is checked.
+    pub sha1: Option<String>,
+    #[serde(default)]
+    pub size: u64,
+    pub replaces: Option<PathBuf>, // Older version of the same mod, removed once the new file is in place.
+}
+
+// Downloads are streamed into a staging folder next to their destination (so the final rename stays on the same file system),
+// and only moved into place once verified. Loaders ignore the folder, as well as the '.part' files inside it.
+pub fn staging_path(dest: &Path) -> PathBuf {
+    let file_name = dest
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    dest.parent()
+        .unwrap_or(Path::new("."))
+        .join(".modman-staging")
+        .join(format!("{}.part", file_name))
+}
+
+// Removes the older version of a mod that a download replaces, if it is still there.
+pub fn remove_replaced_file(task: &DownloadTask) -> Result<(), io::Error> {
+    if let Some(replaces) = &task.replaces {
+        if *replaces != task.dest && replaces.exists() {
+            fs::remove_file(replaces)?;
+        }
+    }
+    Ok(())
+}
+
+// Limits the combined speed of every download it is shared with.
+pub struct RateLimiter {
+    bytes_per_second: u64,
+    state: Mutex<(Instant, u64)>, // (time of the first download, bytes downloaded since)
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_second: u64) -> Self {
+        RateLimiter {
+            bytes_per_second: bytes_per_second.max(1),
+            state: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    // Waits until `bytes` more can be downloaded without going over the limit.
+    async fn consume(&self, bytes: u64) {
+        // The bytes are counted under the lock, so each download waits for everything counted before it.
+        // The lock is released before sleeping, so other downloads can count their bytes in the meantime.
+        let wait = {
+            let mut state = self.state.lock().await;
+            state.1 += bytes;
+            let expected = Duration::from_secs_f64(state.1 as f64 / self.bytes_per_second as f64);
+            expected.saturating_sub(state.0.elapsed())
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
 pub async fn download_mod(
     client: &Client,
-    url: &str,
-    dest: &PathBuf,
-    mod_name: &str,
-    multi_pb: &MultiProgress,
-    hash: &str,
+    task: &DownloadTask,
+    pb: &ProgressBar,
+    rate_limiter: Option<&RateLimiter>,
 ) -> Result<(), Box<dyn Error + Send>> {
-    let response = match client.get(url).send().await {
+    let staging = staging_path(&task.dest);
+    if let Some(staging_dir) = staging.parent() {
+        if let Err(err) = fs::create_dir_all(staging_dir) {
+            pb.finish_with_message(format!("Error creating staging folder: {}", err));
+            return Err(Box::new(err));
+        }
+    }
+
+    // Resume a partial download left in the staging folder by an earlier, interrupted attempt.
+    let resume_from = fs::metadata(&staging).map(|m| m.len()).unwrap_or(0);
+    let mut request = client.get(&task.url);
+    if resume_from > 0 {
+        request = request.header(RANGE, format!("bytes={}-", resume_from));
+    }
+
+    let response = match request.send().await {
         Ok(resp) => resp,
         Err(err) => {
+            pb.finish_with_message(format!("Error downloading: {}", err));
             return Err(Box::new(err));
         }
     };
 
-    let terminal_width = match terminal_size() {
-        Some((Width(width), _)) => width as usize,
-        None => 80, // Default to 80 if terminal size cannot be determined
-    };
-
-    let pb = multi_pb.add(ProgressBar::new(terminal_width as u64 - 22)); // Adjust the width here
-    pb.set_style(
-        ProgressStyle::with_template(
-            " {spinner:.green} [{msg}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})",
-        )
-        .unwrap()
-        .progress_chars("#>-"),
-    );
-
-    let display_name = if mod_name.len() > 30 {
-        format!("{}...", &mod_name[..27])
-    } else {
-        format!("{:<30}", mod_name)
+    let (file, downloaded) = match response.status() {
+        StatusCode::PARTIAL_CONTENT => {
+            (OpenOptions::new().append(true).open(&staging), resume_from)
+        }
+        // The partial download is already complete.
+        StatusCode::RANGE_NOT_SATISFIABLE if resume_from > 0 => {
+            (OpenOptions::new().append(true).open(&staging), resume_from)
+        }
+        status if status.is_success() => (File::create(&staging), 0),
+        status => {
+            pb.finish_with_message(format!("Received unexpected status code: {}", status));
+            return Err(Box::new(io::Error::other(format!(
+                "Received unexpected status code: {}",
+                status
+            ))));
+        }
     };
+    let complete = response.status() == StatusCode::RANGE_NOT_SATISFIABLE;
 
-    pb.set_message(display_name.to_string());
-
-    let mut file = match File::create(dest) {
+    let mut file = match file {
         Ok(result) => result,
         Err(err) => {
             pb.finish_with_message(format!("Error creating file: {}", err));
@@ -70,65 +159,281 @@ pub async fn download_mod(
         }
     };
 
-    let mut downloaded: u64 = 0;
-    let mut stream = response.bytes_stream();
-
-    while let Some(item) = stream.next().await {
-        let chunk = match item {
-            Ok(result) => result,
-            Err(err) => {
-                pb.finish_with_message(format!("Error downloading: {}", err));
-                return Err(Box::new(err));
+    let total_size = match response.content_length() {
+        Some(length) if !complete => {
+            // Abort before downloading anything if the server is about to send a different file.
+            if task.size > 0 && downloaded + length != task.size {
+                pb.finish_with_message("Size mismatch!".to_string());
+                let _ = fs::remove_file(&staging);
+                return Err(Box::new(io::Error::other(format!(
+                    "Size mismatch! Expected {} bytes, server reported {} bytes",
+                    task.size,
+                    downloaded + length
+                ))));
             }
-        };
-        if let Err(e) = file.write_all(&chunk) {
-            pb.finish_with_message(format!("Error writing to file: {}", e));
+            downloaded + length
+        }
+        _ => task.size.max(downloaded),
+    };
+    pb.set_length(total_size);
+    pb.set_position(downloaded);
+    pb.set_style(download_style());
+    if downloaded > 0 {
+        pb.set_message(format!("{} (resumed)", display_name(&task.name, 20)));
+    }
+
+    // Hashes are computed on the chunks as they are written, so verifying does not need to read the file again.
+    // Only the bytes of a resumed download (written by an earlier attempt) are read back from disk.
+    let mut hashes = StreamHashes::new();
+    if downloaded > 0 {
+        if let Err(e) = hashes.update_from_file(&staging) {
+            pb.finish_with_message(format!("Error reading partial download: {}", e));
             return Err(Box::new(e));
         }
-        downloaded += chunk.len() as u64;
-        pb.set_position(downloaded);
     }
-    match verify_file(dest, hash) {
-        Ok(true) => Ok(()),
-        Ok(false) => {
-            pb.finish_with_message("Hash Sum mismatch!".to_string());
-            match fs::remove_file(dest) {
+
+    let mut downloaded = downloaded;
+    let mut stream = response.bytes_stream();
+
+    // Nothing left to download if the partial download was already complete.
+    if !complete {
+        while let Some(item) = stream.next().await {
+            let chunk = match item {
                 Ok(result) => result,
                 Err(err) => {
-                    pb.finish_with_message(format!("Error deleting erroneous file: {}", err));
+                    pb.finish_with_message(format!("Error downloading: {}", err));
                     return Err(Box::new(err));
                 }
             };
-            return Err(Box::new(StrError("Hash Sum mismatch!")));
+            if let Some(rate_limiter) = rate_limiter {
+                rate_limiter.consume(chunk.len() as u64).await;
+            }
+            if let Err(e) = file.write_all(&chunk) {
+                pb.finish_with_message(format!("Error writing to file: {}", e));
+                return Err(Box::new(e));
+            }
+            hashes.update(&chunk);
+            downloaded += chunk.len() as u64;
+            pb.set_position(downloaded);
         }
-        Err(e) => {
-            pb.finish_with_message(format!("Error verifying file: {}", e));
-            Err(Box::new(e))
+    }
+    drop(file);
+
+    let (sha512, sha1) = hashes.finalize();
+    let sha512_matches = sha512 == task.sha512 || (task.sha512.is_empty() && task.sha1.is_some());
+    let sha1_matches = task.sha1.as_ref().is_none_or(|expected| *expected == sha1);
+    if !sha512_matches || !sha1_matches {
+        pb.finish_with_message("Hash Sum mismatch!".to_string());
+        match fs::remove_file(&staging) {
+            Ok(result) => result,
+            Err(err) => {
+                pb.finish_with_message(format!("Error deleting erroneous file: {}", err));
+                return Err(Box::new(err));
+            }
+        };
+        return Err(Box::new(StrError("Hash Sum mismatch!")));
+    }
+
+    // Move the verified file into place, then remove the older version it replaces.
+    if let Err(err) = fs::rename(&staging, &task.dest) {
+        pb.finish_with_message(format!("Error moving file into place: {}", err));
+        return Err(Box::new(err));
+    }
+    if let Err(err) = remove_replaced_file(task) {
+        pb.finish_with_message(format!("Error removing older version: {}", err));
+        return Err(Box::new(err));
+    }
+    pb.finish();
+    Ok(())
+}
+
+// SHA-512 and SHA-1 of a download, computed incrementally.
+struct StreamHashes {
+    sha512: Sha512,
+    sha1: Sha1,
+}
+
+impl StreamHashes {
+    fn new() -> Self {
+        StreamHashes {
+            sha512: Sha512::new(),
+            sha1: Sha1::new(),
+        }
+    }
+
+    fn update(&mut self, chunk: &[u8]) {
+        self.sha512.update(chunk);
+        self.sha1.update(chunk);
+    }
+
+    fn update_from_file(&mut self, file_path: &Path) -> Result<(), io::Error> {
+        let mut file = File::open(file_path)?;
+        let mut buffer = [0; 64 * 1024];
+        loop {
+            let n = file.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            self.update(&buffer[..n]);
+        }
+        Ok(())
+    }
+
+    // Returns (sha512, sha1) as hex strings.
+    fn finalize(self) -> (String, String) {
+        (
+            hex::encode(self.sha512.finalize()),
+            hex::encode(self.sha1.finalize()),
+        )
+    }
+}
+
+fn display_name(mod_name: &str, width: usize) -> String {
+    if mod_name.chars().count() > width {
+        format!(
+            "{}...",
+            mod_name.chars().take(width - 3).collect::<String>()
+        )
+    } else {
+        format!("{:<width$}", mod_name, width = width)
+    }
+}
+
+fn download_style() -> ProgressStyle {
+    ProgressStyle::with_template(
+        " {spinner:.green} [{msg}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} {bytes_per_sec} ({eta})",
+    )
+    .unwrap()
+    .progress_chars("#>-")
+}
+
+pub struct DownloadOutcome {
+    pub task: DownloadTask,
+    pub error: Option<String>, // None if the file was downloaded, verified and moved into place.
+}
+
+// The outcome of every download, in the order they were requested.
+pub struct DownloadReport {
+    pub outcomes: Vec<DownloadOutcome>,
+}
+
+impl DownloadReport {
+    pub fn failed(&self) -> Vec<&DownloadOutcome> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.error.is_some())
+            .collect()
+    }
+
+    pub fn print_failures(&self) {
+        for outcome in self.failed() {
+            alert!(format!(
+                "Failed to download '{}': {}",
+                outcome.task.name,
+                outcome.error.clone().unwrap_or_default()
+            ));
         }
     }
 }
 
 pub async fn download_all_mods(
     client: &Arc<Client>,
-    mods: Vec<(String, PathBuf, String, String)>,
-) -> Result<(), Box<dyn Error + Send>> {
+    mods: Vec<DownloadTask>,
+    settings: &DownloadSettings,
+) -> DownloadReport {
+    if mods.is_empty() {
+        return DownloadReport {
+            outcomes: Vec::new(),
+        };
+    }
+
+    let max_concurrent = settings.max_concurrent.max(1);
+    match settings.speed_limit_kib {
+        Some(limit) => info!(format!(
+            "Downloading {} file(s), {} at a time, limited to {} KiB/s...",
+            mods.len(),
+            max_concurrent,
+            limit
+        )),
+        None => info!(format!(
+            "Downloading {} file(s), {} at a time...",
+            mods.len(),
+            max_concurrent
+        )),
+    }
+
     let multi_pb = Arc::new(MultiProgress::new());
+    let semaphore = Arc::new(Semaphore::new(max_concurrent));
+    let rate_limiter: Option<Arc<RateLimiter>> = settings
+        .speed_limit_kib
+        .map(|limit| Arc::new(RateLimiter::new(limit * 1024)));
+
+    // Overall progress, so that queued downloads are visible too.
+    let overall_pb = multi_pb.add(ProgressBar::new(mods.len() as u64));
+    overall_pb.set_style(
+        ProgressStyle::with_template(" {spinner:.green} {pos}/{len} file(s) done, {msg}").unwrap(),
+    );
+    overall_pb.set_message(format!("{} queued", mods.len()));
+    overall_pb.enable_steady_tick(Duration::from_millis(100));
+    let queued = Arc::new(AtomicUsize::new(mods.len()));
+
     let mut tasks = Vec::new();
+    let mut staging_dirs: Vec<PathBuf> = Vec::new();
 
-    for (url, dest, name, hash) in mods {
+    for task in mods.iter().cloned() {
+        if let Some(staging_dir) = staging_path(&task.dest).parent() {
+            if !staging_dirs.iter().any(|dir| dir == staging_dir) {
+                staging_dirs.push(staging_dir.to_path_buf());
+            }
+        }
         let client = Arc::clone(client);
         let multi_pb = Arc::clone(&multi_pb);
+        let semaphore = Arc::clone(&semaphore);
+        let rate_limiter = rate_limiter.clone();
+        let overall_pb = overall_pb.clone();
+        let queued = Arc::clone(&queued);
         let mod_match = tokio::spawn(async move {
-            download_mod(&client, &url, &dest, &name, &multi_pb, &hash).await
+            // The permit is held until the download is done. The semaphore is never closed, so this cannot fail.
+            let _permit = semaphore.acquire_owned().await;
+            let remaining = queued.fetch_sub(1, Ordering::SeqCst) - 1;
+            overall_pb.set_message(format!("{} queued", remaining));
+
+            let pb = multi_pb.insert_before(&overall_pb, ProgressBar::new(task.size));
+            pb.set_style(download_style());
+            pb.set_message(display_name(&task.name, 30));
+
+            let result = download_mod(&client, &task, &pb, rate_limiter.as_deref()).await;
+            overall_pb.inc(1);
+            result
         });
         tasks.push(mod_match)
     }
 
-    let _ = futures::future::join_all(tasks).await;
-    Ok(())
+    let results = futures::future::join_all(tasks).await;
+    overall_pb.finish_with_message("all downloads finished.");
+
+    // Staging folders are only removed once empty, so partial downloads are kept to be resumed.
+    for staging_dir in staging_dirs {
+        let _ = fs::remove_dir(staging_dir);
+    }
+
+    let outcomes = mods
+        .into_iter()
+        .zip(results)
+        .map(|(task, result)| {
+            let error = match result {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(e.to_string()),
+                Err(join_error) => Some(format!("Task failed: {:?}", join_error)),
+            };
+            DownloadOutcome { task, error }
+        })
+        .collect();
+
+    DownloadReport { outcomes }
 }
 
-pub fn calculate_sha512(file_path: &PathBuf) -> Result<String, io::Error> {
+pub fn calculate_sha512(file_path: &Path) -> Result<String, io::Error> {
     // Open the file
     let mut file = File::open(file_path)?;
 
@@ -136,7 +441,7 @@ pub fn calculate_sha512(file_path: &PathBuf) -> Result<String, io::Error> {
     let mut hasher = Sha512::new();
 
     // Read file in chunks
-    let mut buffer = [0; 1024];
+    let mut buffer = [0; 64 * 1024];
     loop {
         let n = file.read(&mut buffer)?;
         if n == 0 {
@@ -150,10 +455,46 @@ pub fn calculate_sha512(file_path: &PathBuf) -> Result<String, io::Error> {
     Ok(hex::encode(hash_result))
 }
 
-pub fn verify_file(file_path: &PathBuf, expected_hash: &str) -> Result<bool, io::Error> {
+pub fn calculate_sha1(file_path: &Path) -> Result<String, io::Error> {
+    let mut file = File::open(file_path)?;
+    let mut hasher = Sha1::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+pub fn verify_file(file_path: &Path, expected_hash: &str) -> Result<bool, io::Error> {
     // Calculate the SHA-512 hash of the file
     let file_hash = calculate_sha512(file_path)?;
 
     // Compare the calculated hash with the expected hash
     Ok(file_hash == expected_hash)
 }
+
+// Like verify_file, but checks the sha1 when the sha512 is not known (CurseForge only gives the sha1).
+pub fn verify_file_hashes(
+    file_path: &Path,
+    sha512: &str,
+    sha1: Option<&str>,
+) -> Result<bool, io::Error> {
+    match sha1 {
+        _ if !sha512.is_empty() => verify_file(file_path, sha512),
+        Some(sha1) => Ok(calculate_sha1(file_path)? == sha1),
+        None => Ok(false),
+    }
+}
+
+// Whether a file, whose sha512 is already known, is the file of a lockfile entry.
+pub fn file_matches(file_path: &Path, sha512: &str, lock_mod: &LockMod) -> Result<bool, io::Error> {
+    match &lock_mod.sha1 {
+        _ if !lock_mod.sha512.is_empty() => Ok(lock_mod.sha512 == sha512),
+        Some(sha1) => Ok(calculate_sha1(file_path)? == *sha1),
+        None => Ok(false),
+    }
+}
@@ -0,0 +1,254 @@
+use std::{fs, path::Path};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{datatypes::GameLoader, errors::ModManError};
+
+/*
+    Prism Launcher and MultiMC instances: a folder with instance.cfg, and mmc-pack.json listing the instance's components
+    (Minecraft, the loader and the libraries they need), each with a uid and a version.
+    The game directory is '.minecraft' (or 'minecraft' in older instances) inside the instance folder.
+
+    mmc-pack.json is read and written as plain JSON, so fields which modman doesn't know about are kept.
+*/
+
+pub const PACK_FILE_NAME: &str = "mmc-pack.json";
+pub const CONFIG_FILE_NAME: &str = "instance.cfg";
+const MINECRAFT_UID: &str = "net.minecraft";
+
+// Uids of the components of the loaders that the launchers can install.
+const LOADER_UIDS: [(GameLoader, &str); 5] = [
+    (GameLoader::Fabric, "net.fabricmc.fabric-loader"),
+    (GameLoader::Quilt, "org.quiltmc.quilt-loader"),
+    (GameLoader::Forge, "net.minecraftforge"),
+    (GameLoader::NeoForge, "net.neoforged"),
+    (GameLoader::LiteLoader, "com.mumfrey.liteloader"),
+];
+
+pub struct Instance {
+    pub game_version: String,
+    pub game_loader: GameLoader,
+    pub loader_version: Option<String>,
+    pub game_dir_name: &'static str, // '.minecraft' or 'minecraft'
+}
+
+pub fn is_instance(path: &Path) -> bool {
+    path.join(PACK_FILE_NAME).is_file() && path.join(CONFIG_FILE_NAME).is_file()
+}
+
+// Name of the game directory inside an instance. New instances use '.minecraft'.
+pub fn game_dir_name(path: &Path) -> &'static str {
+    match !path.join(".minecraft").is_dir() && path.join("minecraft").is_dir() {
+        true => "minecraft",
+        false => ".minecraft",
+    }
+}
+
+fn loader_uid(game_loader: &GameLoader) -> Option<&'static str> {
+    LOADER_UIDS
+        .iter()
+        .find(|(loader, _)| loader == game_loader)
+        .map(|(_, uid)| *uid)
+}
+
+fn read_pack(path: &Path) -> Result<Value, ModManError> {
+    let contents = fs::read_to_string(path.join(PACK_FILE_NAME)).map_err(ModManError::IoError)?;
+    serde_json::from_str(&contents).map_err(ModManError::JsonError)
+}
+
+fn component_version(pack: &Value, uid: &str) -> Option<String> {
+    pack.get("components")?
+        .as_array()?
+        .iter()
+        .find(|component| component.get("uid").and_then(Value::as_str) == Some(uid))?
+        .get("version")?
+        .as_str()
+        .map(str::to_string)
+}
+
+// The Minecraft version and loader of an instance, from its mmc-pack.json.
+pub fn read_instance(path: &Path) -> Result<Instance, ModManError> {
+    if !is_instance(path) {
+        return Err(ModManError::InvalidCommandArguments(format!(
+            "'{}' is not a Prism Launcher or MultiMC instance: it needs {} and {}.",
+            path.display(),
+            CONFIG_FILE_NAME,
+            PACK_FILE_NAME
+        )));
+    }
+    let pack = read_pack(path)?;
+    let game_version = match component_version(&pack, MINECRAFT_UID) {
+        Some(result) => result,
+        None => {
+            return Err(ModManError::InvalidCommandArguments(format!(
+                "The instance has no Minecraft component in {}.",
+                PACK_FILE_NAME
+            )))
+        }
+    };
+    let (game_loader, loader_version) = LOADER_UIDS
+        .iter()
+        .find_map(|(game_loader, uid)| {
+            Some((game_loader.clone(), Some(component_version(&pack, uid)?)))
+        })
+        .unwrap_or((GameLoader::Vanilla, None));
+
+    Ok(Instance {
+        game_version,
+        game_loader,
+        loader_version,
+        game_dir_name: game_dir_name(path),
+    })
+}
+
+// Contents of the instance's mmc-pack.json with Minecraft at `game_version`, and the loader at `loader_version` if it is given.
+// Components which are only there as dependencies (e.g. LWJGL and intermediary mappings) are removed, as their versions
+// depend on the Minecraft version. The launcher adds them back, at the right versions, when the instance is started.
+pub fn synced_pack(
+    path: &Path,
+    game_loader: &GameLoader,
+    game_version: &str,
+    loader_version: Option<&str>,
+) -> Result<String, ModManError> {
+    let mut pack = read_pack(path)?;
+    let loader_uid = loader_uid(game_loader);
+    if let Some(components) = pack.get_mut("components").and_then(Value::as_array_mut) {
+        components.retain(|component| {
+            !component
+                .get("dependencyOnly")
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+        });
+        for component in components.iter_mut().filter_map(Value::as_object_mut) {
+            let uid = component.get("uid").and_then(Value::as_str);
+            let version = match uid {
+                Some(MINECRAFT_UID) => Some(game_version),
+                Some(uid) if Some(uid) == loader_uid => loader_version,
+                _ => None,
+            };
+            if let Some(version) = version {
+                set_version(component, version);
+            }
+        }
+    }
+
+    // The launchers write mmc-pack.json with sorted keys and four spaces of indentation, as serde_json does here.
+    let mut contents = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut contents, formatter);
+    pack.serialize(&mut serializer)
+        .map_err(ModManError::JsonError)?;
+    Ok(String::from_utf8_lossy(&contents).to_string())
+}
+
+// The cached fields are the launcher's copy of the component's metadata. The ones for the old version are dropped,
+// so the launcher fetches them again.
+fn set_version(component: &mut Map<String, Value>, version: &str) {
+    component.insert("version".to_string(), Value::String(version.to_string()));
+    component.insert(
+        "cachedVersion".to_string(),
+        Value::String(version.to_string()),
+    );
+    component.remove("cachedRequires");
+    component.remove("cachedConflicts");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A Fabric instance, as Prism Launcher writes it.
+    const PACK: &str = r#"{
+        "components": [
+            {"cachedName": "LWJGL 3", "cachedVersion": "3.3.1", "dependencyOnly": true, "uid": "org.lwjgl3", "version": "3.3.1"},
+            {"cachedName": "Minecraft", "cachedRequires": [{"suggests": "3.3.1", "uid": "org.lwjgl3"}],
+             "cachedVersion": "1.20.1", "important": true, "uid": "net.minecraft", "version": "1.20.1"},
+            {"cachedName": "Intermediary Mappings", "cachedVersion": "1.20.1", "dependencyOnly": true,
+             "uid": "net.fabricmc.intermediary", "version": "1.20.1"},
+            {"cachedName": "Fabric Loader", "cachedVersion": "0.15.0", "uid": "net.fabricmc.fabric-loader", "version": "0.15.0"}
+        ],
+        "formatVersion": 1
+    }"#;
+
+    fn write_instance(pack: &str) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(CONFIG_FILE_NAME), "InstanceType=OneSix\n").unwrap();
+        fs::write(dir.path().join(PACK_FILE_NAME), pack).unwrap();
+        dir
+    }
+
+    fn component<'a>(pack: &'a Value, uid: &str) -> Option<&'a Value> {
+        pack["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|component| component["uid"] == uid)
+    }
+
+    #[test]
+    fn instance_is_read_from_its_pack() {
+        let dir = write_instance(PACK);
+
+        let instance = read_instance(dir.path()).unwrap();
+
+        assert_eq!(instance.game_version, "1.20.1");
+        assert_eq!(instance.game_loader, GameLoader::Fabric);
+        assert_eq!(instance.loader_version.as_deref(), Some("0.15.0"));
+        assert_eq!(instance.game_dir_name, ".minecraft");
+    }
+
+    #[test]
+    fn instance_without_a_loader_is_vanilla() {
+        let dir =
+            write_instance(r#"{"components": [{"uid": "net.minecraft", "version": "1.21.1"}]}"#);
+
+        let instance = read_instance(dir.path()).unwrap();
+
+        assert_eq!(instance.game_loader, GameLoader::Vanilla);
+        assert_eq!(instance.loader_version, None);
+    }
+
+    #[test]
+    fn folder_without_instance_cfg_is_not_an_instance() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(PACK_FILE_NAME), PACK).unwrap();
+
+        assert!(read_instance(dir.path()).is_err());
+    }
+
+    #[test]
+    fn synced_pack_updates_versions_and_keeps_unknown_keys() {
+        let dir = write_instance(PACK);
+
+        let contents =
+            synced_pack(dir.path(), &GameLoader::Fabric, "1.21.1", Some("0.16.0")).unwrap();
+        let pack: Value = serde_json::from_str(&contents).unwrap();
+
+        assert_eq!(pack["formatVersion"], 1);
+        let minecraft = component(&pack, MINECRAFT_UID).unwrap();
+        assert_eq!(minecraft["version"], "1.21.1");
+        assert_eq!(minecraft["cachedVersion"], "1.21.1");
+        assert_eq!(minecraft["cachedName"], "Minecraft");
+        assert_eq!(minecraft["important"], true);
+        assert!(minecraft.get("cachedRequires").is_none());
+        let loader = component(&pack, "net.fabricmc.fabric-loader").unwrap();
+        assert_eq!(loader["version"], "0.16.0");
+        assert_eq!(loader["cachedVersion"], "0.16.0");
+    }
+
+    #[test]
+    fn synced_pack_drops_dependency_only_components() {
+        let dir = write_instance(PACK);
+
+        let contents = synced_pack(dir.path(), &GameLoader::Fabric, "1.21.1", None).unwrap();
+        let pack: Value = serde_json::from_str(&contents).unwrap();
+
+        assert!(component(&pack, "org.lwjgl3").is_none());
+        assert!(component(&pack, "net.fabricmc.intermediary").is_none());
+        assert_eq!(pack["components"].as_array().unwrap().len(), 2);
+        // Without a loader version, the loader is left as it is.
+        let loader = component(&pack, "net.fabricmc.fabric-loader").unwrap();
+        assert_eq!(loader["version"], "0.15.0");
+    }
+}
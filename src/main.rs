@@ -2,10 +2,17 @@ mod api;
 mod commands;
 mod config;
 mod config_sync;
+mod conflicts;
 mod datatypes;
 mod errors;
+mod hash_cache;
 mod install;
+mod instance;
 mod macros;
+mod metadata;
+mod modpack;
+mod tags;
+mod transactions;
 mod utils;
 
 use commands::command_handler;
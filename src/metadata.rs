@@ -0,0 +1,866 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use serde::Deserialize;
+use zip::ZipArchive;
+
+use crate::datatypes::{DependencyType, GameLoader, LockDependency, ModSources, SideSupport};
+
+/*
+    Reads the metadata that mods and plugins ship inside their jar, for jars that can't be matched to a source.
+
+    Supported files:
+        fabric.mod.json                 - Fabric
+        quilt.mod.json                  - Quilt
+        META-INF/mods.toml              - Forge
+        META-INF/neoforge.mods.toml     - NeoForge
+        plugin.yml                      - Bukkit, Spigot, Paper, Purpur
+        paper-plugin.yml                - Paper, Purpur
+        velocity-plugin.json            - Velocity
+
+    Multi-loader jars can contain more than one of these. The first one found gives the id, name and version,
+    and the loaders, game versions and dependencies of all of them are combined.
+*/
+
+pub struct ModMetadata {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub loaders: Vec<GameLoader>,
+    pub game_versions: Vec<String>, // Version predicates or ranges, as declared by the mod.
+    pub dependencies: Vec<LockDependency>, // Internal mod ids, with the source set to Local.
+    pub client_side: SideSupport,
+    pub server_side: SideSupport,
+}
+
+// Dependencies on the game or the loader itself, rather than on other mods.
+const PLATFORM_IDS: &[&str] = &[
+    "minecraft",
+    "java",
+    "fabricloader",
+    "fabric-loader",
+    "quilt_loader",
+    "forge",
+    "neoforge",
+];
+
+// Returns None if the jar can't be read, or has no metadata that modman understands.
+pub fn read_metadata(path: &Path) -> Option<ModMetadata> {
+    let file = File::open(path).ok()?;
+    let mut archive = ZipArchive::new(BufReader::new(file)).ok()?;
+
+    let mut found: Vec<ModMetadata> = Vec::new();
+    if let Some(contents) = read_entry(&mut archive, "fabric.mod.json") {
+        found.extend(parse_fabric(&contents));
+    }
+    if let Some(contents) = read_entry(&mut archive, "quilt.mod.json") {
+        found.extend(parse_quilt(&contents));
+    }
+    if let Some(contents) = read_entry(&mut archive, "META-INF/neoforge.mods.toml") {
+        found.extend(parse_mods_toml(
+            &contents,
+            GameLoader::NeoForge,
+            &mut archive,
+        ));
+    }
+    if let Some(contents) = read_entry(&mut archive, "META-INF/mods.toml") {
+        found.extend(parse_mods_toml(&contents, GameLoader::Forge, &mut archive));
+    }
+    if let Some(contents) = read_entry(&mut archive, "paper-plugin.yml") {
+        found.extend(parse_paper_plugin(&contents));
+    }
+    if let Some(contents) = read_entry(&mut archive, "plugin.yml") {
+        found.extend(parse_bukkit_plugin(&contents));
+    }
+    if let Some(contents) = read_entry(&mut archive, "velocity-plugin.json") {
+        found.extend(parse_velocity_plugin(&contents));
+    }
+
+    let mut found = found.into_iter();
+    let mut metadata = found.next()?;
+    for other in found {
+        if metadata.client_side.is_unknown() && metadata.server_side.is_unknown() {
+            metadata.client_side = other.client_side;
+            metadata.server_side = other.server_side;
+        }
+        for loader in other.loaders {
+            if !metadata.loaders.contains(&loader) {
+                metadata.loaders.push(loader);
+            }
+        }
+        for game_version in other.game_versions {
+            if !metadata.game_versions.contains(&game_version) {
+                metadata.game_versions.push(game_version);
+            }
+        }
+        for dependency in other.dependencies {
+            if !metadata
+                .dependencies
+                .iter()
+                .any(|dep| dep.project_id == dependency.project_id)
+            {
+                metadata.dependencies.push(dependency);
+            }
+        }
+    }
+    Some(metadata)
+}
+
+fn read_entry<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>, name: &str) -> Option<String> {
+    let mut entry = archive.by_name(name).ok()?;
+    let mut contents = String::new();
+    entry.read_to_string(&mut contents).ok()?;
+    Some(contents)
+}
+
+fn dependency(project_id: &str, dependency_type: DependencyType) -> LockDependency {
+    LockDependency {
+        source: ModSources::Local,
+        project_id: project_id.to_string(),
+        dependency_type,
+    }
+}
+
+fn is_platform_id(id: &str) -> bool {
+    PLATFORM_IDS.contains(&id)
+}
+
+// Sides from the environment of a fabric.mod.json or quilt.mod.json ('client', 'server' or '*').
+fn environment_sides(environment: Option<&str>) -> (SideSupport, SideSupport) {
+    match environment {
+        Some("client") => (SideSupport::Required, SideSupport::Unsupported),
+        Some("server") | Some("dedicated_server") => {
+            (SideSupport::Unsupported, SideSupport::Required)
+        }
+        Some("*") => (SideSupport::Required, SideSupport::Required),
+        _ => (SideSupport::Unknown, SideSupport::Unknown),
+    }
+}
+
+// Version predicates can be a single string, or a list of them.
+fn json_strings(value: &serde_json::Value) -> Vec<String> {
+    match value {
+        serde_json::Value::String(s) => vec![s.clone()],
+        serde_json::Value::Array(values) => values
+            .iter()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// fabric.mod.json
+#[derive(Deserialize)]
+struct FabricModJson {
+    id: String,
+    #[serde(default)]
+    version: String,
+    name: Option<String>,
+    #[serde(default)]
+    depends: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    recommends: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    suggests: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    breaks: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    conflicts: BTreeMap<String, serde_json::Value>,
+    environment: Option<String>,
+}
+
+fn parse_fabric(contents: &str) -> Option<ModMetadata> {
+    let fabric: FabricModJson = serde_json::from_str(contents).ok()?;
+
+    let mut dependencies = Vec::new();
+    let groups = [
+        (&fabric.depends, DependencyType::Required),
+        (&fabric.recommends, DependencyType::Optional),
+        (&fabric.suggests, DependencyType::Optional),
+        (&fabric.breaks, DependencyType::Incompatible),
+        (&fabric.conflicts, DependencyType::Incompatible),
+    ];
+    for (group, dependency_type) in groups {
+        for id in group.keys().filter(|id| !is_platform_id(id)) {
+            dependencies.push(dependency(id, dependency_type.clone()));
+        }
+    }
+
+    let (client_side, server_side) = environment_sides(fabric.environment.as_deref());
+    Some(ModMetadata {
+        name: fabric.name.unwrap_or(fabric.id.clone()),
+        id: fabric.id,
+        version: fabric.version,
+        loaders: vec![GameLoader::Fabric],
+        game_versions: fabric
+            .depends
+            .get("minecraft")
+            .map(json_strings)
+            .unwrap_or_default(),
+        dependencies,
+        client_side,
+        server_side,
+    })
+}
+
+// quilt.mod.json
+#[derive(Deserialize)]
+struct QuiltModJson {
+    quilt_loader: QuiltLoader,
+    #[serde(default)]
+    minecraft: QuiltMinecraft,
+}
+
+#[derive(Deserialize, Default)]
+struct QuiltMinecraft {
+    environment: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct QuiltLoader {
+    id: String,
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    metadata: QuiltMetadata,
+    #[serde(default)]
+    depends: Vec<serde_json::Value>,
+    #[serde(default)]
+    breaks: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize, Default)]
+struct QuiltMetadata {
+    name: Option<String>,
+}
+
+fn parse_quilt(contents: &str) -> Option<ModMetadata> {
+    let quilt: QuiltModJson = serde_json::from_str(contents).ok()?;
+    let loader = quilt.quilt_loader;
+
+    // Each dependency is either just an id, or an object with an id, versions and whether it is optional.
+    let mut dependencies = Vec::new();
+    let mut game_versions = Vec::new();
+    let groups = [(&loader.depends, false), (&loader.breaks, true)];
+    for (group, breaks) in groups {
+        for entry in group {
+            let (id, versions, optional) = match entry {
+                serde_json::Value::String(id) => (id.as_str(), Vec::new(), false),
+                serde_json::Value::Object(object) => {
+                    match object.get("id").and_then(|id| id.as_str()) {
+                        Some(id) => (
+                            id,
+                            object.get("versions").map(json_strings).unwrap_or_default(),
+                            object
+                                .get("optional")
+                                .and_then(|optional| optional.as_bool())
+                                .unwrap_or(false),
+                        ),
+                        None => continue,
+                    }
+                }
+                _ => continue,
+            };
+            if id == "minecraft" && !breaks {
+                game_versions.extend(versions);
+            } else if !is_platform_id(id) {
+                let dependency_type = if breaks {
+                    DependencyType::Incompatible
+                } else if optional {
+                    DependencyType::Optional
+                } else {
+                    DependencyType::Required
+                };
+                dependencies.push(dependency(id, dependency_type));
+            }
+        }
+    }
+
+    let (client_side, server_side) = environment_sides(quilt.minecraft.environment.as_deref());
+    Some(ModMetadata {
+        name: loader.metadata.name.unwrap_or(loader.id.clone()),
+        id: loader.id,
+        version: loader.version,
+        loaders: vec![GameLoader::Quilt],
+        game_versions,
+        dependencies,
+        client_side,
+        server_side,
+    })
+}
+
+// META-INF/mods.toml and META-INF/neoforge.mods.toml
+#[derive(Deserialize)]
+struct ModsToml {
+    #[serde(default)]
+    mods: Vec<ModsTomlMod>,
+    #[serde(default)]
+    dependencies: HashMap<String, Vec<ModsTomlDependency>>,
+}
+
+#[derive(Deserialize)]
+struct ModsTomlMod {
+    #[serde(rename = "modId")]
+    mod_id: String,
+    version: Option<String>,
+    #[serde(rename = "displayName")]
+    display_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ModsTomlDependency {
+    #[serde(rename = "modId")]
+    mod_id: String,
+    mandatory: Option<bool>, // Forge
+    #[serde(rename = "type")]
+    dependency_type: Option<String>, // NeoForge: required, optional, incompatible or discouraged.
+    #[serde(rename = "versionRange")]
+    version_range: Option<String>,
+}
+
+fn parse_mods_toml<R: Read + std::io::Seek>(
+    contents: &str,
+    loader: GameLoader,
+    archive: &mut ZipArchive<R>,
+) -> Option<ModMetadata> {
+    let mods_toml: ModsToml = toml::from_str(contents).ok()?;
+    let first_mod = mods_toml.mods.first()?;
+
+    // '${file.jarVersion}' is filled in from the jar manifest when the mod is loaded.
+    let mut version = first_mod.version.clone().unwrap_or_default();
+    if version.contains("${file.jarVersion}") {
+        let manifest_version = read_entry(archive, "META-INF/MANIFEST.MF").and_then(|manifest| {
+            manifest.lines().find_map(|line| {
+                line.strip_prefix("Implementation-Version:")
+                    .map(|v| v.trim().to_string())
+            })
+        });
+        if let Some(manifest_version) = manifest_version {
+            version = version.replace("${file.jarVersion}", &manifest_version);
+        }
+    }
+
+    let mut dependencies = Vec::new();
+    let mut game_versions = Vec::new();
+    for mods_toml_mod in &mods_toml.mods {
+        for dep in mods_toml
+            .dependencies
+            .get(&mods_toml_mod.mod_id)
+            .into_iter()
+            .flatten()
+        {
+            if dep.mod_id == "minecraft" {
+                game_versions.extend(dep.version_range.clone());
+                continue;
+            }
+            if is_platform_id(&dep.mod_id)
+                || dependencies
+                    .iter()
+                    .any(|d: &LockDependency| d.project_id == dep.mod_id)
+            {
+                continue;
+            }
+            let dependency_type = match (dep.dependency_type.as_deref(), dep.mandatory) {
+                (Some("required"), _) => DependencyType::Required,
+                (Some("incompatible"), _) => DependencyType::Incompatible,
+                (Some(_), _) => DependencyType::Optional,
+                (None, Some(false)) => DependencyType::Optional,
+                (None, _) => DependencyType::Required,
+            };
+            dependencies.push(dependency(&dep.mod_id, dependency_type));
+        }
+    }
+
+    Some(ModMetadata {
+        id: first_mod.mod_id.clone(),
+        name: first_mod
+            .display_name
+            .clone()
+            .unwrap_or(first_mod.mod_id.clone()),
+        version,
+        loaders: vec![loader],
+        game_versions,
+        dependencies,
+        client_side: SideSupport::Unknown,
+        server_side: SideSupport::Unknown,
+    })
+}
+
+// plugin.yml
+// Versions are read as strings, so YAML doesn't turn an unquoted '1.20' into the number 1.2.
+#[derive(Deserialize)]
+struct BukkitPluginYml {
+    name: String,
+    version: Option<String>,
+    #[serde(rename = "api-version")]
+    api_version: Option<String>,
+    #[serde(default)]
+    depend: Vec<String>,
+    #[serde(default)]
+    softdepend: Vec<String>,
+    #[serde(rename = "folia-supported", default)]
+    folia_supported: bool,
+}
+
+fn parse_bukkit_plugin(contents: &str) -> Option<ModMetadata> {
+    let plugin: BukkitPluginYml = serde_yaml::from_str(contents).ok()?;
+
+    let mut loaders = vec![
+        GameLoader::Bukkit,
+        GameLoader::Spigot,
+        GameLoader::Paper,
+        GameLoader::Purpur,
+    ];
+    if plugin.folia_supported {
+        loaders.push(GameLoader::Folia);
+    }
+    let mut dependencies: Vec<LockDependency> = plugin
+        .depend
+        .iter()
+        .map(|id| dependency(id, DependencyType::Required))
+        .collect();
+    dependencies.extend(
+        plugin
+            .softdepend
+            .iter()
+            .map(|id| dependency(id, DependencyType::Optional)),
+    );
+
+    Some(ModMetadata {
+        id: plugin.name.clone(),
+        name: plugin.name,
+        version: plugin.version.unwrap_or_default(),
+        loaders,
+        game_versions: plugin.api_version.into_iter().collect(),
+        dependencies,
+        client_side: SideSupport::Unsupported, // Plugins only run on servers.
+        server_side: SideSupport::Required,
+    })
+}
+
+// paper-plugin.yml
+#[derive(Deserialize)]
+struct PaperPluginYml {
+    name: String,
+    version: Option<String>,
+    #[serde(rename = "api-version")]
+    api_version: Option<String>,
+    #[serde(default)]
+    dependencies: PaperDependencies,
+    #[serde(rename = "folia-supported", default)]
+    folia_supported: bool,
+}
+
+#[derive(Deserialize, Default)]
+struct PaperDependencies {
+    #[serde(default)]
+    server: BTreeMap<String, PaperDependency>,
+}
+
+#[derive(Deserialize)]
+struct PaperDependency {
+    #[serde(default = "default_required")]
+    required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+fn parse_paper_plugin(contents: &str) -> Option<ModMetadata> {
+    let plugin: PaperPluginYml = serde_yaml::from_str(contents).ok()?;
+
+    let mut loaders = vec![GameLoader::Paper, GameLoader::Purpur];
+    if plugin.folia_supported {
+        loaders.push(GameLoader::Folia);
+    }
+    let dependencies = plugin
+        .dependencies
+        .server
+        .iter()
+        .map(|(id, dep)| {
+            let dependency_type = if dep.required {
+                DependencyType::Required
+            } else {
+                DependencyType::Optional
+            };
+            dependency(id, dependency_type)
+        })
+        .collect();
+
+    Some(ModMetadata {
+        id: plugin.name.clone(),
+        name: plugin.name,
+        version: plugin.version.unwrap_or_default(),
+        loaders,
+        game_versions: plugin.api_version.into_iter().collect(),
+        dependencies,
+        client_side: SideSupport::Unsupported, // Plugins only run on servers.
+        server_side: SideSupport::Required,
+    })
+}
+
+// velocity-plugin.json
+#[derive(Deserialize)]
+struct VelocityPluginJson {
+    id: String,
+    name: Option<String>,
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    dependencies: Vec<VelocityDependency>,
+}
+
+#[derive(Deserialize)]
+struct VelocityDependency {
+    id: String,
+    #[serde(default)]
+    optional: bool,
+}
+
+fn parse_velocity_plugin(contents: &str) -> Option<ModMetadata> {
+    let plugin: VelocityPluginJson = serde_json::from_str(contents).ok()?;
+
+    let dependencies = plugin
+        .dependencies
+        .iter()
+        .map(|dep| {
+            let dependency_type = if dep.optional {
+                DependencyType::Optional
+            } else {
+                DependencyType::Required
+            };
+            dependency(&dep.id, dependency_type)
+        })
+        .collect();
+
+    Some(ModMetadata {
+        name: plugin.name.unwrap_or(plugin.id.clone()),
+        id: plugin.id,
+        version: plugin.version,
+        loaders: vec![GameLoader::Velocity],
+        game_versions: Vec::new(),
+        dependencies,
+        client_side: SideSupport::Unsupported,
+        server_side: SideSupport::Required,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::*;
+
+    fn zip_of(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn dependency_ids(metadata: &ModMetadata, dependency_type: DependencyType) -> Vec<&str> {
+        metadata
+            .dependencies
+            .iter()
+            .filter(|d| d.dependency_type == dependency_type)
+            .map(|d| d.project_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn fabric_mod_json() {
+        let metadata = parse_fabric(
+            r#"{"schemaVersion": 1, "id": "sodium", "name": "Sodium", "version": "0.5.8",
+                "environment": "client",
+                "depends": {"minecraft": [">=1.20", "<1.21"], "fabricloader": ">=0.15", "fabric-api": "*"},
+                "recommends": {"modmenu": "*"},
+                "breaks": {"optifabric": "*"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(metadata.id, "sodium");
+        assert_eq!(metadata.name, "Sodium");
+        assert_eq!(metadata.version, "0.5.8");
+        assert_eq!(metadata.loaders, vec![GameLoader::Fabric]);
+        assert_eq!(metadata.game_versions, vec![">=1.20", "<1.21"]);
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Required),
+            vec!["fabric-api"]
+        );
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Optional),
+            vec!["modmenu"]
+        );
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Incompatible),
+            vec!["optifabric"]
+        );
+        assert_eq!(metadata.client_side, SideSupport::Required);
+        assert_eq!(metadata.server_side, SideSupport::Unsupported);
+    }
+
+    #[test]
+    fn fabric_mod_json_without_name_uses_id() {
+        let metadata = parse_fabric(r#"{"id": "lithium", "version": "1.0"}"#).unwrap();
+
+        assert_eq!(metadata.name, "lithium");
+        assert!(metadata.game_versions.is_empty());
+        assert_eq!(metadata.client_side, SideSupport::Unknown);
+        assert!(parse_fabric(r#"{"name": "No id"}"#).is_none());
+    }
+
+    #[test]
+    fn quilt_mod_json() {
+        let metadata = parse_quilt(
+            r#"{"quilt_loader": {"id": "qsl_mod", "version": "2.0.0", "metadata": {"name": "QSL Mod"},
+                "depends": [
+                    "quilt_loader",
+                    {"id": "minecraft", "versions": "1.20.1"},
+                    {"id": "qsl"},
+                    {"id": "emi", "optional": true}
+                ],
+                "breaks": [{"id": "minecraft", "versions": "1.19"}, "oldmod"]},
+                "minecraft": {"environment": "dedicated_server"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(metadata.id, "qsl_mod");
+        assert_eq!(metadata.name, "QSL Mod");
+        assert_eq!(metadata.version, "2.0.0");
+        assert_eq!(metadata.loaders, vec![GameLoader::Quilt]);
+        assert_eq!(metadata.game_versions, vec!["1.20.1"]);
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Required),
+            vec!["qsl"]
+        );
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Optional),
+            vec!["emi"]
+        );
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Incompatible),
+            vec!["oldmod"]
+        );
+        assert_eq!(metadata.client_side, SideSupport::Unsupported);
+        assert_eq!(metadata.server_side, SideSupport::Required);
+    }
+
+    #[test]
+    fn forge_mods_toml_with_jar_version() {
+        let jar = zip_of(&[(
+            "META-INF/MANIFEST.MF",
+            "Manifest-Version: 1.0\nImplementation-Version: 3.1.4\n",
+        )]);
+        let mut archive = ZipArchive::new(Cursor::new(jar)).unwrap();
+        let metadata = parse_mods_toml(
+            r#"
+modLoader = "javafml"
+loaderVersion = "[47,)"
+
+[[mods]]
+modId = "jei"
+version = "${file.jarVersion}"
+displayName = "Just Enough Items"
+
+[[dependencies.jei]]
+modId = "forge"
+mandatory = true
+versionRange = "[47,)"
+
+[[dependencies.jei]]
+modId = "minecraft"
+mandatory = true
+versionRange = "[1.20.1,1.21)"
+
+[[dependencies.jei]]
+modId = "architectury"
+mandatory = true
+
+[[dependencies.jei]]
+modId = "rei"
+mandatory = false
+"#,
+            GameLoader::Forge,
+            &mut archive,
+        )
+        .unwrap();
+
+        assert_eq!(metadata.id, "jei");
+        assert_eq!(metadata.name, "Just Enough Items");
+        assert_eq!(metadata.version, "3.1.4");
+        assert_eq!(metadata.loaders, vec![GameLoader::Forge]);
+        assert_eq!(metadata.game_versions, vec!["[1.20.1,1.21)"]);
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Required),
+            vec!["architectury"]
+        );
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Optional),
+            vec!["rei"]
+        );
+    }
+
+    #[test]
+    fn neoforge_mods_toml_dependency_types() {
+        let mut archive = ZipArchive::new(Cursor::new(zip_of(&[]))).unwrap();
+        let metadata = parse_mods_toml(
+            r#"
+[[mods]]
+modId = "create"
+version = "0.5.1"
+
+[[dependencies.create]]
+modId = "neoforge"
+type = "required"
+
+[[dependencies.create]]
+modId = "flywheel"
+type = "required"
+
+[[dependencies.create]]
+modId = "jei"
+type = "optional"
+
+[[dependencies.create]]
+modId = "optifine"
+type = "incompatible"
+"#,
+            GameLoader::NeoForge,
+            &mut archive,
+        )
+        .unwrap();
+
+        assert_eq!(metadata.name, "create");
+        assert_eq!(metadata.version, "0.5.1");
+        assert_eq!(metadata.loaders, vec![GameLoader::NeoForge]);
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Required),
+            vec!["flywheel"]
+        );
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Optional),
+            vec!["jei"]
+        );
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Incompatible),
+            vec!["optifine"]
+        );
+        assert!(parse_mods_toml("mods = []", GameLoader::Forge, &mut archive).is_none());
+    }
+
+    #[test]
+    fn bukkit_plugin_yml() {
+        let metadata = parse_bukkit_plugin(
+            "name: WorldEdit\nversion: 7.3.0\nmain: com.sk89q.WorldEdit\napi-version: 1.20\n\
+             depend: [Vault]\nsoftdepend: [PlaceholderAPI]\nfolia-supported: true\n",
+        )
+        .unwrap();
+
+        assert_eq!(metadata.id, "WorldEdit");
+        assert_eq!(metadata.version, "7.3.0");
+        assert_eq!(metadata.game_versions, vec!["1.20"]);
+        assert!(metadata.loaders.contains(&GameLoader::Spigot));
+        assert!(metadata.loaders.contains(&GameLoader::Folia));
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Required),
+            vec!["Vault"]
+        );
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Optional),
+            vec!["PlaceholderAPI"]
+        );
+        assert_eq!(metadata.client_side, SideSupport::Unsupported);
+        assert_eq!(metadata.server_side, SideSupport::Required);
+    }
+
+    #[test]
+    fn paper_plugin_yml() {
+        let metadata = parse_paper_plugin(
+            "name: Chunky\nversion: '1.4'\napi-version: '1.20'\ndependencies:\n  server:\n    \
+             LuckPerms:\n      load: BEFORE\n    Vault:\n      required: false\n",
+        )
+        .unwrap();
+
+        assert_eq!(metadata.id, "Chunky");
+        assert_eq!(metadata.version, "1.4");
+        assert_eq!(
+            metadata.loaders,
+            vec![GameLoader::Paper, GameLoader::Purpur]
+        );
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Required),
+            vec!["LuckPerms"]
+        );
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Optional),
+            vec!["Vault"]
+        );
+    }
+
+    #[test]
+    fn velocity_plugin_json() {
+        let metadata = parse_velocity_plugin(
+            r#"{"id": "luckperms", "name": "LuckPerms", "version": "5.4",
+                "dependencies": [{"id": "protocolize"}, {"id": "papiproxybridge", "optional": true}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(metadata.id, "luckperms");
+        assert_eq!(metadata.name, "LuckPerms");
+        assert_eq!(metadata.loaders, vec![GameLoader::Velocity]);
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Required),
+            vec!["protocolize"]
+        );
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Optional),
+            vec!["papiproxybridge"]
+        );
+    }
+
+    #[test]
+    fn multi_loader_jar_combines_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("multi.jar");
+        std::fs::write(
+            &path,
+            zip_of(&[
+                (
+                    "fabric.mod.json",
+                    r#"{"id": "multi", "name": "Multi", "version": "1.0",
+                        "depends": {"minecraft": "1.20.1", "fabric-api": "*"}}"#,
+                ),
+                (
+                    "META-INF/mods.toml",
+                    "[[mods]]\nmodId = \"multi_forge\"\n\n[[dependencies.multi_forge]]\n\
+                     modId = \"minecraft\"\nversionRange = \"[1.20.1]\"\n\n\
+                     [[dependencies.multi_forge]]\nmodId = \"architectury\"\n",
+                ),
+            ]),
+        )
+        .unwrap();
+
+        let metadata = read_metadata(&path).unwrap();
+
+        assert_eq!(metadata.id, "multi");
+        assert_eq!(
+            metadata.loaders,
+            vec![GameLoader::Fabric, GameLoader::Forge]
+        );
+        assert_eq!(metadata.game_versions, vec!["1.20.1", "[1.20.1]"]);
+        assert_eq!(
+            dependency_ids(&metadata, DependencyType::Required),
+            vec!["fabric-api", "architectury"]
+        );
+        assert!(read_metadata(&dir.path().join("missing.jar")).is_none());
+    }
+}
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::{GameLoader, LockMod, ModSources};
+
+/*
+    CurseForge modpacks: a zip with a manifest (manifest.json) of the CurseForge files to download (by project and file id),
+    and an overrides folder (named in the manifest) that is copied into the game directory.
+*/
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+pub const OVERRIDES_FOLDER: &str = "overrides";
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseForgeManifest {
+    pub minecraft: ManifestMinecraft,
+    pub manifest_type: String, // Always 'minecraftModpack'.
+    pub manifest_version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub author: String,
+    pub files: Vec<ManifestFile>,
+    #[serde(default = "default_overrides")]
+    pub overrides: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestMinecraft {
+    pub version: String,
+    #[serde(default)]
+    pub mod_loaders: Vec<ManifestModLoader>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ManifestModLoader {
+    pub id: String, // '<loader>-<version>', e.g. 'fabric-0.15.11'
+    #[serde(default)]
+    pub primary: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ManifestFile {
+    #[serde(rename = "projectID")]
+    pub project_id: u64,
+    #[serde(rename = "fileID")]
+    pub file_id: u64,
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_overrides() -> String {
+    OVERRIDES_FOLDER.to_string()
+}
+
+fn default_required() -> bool {
+    true
+}
+
+// Loaders that the CurseForge app can install.
+pub fn is_supported_loader(game_loader: &GameLoader) -> bool {
+    matches!(
+        game_loader,
+        GameLoader::Fabric | GameLoader::Quilt | GameLoader::Forge | GameLoader::NeoForge
+    )
+}
+
+// The primary loader of the manifest, and its version.
+pub fn loader_from_mod_loaders(mod_loaders: &[ManifestModLoader]) -> Option<(GameLoader, String)> {
+    let mod_loader = mod_loaders
+        .iter()
+        .find(|l| l.primary)
+        .or(mod_loaders.first())?;
+    let (name, version) = mod_loader.id.split_once('-')?;
+    let game_loader: GameLoader = name.parse().ok()?;
+    is_supported_loader(&game_loader).then(|| (game_loader, version.to_string()))
+}
+
+// CurseForge project and file id of a lockfile entry, if it is from CurseForge.
+pub fn curseforge_ids(lock_mod: &LockMod) -> Option<(u64, u64)> {
+    match (&lock_mod.source, lock_mod.file_id) {
+        (ModSources::CurseForge, Some(file_id)) => {
+            lock_mod.id.parse::<u64>().ok().map(|id| (id, file_id))
+        }
+        _ => None,
+    }
+}
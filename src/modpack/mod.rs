@@ -0,0 +1,136 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use colored::Colorize;
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use crate::{alert, errors::ModManError};
+
+pub mod curseforge;
+pub mod mrpack;
+pub mod packwiz;
+
+/*
+    Modpack formats that modman can export to and import from.
+
+    A modpack lists the files of the lockfile (modman.lock) with where to download them from,
+    and bundles everything else (config files, local mods, etc.) as overrides, copied as-is into the game directory.
+*/
+
+fn zip_error(e: zip::result::ZipError) -> ModManError {
+    ModManError::IoError(io::Error::other(e))
+}
+
+// Adds a file to the zip, at `name` ('/' separated).
+pub fn zip_bytes(zip: &mut ZipWriter<File>, name: &str, bytes: &[u8]) -> Result<(), ModManError> {
+    zip.start_file(name, SimpleFileOptions::default())
+        .map_err(zip_error)?;
+    zip.write_all(bytes).map_err(ModManError::IoError)
+}
+
+pub fn zip_file(zip: &mut ZipWriter<File>, name: &str, path: &Path) -> Result<(), ModManError> {
+    zip.start_file(name, SimpleFileOptions::default())
+        .map_err(zip_error)?;
+    let mut file = File::open(path).map_err(ModManError::IoError)?;
+    io::copy(&mut file, zip).map_err(ModManError::IoError)?;
+    Ok(())
+}
+
+// Adds every file in `folder` (and its subfolders) to the zip, under `prefix`. Returns the number of files added.
+pub fn zip_folder(
+    zip: &mut ZipWriter<File>,
+    prefix: &str,
+    folder: &Path,
+) -> Result<usize, ModManError> {
+    let mut entries: Vec<_> = fs::read_dir(folder)
+        .map_err(ModManError::IoError)?
+        .collect::<Result<_, _>>()
+        .map_err(ModManError::IoError)?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut count = 0;
+    for entry in entries {
+        let name = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+        let path = entry.path();
+        if path.is_dir() {
+            count += zip_folder(zip, &name, &path)?;
+        } else if path.is_file() {
+            zip_file(zip, &name, &path)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+pub fn finish_zip(zip: ZipWriter<File>) -> Result<(), ModManError> {
+    zip.finish().map_err(zip_error)?;
+    Ok(())
+}
+
+pub fn open_zip(path: &Path) -> Result<ZipArchive<File>, ModManError> {
+    if !path.is_file() {
+        return Err(ModManError::FileNotFound);
+    }
+    let file = File::open(path).map_err(ModManError::IoError)?;
+    ZipArchive::new(file).map_err(zip_error)
+}
+
+// Contents of the file at `name` in the zip, or None if there is no such file.
+pub fn read_zip_entry(
+    zip: &mut ZipArchive<File>,
+    name: &str,
+) -> Result<Option<String>, ModManError> {
+    let mut entry = match zip.by_name(name) {
+        Ok(result) => result,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(zip_error(e)),
+    };
+    let mut contents = String::new();
+    entry
+        .read_to_string(&mut contents)
+        .map_err(ModManError::IoError)?;
+    Ok(Some(contents))
+}
+
+// Reads every file under `prefix` in the zip, returning their paths (relative to `prefix`) and contents.
+// Entries with absolute paths or '..' in them are skipped (and listed), so they can't be written outside the game directory.
+pub fn read_zip_folder(
+    zip: &mut ZipArchive<File>,
+    prefix: &str,
+) -> Result<Vec<(PathBuf, Vec<u8>)>, ModManError> {
+    let mut files = Vec::new();
+    for index in 0..zip.len() {
+        let mut entry = zip.by_index(index).map_err(zip_error)?;
+        let relative_path = match Path::new(entry.name()).strip_prefix(prefix) {
+            Ok(result) if entry.is_file() && !result.as_os_str().is_empty() => result.to_path_buf(),
+            _ => continue,
+        };
+        if !is_relative_path(&relative_path) || entry.name().contains('\\') {
+            alert!(format!(
+                "Skipped '{}': it is outside the game directory.",
+                entry.name()
+            ));
+            continue;
+        }
+        let mut bytes = Vec::new();
+        entry
+            .read_to_end(&mut bytes)
+            .map_err(ModManError::IoError)?;
+        files.push((relative_path, bytes));
+    }
+    Ok(files)
+}
+
+// Whether `path` only goes down into folders, with no '..', root or prefix (e.g. 'C:') in it.
+pub fn is_relative_path(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+// Whether `name` is just the name of a file, which can't point into another folder (e.g. '../mod.jar', or 'a\\b.jar' on Windows).
+pub fn is_file_name(name: &str) -> bool {
+    !name.contains('\\') && Path::new(name).file_name() == Some(name.as_ref())
+}
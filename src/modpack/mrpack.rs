@@ -0,0 +1,332 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::is_file_name;
+use crate::datatypes::{Config, GameLoader, LockMod, ModSources, ProjectType, Side, SideSupport};
+
+/*
+    Modrinth modpacks (.mrpack): a zip with an index (modrinth.index.json) of the files to download,
+    and an 'overrides' folder that is copied into the game directory.
+
+    See https://support.modrinth.com/en/articles/8802351-modrinth-modpack-format-mrpack
+*/
+
+pub const INDEX_FILE_NAME: &str = "modrinth.index.json";
+pub const OVERRIDES_FOLDER: &str = "overrides";
+
+// Hosts that files may be downloaded from. Modrinth rejects packs with downloads from anywhere else.
+pub const ALLOWED_DOWNLOAD_HOSTS: &[&str] = &[
+    "cdn.modrinth.com",
+    "github.com",
+    "raw.githubusercontent.com",
+    "gitlab.com",
+];
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MrpackIndex {
+    pub format_version: u32,
+    pub game: String,
+    pub version_id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    pub files: Vec<MrpackFile>,
+    pub dependencies: BTreeMap<String, String>, // 'minecraft' and the loader, to their versions.
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MrpackFile {
+    pub path: String, // Relative to the game directory, e.g. 'mods/sodium.jar'.
+    pub hashes: MrpackHashes,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<MrpackEnv>,
+    pub downloads: Vec<String>,
+    pub file_size: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MrpackHashes {
+    pub sha1: String,
+    pub sha512: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MrpackEnv {
+    pub client: String, // 'required', 'optional' or 'unsupported'
+    pub server: String,
+}
+
+// Name of the loader in the index's dependencies. Launchers only support these loaders.
+pub fn loader_dependency(game_loader: &GameLoader) -> Option<&'static str> {
+    match game_loader {
+        GameLoader::Fabric => Some("fabric-loader"),
+        GameLoader::Quilt => Some("quilt-loader"),
+        GameLoader::Forge => Some("forge"),
+        GameLoader::NeoForge => Some("neoforge"),
+        _ => None,
+    }
+}
+
+// The loader in the index's dependencies, and its version.
+pub fn loader_from_dependencies(
+    dependencies: &BTreeMap<String, String>,
+) -> Option<(GameLoader, String)> {
+    [
+        GameLoader::Fabric,
+        GameLoader::Quilt,
+        GameLoader::Forge,
+        GameLoader::NeoForge,
+    ]
+    .into_iter()
+    .find_map(|game_loader| {
+        let version = dependencies.get(loader_dependency(&game_loader)?)?;
+        Some((game_loader, version.clone()))
+    })
+}
+
+// Folder of a project type in the game directory. Datapacks belong to a world, so they have none.
+pub fn game_folder(project_type: &ProjectType) -> Option<&'static str> {
+    match project_type {
+        ProjectType::Mod => Some("mods"),
+        ProjectType::ResourcePack => Some("resourcepacks"),
+        ProjectType::Shader => Some("shaderpacks"),
+        ProjectType::Datapack => None,
+    }
+}
+
+// Project type of a file in the game directory, from its folder (e.g. 'mods/sodium.jar').
+pub fn project_type_of_path(path: &str) -> Option<ProjectType> {
+    let (folder, file_name) = path.split_once('/')?;
+    if file_name.contains('/') {
+        return None;
+    }
+    ProjectType::ALL
+        .into_iter()
+        .find(|project_type| game_folder(project_type) == Some(folder))
+}
+
+pub fn is_allowed_download(url: &str) -> bool {
+    let host = url
+        .strip_prefix("https://")
+        .and_then(|rest| rest.split('/').next())
+        .unwrap_or_default();
+    ALLOWED_DOWNLOAD_HOSTS.contains(&host)
+}
+
+// The side set on the mod in the config comes first, then the sides the project supports.
+// Nothing is written if the sides are not known, and launchers install the file on both.
+pub fn mrpack_env(config: &Config, lock_mod: &LockMod) -> Option<MrpackEnv> {
+    let mod_side = config
+        .mods
+        .iter()
+        .find(|m| m.id == lock_mod.id)
+        .and_then(|m| m.side.clone());
+    let (client, server) = match mod_side {
+        Some(Side::Client) => (SideSupport::Required, SideSupport::Unsupported),
+        Some(Side::Server) => (SideSupport::Unsupported, SideSupport::Required),
+        Some(Side::Both) => (SideSupport::Required, SideSupport::Required),
+        None => (lock_mod.client_side.clone(), lock_mod.server_side.clone()),
+    };
+    if client.is_unknown() && server.is_unknown() {
+        return None;
+    }
+    Some(MrpackEnv {
+        client: env_value(&client).to_string(),
+        server: env_value(&server).to_string(),
+    })
+}
+
+// The index entry of a lockfile entry, at `path` in the game directory.
+pub fn mrpack_file(config: &Config, lock_mod: &LockMod, path: String, sha1: String) -> MrpackFile {
+    MrpackFile {
+        path,
+        hashes: MrpackHashes {
+            sha1,
+            sha512: lock_mod.sha512.clone(),
+        },
+        env: mrpack_env(config, lock_mod),
+        downloads: vec![lock_mod.download_url.clone()],
+        file_size: lock_mod.size,
+    }
+}
+
+// Name of a file in the index, if it is just a file name. Packs are untrusted, so names which could point
+// into another folder (e.g. 'mods/a\\b.jar' or 'mods/..') are rejected.
+pub fn file_name_of_path(path: &str) -> Option<&str> {
+    let file_name = path.rsplit('/').next()?;
+    is_file_name(file_name).then_some(file_name)
+}
+
+// A lockfile entry from an index entry alone, for files which are not on Modrinth. It is a local mod, downloaded from the pack's url.
+pub fn local_lock_mod_from_file(
+    file: &MrpackFile,
+    file_name: &str,
+    project_type: ProjectType,
+) -> LockMod {
+    let side_support = |value: Option<&String>| -> SideSupport {
+        value.and_then(|v| v.parse().ok()).unwrap_or_default()
+    };
+    LockMod {
+        name: file_name.to_string(),
+        source: ModSources::Local,
+        id: file_name.to_string(),
+        version: "0".to_string(),
+        file_name: file_name.to_string(),
+        release_date: "Unknown".to_string(),
+        sha512: file.hashes.sha512.clone(),
+        sha1: Some(file.hashes.sha1.clone()),
+        download_url: file
+            .downloads
+            .first()
+            .cloned()
+            .unwrap_or_else(|| "Unknown".to_string()),
+        file_id: None,
+        dependencies: vec![],
+        size: file.file_size,
+        disabled: false,
+        project_type,
+        loaders: vec![],
+        game_versions: vec![],
+        client_side: side_support(file.env.as_ref().map(|e| &e.client)),
+        server_side: side_support(file.env.as_ref().map(|e| &e.server)),
+    }
+}
+
+// The side a file is only for, if the index says it is unsupported on the other one.
+pub fn side_from_env(env: Option<&MrpackEnv>) -> Option<Side> {
+    let env = env?;
+    match (env.client.as_str(), env.server.as_str()) {
+        ("unsupported", "unsupported") => None,
+        (_, "unsupported") => Some(Side::Client),
+        ("unsupported", _) => Some(Side::Server),
+        _ => None,
+    }
+}
+
+fn env_value(side_support: &SideSupport) -> &'static str {
+    match side_support {
+        SideSupport::Optional => "optional",
+        SideSupport::Unsupported => "unsupported",
+        SideSupport::Required | SideSupport::Unknown => "required",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::datatypes::{DownloadSettings, Mod, ModpackSettings, ReleaseTypes};
+
+    fn config(mods: Vec<Mod>) -> Config {
+        Config {
+            game_version: "1.20.1".to_string(),
+            game_loader: GameLoader::Fabric,
+            loader_version: None,
+            allowed_release_types: vec![ReleaseTypes::Release],
+            mods_folder: PathBuf::from("mods"),
+            resourcepacks_folder: PathBuf::from("resourcepacks"),
+            shaderpacks_folder: PathBuf::from("shaderpacks"),
+            datapacks_folder: PathBuf::from("world/datapacks"),
+            downloads: DownloadSettings::default(),
+            side: Side::Both,
+            modpack: ModpackSettings::default(),
+            instance: None,
+            mods,
+            profiles: Default::default(),
+        }
+    }
+
+    fn lock_mod(file_name: &str, project_type: ProjectType) -> LockMod {
+        LockMod {
+            name: "Sodium".to_string(),
+            source: ModSources::Modrinth,
+            id: "AANobbMI".to_string(),
+            version: "mc1.20.1-0.5.3".to_string(),
+            file_name: file_name.to_string(),
+            release_date: "2023-10-01".to_string(),
+            sha512: "ab".repeat(64),
+            sha1: Some("cd".repeat(20)),
+            download_url: format!(
+                "https://cdn.modrinth.com/data/AANobbMI/versions/OihdIimA/{}",
+                file_name
+            ),
+            file_id: None,
+            dependencies: vec![],
+            size: 1234,
+            disabled: false,
+            project_type,
+            loaders: vec![GameLoader::Fabric],
+            game_versions: vec!["1.20.1".to_string()],
+            client_side: SideSupport::Required,
+            server_side: SideSupport::Unsupported,
+        }
+    }
+
+    // Exports `lock_mod` into an index, and imports it back like 'modman import' does for files not on Modrinth.
+    fn round_trip(config: &Config, lock_mod: &LockMod) -> (LockMod, Option<Side>) {
+        let path = format!(
+            "{}/{}",
+            game_folder(&lock_mod.project_type).unwrap(),
+            lock_mod.file_name
+        );
+        let file = mrpack_file(config, lock_mod, path, lock_mod.sha1.clone().unwrap());
+        let json = serde_json::to_string(&file).unwrap();
+        let file: MrpackFile = serde_json::from_str(&json).unwrap();
+
+        let project_type = project_type_of_path(&file.path).unwrap();
+        let file_name = file_name_of_path(&file.path).unwrap();
+        let imported = local_lock_mod_from_file(&file, file_name, project_type);
+        (imported, side_from_env(file.env.as_ref()))
+    }
+
+    #[test]
+    fn exported_files_import_as_the_same_file() {
+        let exported = lock_mod("sodium-fabric-0.5.3.jar", ProjectType::Mod);
+        let (imported, side) = round_trip(&config(vec![]), &exported);
+
+        assert_eq!(imported.file_name, exported.file_name);
+        assert_eq!(imported.project_type, ProjectType::Mod);
+        assert_eq!(imported.sha512, exported.sha512);
+        assert_eq!(imported.sha1, exported.sha1);
+        assert_eq!(imported.download_url, exported.download_url);
+        assert_eq!(imported.size, exported.size);
+        assert_eq!(imported.client_side, SideSupport::Required);
+        assert_eq!(imported.server_side, SideSupport::Unsupported);
+        assert_eq!(side, Some(Side::Client));
+    }
+
+    #[test]
+    fn project_types_and_config_sides_survive_the_round_trip() {
+        let exported = lock_mod("faithful.zip", ProjectType::ResourcePack);
+        let config = config(vec![Mod {
+            source: ModSources::Modrinth,
+            id: exported.id.clone(),
+            name: exported.name.clone(),
+            enabled: true,
+            project_type: ProjectType::ResourcePack,
+            side: Some(Side::Server),
+        }]);
+        let (imported, side) = round_trip(&config, &exported);
+
+        assert_eq!(imported.project_type, ProjectType::ResourcePack);
+        assert_eq!(imported.file_name, "faithful.zip");
+        assert_eq!(side, Some(Side::Server));
+    }
+
+    #[test]
+    fn paths_leaving_their_folder_are_rejected() {
+        assert_eq!(file_name_of_path("mods/sodium.jar"), Some("sodium.jar"));
+        assert_eq!(file_name_of_path("mods/a\\b.jar"), None);
+        assert_eq!(file_name_of_path("mods/.."), None);
+        assert_eq!(file_name_of_path("mods/"), None);
+
+        assert_eq!(project_type_of_path("mods/../x.jar"), None);
+        assert_eq!(project_type_of_path("mods/a/b.jar"), None);
+        assert_eq!(project_type_of_path("../x.jar"), None);
+        assert_eq!(project_type_of_path("config/x.json"), None);
+    }
+}
@@ -0,0 +1,465 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+use super::{curseforge::curseforge_ids, is_file_name};
+use crate::datatypes::{Config, GameLoader, LockMod, ModSources, ProjectType, Side, SideSupport};
+
+/*
+    packwiz packs: a folder with a pack.toml (name, Minecraft and loader versions), and an index (index.toml) of every file
+    in the pack, with its hash. Files are either copied as-is into the game directory, or are metafiles ('<name>.pw.toml')
+    saying where to download a file from, and which project and version it is on Modrinth or CurseForge.
+
+    See https://packwiz.infra.link/reference/pack-format/
+*/
+
+pub const PACK_FILE_NAME: &str = "pack.toml";
+pub const INDEX_FILE_NAME: &str = "index.toml";
+pub const METAFILE_EXTENSION: &str = ".pw.toml";
+pub const PACK_FORMAT: &str = "packwiz:1.1.0";
+// The index and the files copied as-is are hashed with this. Metafiles have their own hash format.
+pub const HASH_FORMAT: &str = "sha256";
+
+// Loaders that packwiz can install, which are also its keys in [versions].
+pub const LOADERS: [GameLoader; 5] = [
+    GameLoader::Fabric,
+    GameLoader::Quilt,
+    GameLoader::Forge,
+    GameLoader::NeoForge,
+    GameLoader::LiteLoader,
+];
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizPack {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "pack-format", default)]
+    pub pack_format: String,
+    pub index: PackwizIndexFileRef,
+    pub versions: BTreeMap<String, String>, // 'minecraft' and the loader, to their versions.
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizIndexFileRef {
+    pub file: String, // Relative to pack.toml.
+    #[serde(rename = "hash-format")]
+    pub hash_format: String,
+    pub hash: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizIndex {
+    #[serde(rename = "hash-format")]
+    pub hash_format: String,
+    #[serde(default)]
+    pub files: Vec<PackwizIndexFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizIndexFile {
+    pub file: String, // Relative to the index, e.g. 'mods/sodium.pw.toml' or 'config/sodium-options.json'.
+    pub hash: String,
+    #[serde(
+        rename = "hash-format",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub hash_format: Option<String>, // Overrides the index's hash format.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub metafile: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizMod {
+    pub name: String,
+    pub filename: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub side: Option<String>, // 'client', 'server' or 'both'
+    pub download: PackwizDownload,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub option: Option<PackwizOption>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update: Option<PackwizUpdate>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizDownload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>, // None for CurseForge files, which are downloaded through CurseForge's API.
+    #[serde(rename = "hash-format")]
+    pub hash_format: String,
+    pub hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>, // 'metadata:curseforge' for CurseForge files.
+}
+
+// Optional mods, which users choose to install or not.
+#[derive(Serialize, Deserialize)]
+pub struct PackwizOption {
+    pub optional: bool,
+    #[serde(default)]
+    pub default: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modrinth: Option<PackwizModrinth>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub curseforge: Option<PackwizCurseForge>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizModrinth {
+    #[serde(rename = "mod-id")]
+    pub mod_id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub version: String, // Version id, not the version number.
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizCurseForge {
+    #[serde(rename = "file-id")]
+    pub file_id: u64,
+    #[serde(rename = "project-id")]
+    pub project_id: u64,
+}
+
+impl PackwizMod {
+    // Disabled mods are written as optional mods which are not installed by default.
+    pub fn is_disabled(&self) -> bool {
+        self.option
+            .as_ref()
+            .is_some_and(|o| o.optional && !o.default)
+    }
+
+    // The file name, if it is just a file name. Packs are untrusted, so names which could point into another folder
+    // (e.g. '../x.jar' or 'a/b.jar') are rejected.
+    pub fn file_name(&self) -> Option<&str> {
+        is_file_name(&self.filename).then_some(self.filename.as_str())
+    }
+}
+
+pub fn sha256(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+// Whether `bytes` have the hash. Hash formats which modman can't check (md5, murmur2) are trusted.
+pub fn hash_matches(bytes: &[u8], hash_format: &str, hash: &str) -> bool {
+    let actual = match hash_format {
+        "sha256" => sha256(bytes),
+        "sha512" => hex::encode(Sha512::digest(bytes)),
+        "sha1" => hex::encode(Sha1::digest(bytes)),
+        _ => return true,
+    };
+    actual.eq_ignore_ascii_case(hash)
+}
+
+// The loader in [versions], and its version.
+pub fn loader_from_versions(versions: &BTreeMap<String, String>) -> Option<(GameLoader, String)> {
+    LOADERS.into_iter().find_map(|game_loader| {
+        let version = versions.get(&game_loader.to_string())?;
+        Some((game_loader, version.clone()))
+    })
+}
+
+// Modrinth's version id, from the download url of one of its files
+// ('https://cdn.modrinth.com/data/<project id>/versions/<version id>/<file name>').
+pub fn modrinth_version_id(url: &str) -> Option<&str> {
+    let rest = url.strip_prefix("https://cdn.modrinth.com/data/")?;
+    match rest.split('/').collect::<Vec<_>>().as_slice() {
+        [_, "versions", version_id, _] => Some(version_id),
+        _ => None,
+    }
+}
+
+// Name of the metafile of a mod, like packwiz names them: its name in lowercase, with dashes.
+pub fn metafile_name(name: &str) -> String {
+    let slug: Vec<String> = name
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(str::to_string)
+        .collect();
+    match slug.is_empty() {
+        true => format!("mod{}", METAFILE_EXTENSION),
+        false => format!("{}{}", slug.join("-"), METAFILE_EXTENSION),
+    }
+}
+
+// The side set on the mod in the config comes first, then the sides the project supports. Nothing is written if they are not known.
+pub fn packwiz_side(config: &Config, lock_mod: &LockMod) -> Option<String> {
+    let mod_side = config
+        .mods
+        .iter()
+        .find(|m| m.id == lock_mod.id)
+        .and_then(|m| m.side.clone());
+    let side = match (mod_side, &lock_mod.client_side, &lock_mod.server_side) {
+        (Some(side), _, _) => side,
+        (None, SideSupport::Unsupported, SideSupport::Unsupported) => return None,
+        (None, _, SideSupport::Unsupported) => Side::Client,
+        (None, SideSupport::Unsupported, _) => Side::Server,
+        (None, SideSupport::Unknown, SideSupport::Unknown) => return None,
+        (None, _, _) => Side::Both,
+    };
+    Some(side.to_string())
+}
+
+// The side a mod is only for, if the metafile says so.
+pub fn side_from_packwiz(side: Option<&str>) -> Option<Side> {
+    match side?.parse() {
+        Ok(Side::Both) | Err(_) => None,
+        Ok(side) => Some(side),
+    }
+}
+
+// The metafile of a lockfile entry. CurseForge files are downloaded through CurseForge's API, and checked with their sha1.
+// None if the lockfile has no hash to check the file with.
+pub fn metafile(config: &Config, lock_mod: &LockMod) -> Option<PackwizMod> {
+    let (download, update) = match curseforge_ids(lock_mod) {
+        Some((project_id, file_id)) => {
+            let download = PackwizDownload {
+                url: None,
+                hash_format: "sha1".to_string(),
+                hash: lock_mod.sha1.clone()?,
+                mode: Some("metadata:curseforge".to_string()),
+            };
+            let update = PackwizUpdate {
+                modrinth: None,
+                curseforge: Some(PackwizCurseForge {
+                    file_id,
+                    project_id,
+                }),
+            };
+            (download, Some(update))
+        }
+        None => {
+            // The sha512 of a CurseForge file is only known once it is downloaded.
+            let (hash_format, hash) = match (lock_mod.sha512.is_empty(), &lock_mod.sha1) {
+                (false, _) => ("sha512", lock_mod.sha512.clone()),
+                (true, Some(sha1)) => ("sha1", sha1.clone()),
+                (true, None) => return None,
+            };
+            let download = PackwizDownload {
+                url: Some(lock_mod.download_url.clone()),
+                hash_format: hash_format.to_string(),
+                hash,
+                mode: None,
+            };
+            let update = (lock_mod.source == ModSources::Modrinth).then(|| PackwizUpdate {
+                modrinth: Some(PackwizModrinth {
+                    mod_id: lock_mod.id.clone(),
+                    version: modrinth_version_id(&lock_mod.download_url)
+                        .unwrap_or_default()
+                        .to_string(),
+                }),
+                curseforge: None,
+            });
+            (download, update)
+        }
+    };
+
+    Some(PackwizMod {
+        name: lock_mod.name.clone(),
+        filename: lock_mod.file_name.clone(),
+        side: packwiz_side(config, lock_mod),
+        download,
+        option: lock_mod.disabled.then_some(PackwizOption {
+            optional: true,
+            default: false,
+        }),
+        update,
+    })
+}
+
+// A lockfile entry from a metafile alone, for files which could not be looked up. It keeps the same file, but not its
+// dependencies or the versions it supports. Files with no Modrinth project are local mods, downloaded from their url.
+pub fn lock_mod_from_metafile(packwiz_mod: &PackwizMod) -> Option<LockMod> {
+    let file_name = packwiz_mod.file_name()?;
+    let download = &packwiz_mod.download;
+    let (sha512, sha1) = match download.hash_format.as_str() {
+        "sha512" => (download.hash.clone(), None),
+        "sha1" => (String::new(), Some(download.hash.clone())),
+        _ => return None,
+    };
+    let (source, id, version) = match packwiz_mod
+        .update
+        .as_ref()
+        .and_then(|u| u.modrinth.as_ref())
+    {
+        Some(modrinth) if !modrinth.version.is_empty() => (
+            ModSources::Modrinth,
+            modrinth.mod_id.clone(),
+            modrinth.version.clone(),
+        ),
+        _ => (ModSources::Local, file_name.to_string(), "0".to_string()),
+    };
+    let (client_side, server_side) = match side_from_packwiz(packwiz_mod.side.as_deref()) {
+        Some(Side::Client) => (SideSupport::Required, SideSupport::Unsupported),
+        Some(Side::Server) => (SideSupport::Unsupported, SideSupport::Required),
+        _ => (SideSupport::Unknown, SideSupport::Unknown),
+    };
+    Some(LockMod {
+        name: packwiz_mod.name.clone(),
+        source,
+        id,
+        version,
+        file_name: file_name.to_string(),
+        release_date: "Unknown".to_string(),
+        sha512,
+        sha1,
+        download_url: download.url.clone()?,
+        file_id: None,
+        dependencies: vec![],
+        size: 0,
+        disabled: false,
+        project_type: ProjectType::Mod,
+        loaders: vec![],
+        game_versions: vec![],
+        client_side,
+        server_side,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::datatypes::{DownloadSettings, ModpackSettings, ReleaseTypes};
+
+    fn config() -> Config {
+        Config {
+            game_version: "1.20.1".to_string(),
+            game_loader: GameLoader::Fabric,
+            loader_version: None,
+            allowed_release_types: vec![ReleaseTypes::Release],
+            mods_folder: PathBuf::from("mods"),
+            resourcepacks_folder: PathBuf::from("resourcepacks"),
+            shaderpacks_folder: PathBuf::from("shaderpacks"),
+            datapacks_folder: PathBuf::from("world/datapacks"),
+            downloads: DownloadSettings::default(),
+            side: Side::Both,
+            modpack: ModpackSettings::default(),
+            instance: None,
+            mods: vec![],
+            profiles: Default::default(),
+        }
+    }
+
+    fn lock_mod(source: ModSources, id: &str, file_name: &str) -> LockMod {
+        LockMod {
+            name: "Sodium".to_string(),
+            source,
+            id: id.to_string(),
+            version: "mc1.20.1-0.5.3".to_string(),
+            file_name: file_name.to_string(),
+            release_date: "2023-10-01".to_string(),
+            sha512: "ab".repeat(64),
+            sha1: Some("cd".repeat(20)),
+            download_url: format!(
+                "https://cdn.modrinth.com/data/AANobbMI/versions/OihdIimA/{}",
+                file_name
+            ),
+            file_id: None,
+            dependencies: vec![],
+            size: 1234,
+            disabled: false,
+            project_type: ProjectType::Mod,
+            loaders: vec![GameLoader::Fabric],
+            game_versions: vec!["1.20.1".to_string()],
+            client_side: SideSupport::Required,
+            server_side: SideSupport::Unsupported,
+        }
+    }
+
+    // Writes the metafile of `lock_mod`, and reads it back.
+    fn exported(lock_mod: &LockMod) -> PackwizMod {
+        let packwiz_mod = metafile(&config(), lock_mod).unwrap();
+        let toml = toml::to_string_pretty(&packwiz_mod).unwrap();
+        toml::from_str(&toml).unwrap()
+    }
+
+    fn with_filename(filename: &str) -> PackwizMod {
+        let mut packwiz_mod = exported(&lock_mod(ModSources::Modrinth, "AANobbMI", "x.jar"));
+        packwiz_mod.filename = filename.to_string();
+        packwiz_mod
+    }
+
+    #[test]
+    fn modrinth_metafiles_import_as_the_same_version() {
+        let lock_mod = lock_mod(ModSources::Modrinth, "AANobbMI", "sodium-fabric-0.5.3.jar");
+        let packwiz_mod = exported(&lock_mod);
+        let imported = lock_mod_from_metafile(&packwiz_mod).unwrap();
+
+        assert_eq!(imported.source, ModSources::Modrinth);
+        assert_eq!(imported.id, "AANobbMI");
+        assert_eq!(imported.version, "OihdIimA");
+        assert_eq!(imported.file_name, lock_mod.file_name);
+        assert_eq!(imported.sha512, lock_mod.sha512);
+        assert_eq!(imported.download_url, lock_mod.download_url);
+        assert_eq!(imported.client_side, SideSupport::Required);
+        assert_eq!(imported.server_side, SideSupport::Unsupported);
+        assert!(!packwiz_mod.is_disabled());
+    }
+
+    #[test]
+    fn local_mods_without_sha512_import_with_their_sha1() {
+        let mut lock_mod = lock_mod(ModSources::Local, "my-mod.jar", "my-mod.jar");
+        lock_mod.sha512 = String::new();
+        lock_mod.disabled = true;
+        let packwiz_mod = exported(&lock_mod);
+        let imported = lock_mod_from_metafile(&packwiz_mod).unwrap();
+
+        assert_eq!(packwiz_mod.download.hash_format, "sha1");
+        assert!(packwiz_mod.is_disabled());
+        assert_eq!(imported.source, ModSources::Local);
+        assert_eq!(imported.id, "my-mod.jar");
+        assert_eq!(imported.sha1, lock_mod.sha1);
+        assert!(imported.sha512.is_empty());
+    }
+
+    #[test]
+    fn curseforge_metafiles_keep_their_ids() {
+        let mut lock_mod = lock_mod(ModSources::CurseForge, "394468", "sodium.jar");
+        lock_mod.file_id = Some(4776445);
+        let packwiz_mod = exported(&lock_mod);
+        let ids = packwiz_mod
+            .update
+            .as_ref()
+            .unwrap()
+            .curseforge
+            .as_ref()
+            .unwrap();
+
+        assert_eq!((ids.project_id, ids.file_id), (394468, 4776445));
+        assert_eq!(packwiz_mod.download.hash_format, "sha1");
+        assert_eq!(packwiz_mod.download.url, None);
+        // They are looked up on CurseForge by their ids, as there is no url to download them from.
+        assert!(lock_mod_from_metafile(&packwiz_mod).is_none());
+
+        lock_mod.sha1 = None;
+        assert!(metafile(&config(), &lock_mod).is_none());
+    }
+
+    #[test]
+    fn file_names_with_path_parts_are_rejected() {
+        assert_eq!(with_filename("x.jar").file_name(), Some("x.jar"));
+        for filename in ["../x.jar", "a/b.jar", "a\\b.jar", "/x.jar", "..", ""] {
+            let packwiz_mod = with_filename(filename);
+            assert_eq!(packwiz_mod.file_name(), None, "{}", filename);
+            assert!(
+                lock_mod_from_metafile(&packwiz_mod).is_none(),
+                "{}",
+                filename
+            );
+        }
+    }
+}
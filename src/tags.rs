@@ -0,0 +1,130 @@
+use std::{fs, path::PathBuf, str::FromStr};
+
+use colored::Colorize;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alert,
+    api::modrinth::{
+        fetch_modrinth_game_versions, fetch_modrinth_loaders, ModrinthGameVersion, ModrinthLoader,
+    },
+    datatypes::GameLoader,
+    utils::{closest_matches, user_cache_dir},
+};
+
+/*
+    Modrinth's lists of Minecraft versions and loaders (/v2/tag/game_version and /v2/tag/loader),
+    used to check the game version and loader before they are saved to the config.
+
+    The lists are fetched every time they are needed, and cached (<user cache folder>/modman/tags.json)
+    for when Modrinth can't be reached. Without either, the values are not checked.
+*/
+
+#[derive(Serialize, Deserialize)]
+pub struct GameTags {
+    pub game_versions: Vec<ModrinthGameVersion>,
+    pub loaders: Vec<ModrinthLoader>,
+}
+
+fn cache_path() -> Option<PathBuf> {
+    user_cache_dir().map(|dir| dir.join("tags.json"))
+}
+
+impl GameTags {
+    pub async fn load(client: &Client) -> Option<GameTags> {
+        let (game_versions, loaders) = tokio::join!(
+            fetch_modrinth_game_versions(client),
+            fetch_modrinth_loaders(client)
+        );
+        let error = match (game_versions, loaders) {
+            (Ok(game_versions), Ok(loaders)) => {
+                let tags = GameTags {
+                    game_versions,
+                    loaders,
+                };
+                tags.save(); // The tags are still usable if they can't be cached.
+                return Some(tags);
+            }
+            (Err(e), _) | (_, Err(e)) => e,
+        };
+
+        let cached: Option<GameTags> = cache_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok());
+        match cached {
+            Some(_) => alert!(format!(
+                "Could not fetch the Minecraft versions and loaders from Modrinth ({}). Using the cached lists.",
+                error
+            )),
+            None => alert!(format!(
+                "Could not fetch the Minecraft versions and loaders from Modrinth ({}). They will not be checked.",
+                error
+            )),
+        }
+        cached
+    }
+
+    fn save(&self) {
+        let Some(path) = cache_path() else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(content) = serde_json::to_string(self) {
+            let _ = fs::write(path, content);
+        }
+    }
+
+    // The newest full release (not a snapshot).
+    pub fn latest_release(&self) -> Option<&str> {
+        self.game_versions
+            .iter()
+            .find(|v| v.version_type == "release")
+            .map(|v| v.version.as_str())
+    }
+
+    pub fn check_game_version(&self, game_version: &str) -> Result<(), String> {
+        if self.game_versions.iter().any(|v| v.version == game_version) {
+            return Ok(());
+        }
+        let versions: Vec<&str> = self
+            .game_versions
+            .iter()
+            .map(|v| v.version.as_str())
+            .collect();
+        Err(format!(
+            "'{}' is not a Minecraft version known to Modrinth.{}",
+            game_version,
+            did_you_mean(&closest_matches(game_version, &versions, 3))
+        ))
+    }
+
+    pub fn check_loader(&self, game_loader: &GameLoader) -> Result<(), String> {
+        let name = game_loader.to_string();
+        if self.loaders.iter().any(|l| l.name == name) {
+            return Ok(());
+        }
+        Err(format!("'{}' is not a loader known to Modrinth.", name))
+    }
+
+    // Loaders close to a mistyped one. Only loaders modman knows about are suggested.
+    pub fn suggest_loaders(&self, input: &str) -> String {
+        let loaders: Vec<&str> = self
+            .loaders
+            .iter()
+            .map(|l| l.name.as_str())
+            .filter(|name| GameLoader::from_str(name).is_ok())
+            .collect();
+        did_you_mean(&closest_matches(input, &loaders, 3))
+    }
+}
+
+fn did_you_mean(matches: &[&str]) -> String {
+    match matches {
+        [] => String::new(),
+        [only] => format!(" Did you mean '{}'?", only),
+        _ => format!(" Did you mean one of: '{}'?", matches.join("', '")),
+    }
+}
@@ -0,0 +1,689 @@
+use colored::Colorize;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    alert,
+    config::{config_contents, lockfile_contents, lockfile_name, parse_lockfile, read_config},
+    datatypes::{Config, LockMod},
+    errors::ModManError,
+    install::{
+        calculate_sha512, download_all_mods, remove_replaced_file, staging_path,
+        verify_file_hashes, DownloadReport, DownloadTask,
+    },
+    utils::modman_dir,
+};
+
+/*
+    Transactions journal every change a mutating command makes, BEFORE it is made.
+
+    Layout (inside the project directory):
+        .modman/transactions/<id>/journal.toml      - The planned steps, and whether each one is done.
+        .modman/transactions/<id>/staged/           - New contents of modman.toml / modman.lock, written before being moved into place.
+        .modman/transactions/<id>/backup/           - Copies of every file that a step will overwrite or remove.
+
+    A transaction directory is removed once every step is done. Anything left in .modman/transactions is therefore an
+    incomplete (interrupted or failed) transaction, which can be resumed or rolled back with `modman transactions`.
+    A transaction directory without a journal changed nothing; `modman transactions` removes it.
+*/
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Transaction {
+    pub id: String,
+    pub command: String,
+    pub started: u64, // Unix timestamp (seconds)
+    pub steps: Vec<TransactionStep>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TransactionStep {
+    pub done: bool,
+    pub action: TransactionAction,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum TransactionAction {
+    Download {
+        task: DownloadTask,
+        backup: Option<PathBuf>, // Copy of the file at `task.dest` if it existed before the transaction.
+        replaced_backup: Option<PathBuf>, // Copy of the file at `task.replaces`.
+    },
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    WriteFile {
+        path: PathBuf,
+        staged: PathBuf,
+        backup: Option<PathBuf>, // Copy of the file at `path` if it existed before the transaction.
+        // For lockfiles: (index, installed file) of entries whose sha512 and size are taken from their downloaded file.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        fill_in: Vec<(usize, PathBuf)>,
+    },
+    Delete {
+        path: PathBuf,
+        backup: Option<PathBuf>, // Copy of the file at `path`, restored on rollback.
+    },
+}
+
+impl std::fmt::Display for TransactionAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransactionAction::Download { task, .. } => match &task.replaces {
+                Some(replaces) if *replaces != task.dest => write!(
+                    f,
+                    "Download '{}' to {}, replacing {}",
+                    task.name,
+                    task.dest.display(),
+                    replaces.display()
+                ),
+                _ => write!(f, "Download '{}' to {}", task.name, task.dest.display()),
+            },
+            TransactionAction::Rename { from, to } => {
+                write!(f, "Rename {} to {}", from.display(), to.display())
+            }
+            TransactionAction::WriteFile { path, .. } => write!(f, "Write {}", path.display()),
+            TransactionAction::Delete { path, .. } => write!(f, "Delete {}", path.display()),
+        }
+    }
+}
+
+pub fn transactions_dir(dir: &Path) -> PathBuf {
+    modman_dir(dir).join("transactions")
+}
+
+impl Transaction {
+    // Creates a new, empty transaction. Nothing is written to the journal until `save` or `run` is called.
+    pub fn begin(dir: &Path, command: &str) -> Result<Transaction, ModManError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let transaction = Transaction {
+            id: now.as_millis().to_string(),
+            command: command.to_string(),
+            started: now.as_secs(),
+            steps: Vec::new(),
+        };
+        fs::create_dir_all(transaction.path(dir)).map_err(ModManError::IoError)?;
+        Ok(transaction)
+    }
+
+    pub fn load(dir: &Path, id: &str) -> Result<Transaction, ModManError> {
+        let journal_path = transactions_dir(dir).join(id).join("journal.toml");
+        if !journal_path.exists() {
+            return Err(ModManError::FileNotFound);
+        }
+        let toml_content = fs::read_to_string(journal_path).map_err(ModManError::IoError)?;
+        toml::from_str(&toml_content).map_err(ModManError::DeserializationError)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), ModManError> {
+        let journal_data = toml::to_string_pretty(self).map_err(ModManError::SerializationError)?;
+        fs::write(self.path(dir).join("journal.toml"), journal_data).map_err(ModManError::IoError)
+    }
+
+    fn path(&self, dir: &Path) -> PathBuf {
+        transactions_dir(dir).join(&self.id)
+    }
+
+    // Copies `file` into the backup folder of this transaction (if it exists), returning the path of the copy.
+    fn backup(&self, dir: &Path, file: &Path) -> Result<Option<PathBuf>, ModManError> {
+        if !file.exists() {
+            return Ok(None);
+        }
+        let backup_dir = self.path(dir).join("backup");
+        fs::create_dir_all(&backup_dir).map_err(ModManError::IoError)?;
+        // Prefix with the number of backups taken so far, as files from different folders may share a name
+        // (and one step can back up two files).
+        let count = fs::read_dir(&backup_dir)
+            .map_err(ModManError::IoError)?
+            .count();
+        let file_name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let backup_path = backup_dir.join(format!("{}-{}", count, file_name));
+        fs::copy(file, &backup_path).map_err(ModManError::IoError)?;
+        Ok(Some(backup_path))
+    }
+
+    pub fn plan_download(&mut self, dir: &Path, task: DownloadTask) -> Result<(), ModManError> {
+        let backup = self.backup(dir, &task.dest)?;
+        let replaced_backup = match &task.replaces {
+            Some(replaces) if *replaces != task.dest => self.backup(dir, replaces)?,
+            _ => None,
+        };
+        self.steps.push(TransactionStep {
+            done: false,
+            action: TransactionAction::Download {
+                task,
+                backup,
+                replaced_backup,
+            },
+        });
+        Ok(())
+    }
+
+    pub fn plan_rename(&mut self, from: PathBuf, to: PathBuf) {
+        self.steps.push(TransactionStep {
+            done: false,
+            action: TransactionAction::Rename { from, to },
+        });
+    }
+
+    pub fn plan_delete(&mut self, dir: &Path, path: PathBuf) -> Result<(), ModManError> {
+        let backup = self.backup(dir, &path)?;
+        self.steps.push(TransactionStep {
+            done: false,
+            action: TransactionAction::Delete { path, backup },
+        });
+        Ok(())
+    }
+
+    pub fn plan_write_config(&mut self, dir: &Path, config: &Config) -> Result<(), ModManError> {
+        let staged_dir = self.path(dir).join("staged");
+        fs::create_dir_all(&staged_dir).map_err(ModManError::IoError)?;
+        let config_data = config_contents(dir, config)?;
+        fs::write(staged_dir.join("modman.toml"), config_data).map_err(ModManError::IoError)?;
+        self.plan_write(dir, "modman.toml")
+    }
+
+    pub fn plan_write_lockfile(
+        &mut self,
+        dir: &Path,
+        lockfile: &[LockMod],
+    ) -> Result<(), ModManError> {
+        let staged_dir = self.path(dir).join("staged");
+        fs::create_dir_all(&staged_dir).map_err(ModManError::IoError)?;
+        let file_name = lockfile_name(dir)?;
+        fs::write(staged_dir.join(&file_name), lockfile_contents(lockfile)?)
+            .map_err(ModManError::IoError)?;
+        self.plan_write(dir, &file_name)
+    }
+
+    // Writes any other file (e.g. a launcher's instance file, or a modpack's override), staged under its file name.
+    // Returns the path of the staged file, which can be read (or written again) until the transaction is finished.
+    pub fn plan_write_file(
+        &mut self,
+        dir: &Path,
+        path: PathBuf,
+        contents: &[u8],
+    ) -> Result<PathBuf, ModManError> {
+        let staged_dir = self.path(dir).join("staged");
+        fs::create_dir_all(&staged_dir).map_err(ModManError::IoError)?;
+        // Prefix with the step index, as files from different folders may share a name.
+        let staged = match path.file_name() {
+            Some(file_name) => staged_dir.join(format!(
+                "{}-{}",
+                self.steps.len(),
+                file_name.to_string_lossy()
+            )),
+            None => return Err(ModManError::FileNotFound),
+        };
+        fs::write(&staged, contents).map_err(ModManError::IoError)?;
+        let backup = self.backup(dir, &path)?;
+        self.steps.push(TransactionStep {
+            done: false,
+            action: TransactionAction::WriteFile {
+                path,
+                staged: staged.clone(),
+                backup,
+                fill_in: Vec::new(),
+            },
+        });
+        Ok(staged)
+    }
+
+    // Writes a lockfile to `path` (e.g. the lockfile of a profile which is not in modman.toml yet). Entries which are
+    // downloaded without a known sha512 or size (listed with the path of their file) get them from their file when the
+    // step is done, so a resumed transaction fills them in too.
+    pub fn plan_write_lockfile_to(
+        &mut self,
+        dir: &Path,
+        path: PathBuf,
+        lockfile: &[LockMod],
+        fill_in: Vec<(usize, PathBuf)>,
+    ) -> Result<(), ModManError> {
+        self.plan_write_file(dir, path, lockfile_contents(lockfile)?.as_bytes())?;
+        if let Some(TransactionStep {
+            action:
+                TransactionAction::WriteFile {
+                    fill_in: step_fill_in,
+                    ..
+                },
+            ..
+        }) = self.steps.last_mut()
+        {
+            *step_fill_in = fill_in;
+        }
+        Ok(())
+    }
+
+    fn plan_write(&mut self, dir: &Path, file_name: &str) -> Result<(), ModManError> {
+        let path = dir.join(file_name);
+        let backup = self.backup(dir, &path)?;
+        self.steps.push(TransactionStep {
+            done: false,
+            action: TransactionAction::WriteFile {
+                path,
+                staged: self.path(dir).join("staged").join(file_name),
+                backup,
+                fill_in: Vec::new(),
+            },
+        });
+        Ok(())
+    }
+
+    // Runs every step which is not done yet, journaling the progress. Used both for new and resumed transactions.
+    // On success, the transaction is removed from the journal.
+    pub async fn run(&mut self, dir: &Path, client: &Arc<Client>) -> Result<(), ModManError> {
+        let report = self.download(dir, client).await?;
+        if !report.failed().is_empty() {
+            report.print_failures();
+            alert!("The transaction was stopped before changing anything else.");
+            return Err(ModManError::IncompleteTransaction(self.id.clone()));
+        }
+        self.finish(dir)
+    }
+
+    // (1) Downloads. These run all at once, so they are done before any other step.
+    // Only downloads which succeeded are marked as done.
+    pub async fn download(
+        &mut self,
+        dir: &Path,
+        client: &Arc<Client>,
+    ) -> Result<DownloadReport, ModManError> {
+        self.save(dir)?;
+
+        let mut pending_downloads: Vec<usize> = Vec::new();
+        let mut downloads: Vec<DownloadTask> = Vec::new();
+        for (index, step) in self.steps.iter_mut().enumerate() {
+            if step.done {
+                continue;
+            }
+            if let TransactionAction::Download { task, .. } = &step.action {
+                // A resumed transaction may have already downloaded this file.
+                if task.dest.exists()
+                    && verify_file_hashes(&task.dest, &task.sha512, task.sha1.as_deref())
+                        .unwrap_or(false)
+                {
+                    remove_replaced_file(task).map_err(ModManError::IoError)?;
+                    step.done = true;
+                    continue;
+                }
+                pending_downloads.push(index);
+                downloads.push(task.clone());
+            }
+        }
+
+        // Download settings come from the project's config, if it can be read.
+        let settings = read_config(dir)
+            .map(|config| config.downloads)
+            .unwrap_or_default();
+        let report = download_all_mods(client, downloads, &settings).await;
+        for (index, outcome) in pending_downloads.into_iter().zip(&report.outcomes) {
+            self.steps[index].done = outcome.error.is_none();
+        }
+        self.save(dir)?;
+
+        Ok(report)
+    }
+
+    // Removes downloads which did not succeed (and their partial files), along with the (not yet done) config and lockfile writes.
+    // The caller plans the writes again, with only the mods that were actually installed.
+    pub fn drop_failed_downloads(&mut self, dir: &Path) -> Result<(), ModManError> {
+        for step in self.steps.iter().filter(|step| !step.done) {
+            if let TransactionAction::Download { task, .. } = &step.action {
+                remove_staged_download(task)?;
+            }
+        }
+        self.steps.retain(|step| {
+            step.done
+                || !matches!(
+                    step.action,
+                    TransactionAction::Download { .. } | TransactionAction::WriteFile { .. }
+                )
+        });
+        self.save(dir)
+    }
+
+    // (2) Every other step, in order. All downloads must be done.
+    pub fn finish(&mut self, dir: &Path) -> Result<(), ModManError> {
+        for index in 0..self.steps.len() {
+            if self.steps[index].done {
+                continue;
+            }
+            match &self.steps[index].action {
+                TransactionAction::Download { .. } => {
+                    return Err(ModManError::IncompleteTransaction(self.id.clone()));
+                }
+                // A step may have been done just before the process stopped, without being marked as done.
+                TransactionAction::Rename { from, to } => {
+                    if from.exists() || !to.exists() {
+                        fs::rename(from, to).map_err(ModManError::IoError)?;
+                    }
+                }
+                TransactionAction::WriteFile {
+                    path,
+                    staged,
+                    fill_in,
+                    ..
+                } => {
+                    if staged.exists() || !path.exists() {
+                        if !fill_in.is_empty() {
+                            fill_in_lockfile(staged, fill_in)?;
+                        }
+                        if let Some(parent) = path.parent() {
+                            fs::create_dir_all(parent).map_err(ModManError::IoError)?;
+                        }
+                        move_file(staged, path)?;
+                    }
+                }
+                TransactionAction::Delete { path, .. } => {
+                    if path.exists() {
+                        fs::remove_file(path).map_err(ModManError::IoError)?;
+                    }
+                }
+            }
+            self.steps[index].done = true;
+            self.save(dir)?;
+        }
+
+        fs::remove_dir_all(self.path(dir)).map_err(ModManError::IoError)
+    }
+
+    // Undoes every step (done or partially done) in reverse order, then removes the transaction from the journal.
+    pub fn rollback(&self, dir: &Path) -> Result<(), ModManError> {
+        for step in self.steps.iter().rev() {
+            match &step.action {
+                TransactionAction::Download {
+                    task,
+                    backup,
+                    replaced_backup,
+                } => {
+                    remove_staged_download(task)?;
+                    match backup {
+                        Some(backup) => {
+                            fs::copy(backup, &task.dest).map_err(ModManError::IoError)?;
+                        }
+                        None => {
+                            if task.dest.exists() {
+                                fs::remove_file(&task.dest).map_err(ModManError::IoError)?;
+                            }
+                        }
+                    }
+                    if let (Some(replaces), Some(replaced_backup)) =
+                        (&task.replaces, replaced_backup)
+                    {
+                        if !replaces.exists() {
+                            fs::copy(replaced_backup, replaces).map_err(ModManError::IoError)?;
+                        }
+                    }
+                }
+                TransactionAction::Rename { from, to } => {
+                    if to.exists() && !from.exists() {
+                        fs::rename(to, from).map_err(ModManError::IoError)?;
+                    }
+                }
+                TransactionAction::WriteFile {
+                    path,
+                    staged,
+                    backup,
+                    ..
+                } => {
+                    // The staged file is only gone once it was moved into place, even if the step is not marked as done.
+                    if !step.done && staged.exists() {
+                        continue;
+                    }
+                    match backup {
+                        Some(backup) => {
+                            fs::copy(backup, path).map_err(ModManError::IoError)?;
+                        }
+                        None => {
+                            if path.exists() {
+                                fs::remove_file(path).map_err(ModManError::IoError)?;
+                            }
+                        }
+                    }
+                }
+                TransactionAction::Delete { path, backup } => {
+                    if let Some(backup) = backup {
+                        if !path.exists() {
+                            fs::copy(backup, path).map_err(ModManError::IoError)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        fs::remove_dir_all(self.path(dir)).map_err(ModManError::IoError)
+    }
+}
+
+// Fills in the sha512 and size of lockfile entries from their installed files.
+fn fill_in_lockfile(staged: &Path, fill_in: &[(usize, PathBuf)]) -> Result<(), ModManError> {
+    let contents = fs::read_to_string(staged).map_err(ModManError::IoError)?;
+    let mut lockfile = parse_lockfile(&contents)?;
+    for (index, file) in fill_in {
+        let Some(lock_mod) = lockfile.get_mut(*index) else {
+            continue;
+        };
+        if file.is_file() {
+            lock_mod.sha512 = calculate_sha512(file).map_err(ModManError::IoError)?;
+            lock_mod.size = fs::metadata(file).map_err(ModManError::IoError)?.len();
+        }
+    }
+    fs::write(staged, lockfile_contents(&lockfile)?).map_err(ModManError::IoError)
+}
+
+// Removes the partial file of a download, and the staging folder if it is now empty.
+fn remove_staged_download(task: &DownloadTask) -> Result<(), ModManError> {
+    let staging = staging_path(&task.dest);
+    if staging.exists() {
+        fs::remove_file(&staging).map_err(ModManError::IoError)?;
+    }
+    if let Some(staging_dir) = staging.parent() {
+        let _ = fs::remove_dir(staging_dir);
+    }
+    Ok(())
+}
+
+// Moves a file, falling back to copy and delete if a rename is not possible (e.g. across file systems).
+fn move_file(from: &Path, to: &Path) -> Result<(), ModManError> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to).map_err(ModManError::IoError)?;
+        fs::remove_file(from).map_err(ModManError::IoError)?;
+    }
+    Ok(())
+}
+
+pub fn list_incomplete_transactions(dir: &Path) -> Result<Vec<Transaction>, ModManError> {
+    let transactions_path = transactions_dir(dir);
+    if !transactions_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut transactions: Vec<Transaction> = Vec::new();
+    for entry in fs::read_dir(transactions_path).map_err(ModManError::IoError)? {
+        let entry = entry.map_err(ModManError::IoError)?;
+        let id = entry.file_name().to_string_lossy().to_string();
+        match Transaction::load(dir, &id) {
+            Ok(result) => transactions.push(result),
+            // The journal was never written, so nothing was changed yet. See remove_unjournaled_transactions.
+            Err(ModManError::FileNotFound) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    transactions.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(transactions)
+}
+
+// Removes transactions which were stopped before their journal was written. They changed nothing, but may hold backups
+// and staged files. Returns how many were removed.
+pub fn remove_unjournaled_transactions(dir: &Path) -> Result<usize, ModManError> {
+    let transactions_path = transactions_dir(dir);
+    if !transactions_path.exists() {
+        return Ok(0);
+    }
+
+    let mut removed: usize = 0;
+    for entry in fs::read_dir(transactions_path).map_err(ModManError::IoError)? {
+        let entry = entry.map_err(ModManError::IoError)?;
+        if !entry.path().join("journal.toml").exists() {
+            fs::remove_dir_all(entry.path()).map_err(ModManError::IoError)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+// Step 0 of every mutating command: refuse to run while an earlier transaction is incomplete.
+pub fn check_incomplete_transactions(dir: &Path) -> Result<(), ModManError> {
+    let transactions = list_incomplete_transactions(dir)?;
+    if let Some(transaction) = transactions.first() {
+        alert!(format!(
+            "Found {} incomplete transaction(s) from an earlier failed or interrupted command.",
+            transactions.len()
+        ));
+        alert!("Please run 'modman transactions' to resume or roll them back first.");
+        return Err(ModManError::IncompleteTransaction(transaction.id.clone()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A transaction which renames 'a.jar' to 'b.jar' and writes 'notes.txt', journaled but not run.
+    fn journaled(dir: &Path) -> Transaction {
+        fs::write(dir.join("a.jar"), b"a").unwrap();
+        fs::write(dir.join("notes.txt"), b"old").unwrap();
+        let mut transaction = Transaction::begin(dir, "test").unwrap();
+        transaction.plan_rename(dir.join("a.jar"), dir.join("b.jar"));
+        transaction
+            .plan_write_file(dir, dir.join("notes.txt"), b"new")
+            .unwrap();
+        transaction.save(dir).unwrap();
+        transaction
+    }
+
+    // Does every step, as if the process stopped right before marking them as done.
+    fn run_steps_unmarked(dir: &Path, transaction: &Transaction) {
+        fs::rename(dir.join("a.jar"), dir.join("b.jar")).unwrap();
+        if let TransactionAction::WriteFile { path, staged, .. } = &transaction.steps[1].action {
+            fs::rename(staged, path).unwrap();
+        }
+    }
+
+    #[test]
+    fn journaled_transaction_is_resumed() {
+        let dir = tempfile::tempdir().unwrap();
+        let transaction = journaled(dir.path());
+
+        let mut loaded = Transaction::load(dir.path(), &transaction.id).unwrap();
+        loaded.finish(dir.path()).unwrap();
+
+        assert!(!dir.path().join("a.jar").exists());
+        assert!(dir.path().join("b.jar").exists());
+        assert_eq!(fs::read(dir.path().join("notes.txt")).unwrap(), b"new");
+        assert!(list_incomplete_transactions(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn steps_done_but_not_marked_are_resumed() {
+        let dir = tempfile::tempdir().unwrap();
+        let transaction = journaled(dir.path());
+        run_steps_unmarked(dir.path(), &transaction);
+
+        let mut loaded = Transaction::load(dir.path(), &transaction.id).unwrap();
+        loaded.finish(dir.path()).unwrap();
+
+        assert!(dir.path().join("b.jar").exists());
+        assert_eq!(fs::read(dir.path().join("notes.txt")).unwrap(), b"new");
+        assert!(list_incomplete_transactions(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn journaled_transaction_is_rolled_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let transaction = journaled(dir.path());
+
+        let loaded = Transaction::load(dir.path(), &transaction.id).unwrap();
+        loaded.rollback(dir.path()).unwrap();
+
+        assert!(dir.path().join("a.jar").exists());
+        assert!(!dir.path().join("b.jar").exists());
+        assert_eq!(fs::read(dir.path().join("notes.txt")).unwrap(), b"old");
+        assert!(list_incomplete_transactions(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn steps_done_but_not_marked_are_rolled_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let transaction = journaled(dir.path());
+        run_steps_unmarked(dir.path(), &transaction);
+
+        let loaded = Transaction::load(dir.path(), &transaction.id).unwrap();
+        loaded.rollback(dir.path()).unwrap();
+
+        assert!(dir.path().join("a.jar").exists());
+        assert!(!dir.path().join("b.jar").exists());
+        assert_eq!(fs::read(dir.path().join("notes.txt")).unwrap(), b"old");
+        assert!(list_incomplete_transactions(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn resumed_lockfile_write_fills_in_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let jar = dir.path().join("mods/jei.jar");
+        let lock_mod = LockMod {
+            name: "JEI".to_string(),
+            source: crate::datatypes::ModSources::CurseForge,
+            id: "238222".to_string(),
+            version: "1.0.0".to_string(),
+            file_name: "jei.jar".to_string(),
+            release_date: "Unknown".to_string(),
+            sha512: String::new(),
+            sha1: Some("0".repeat(40)),
+            download_url: "Unknown".to_string(),
+            file_id: Some(1),
+            dependencies: vec![],
+            size: 0,
+            disabled: false,
+            project_type: Default::default(),
+            loaders: vec![],
+            game_versions: vec![],
+            client_side: Default::default(),
+            server_side: Default::default(),
+        };
+        let mut transaction = Transaction::begin(dir.path(), "test").unwrap();
+        transaction
+            .plan_write_lockfile_to(
+                dir.path(),
+                dir.path().join("modman.lock"),
+                &[lock_mod],
+                vec![(0, jar.clone())],
+            )
+            .unwrap();
+        transaction.save(dir.path()).unwrap();
+        // The download finished, but the process stopped before the lockfile was written.
+        fs::create_dir_all(jar.parent().unwrap()).unwrap();
+        fs::write(&jar, b"jei").unwrap();
+
+        let mut loaded = Transaction::load(dir.path(), &transaction.id).unwrap();
+        loaded.finish(dir.path()).unwrap();
+
+        let contents = fs::read_to_string(dir.path().join("modman.lock")).unwrap();
+        let lockfile = parse_lockfile(&contents).unwrap();
+        assert_eq!(lockfile[0].sha512, calculate_sha512(&jar).unwrap());
+        assert_eq!(lockfile[0].size, 3);
+    }
+}
@@ -1,23 +1,82 @@
-use std::{env, path::PathBuf};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 
-use crate::datatypes::{Config, LockMod};
+use crate::{
+    datatypes::{Config, LockMod},
+    install::DownloadTask,
+};
 
 pub fn get_current_working_dir() -> std::io::Result<PathBuf> {
     env::current_dir()
 }
 
-pub fn convert_lock_mods_to_tuples(
+// Folder for modman's own state (transactions, caches, etc.) inside the project directory.
+pub fn modman_dir(dir: &Path) -> PathBuf {
+    dir.join(".modman")
+}
+
+// Folder for caches shared by every project (e.g. Modrinth's tag lists), so they are also available offline in new projects.
+pub fn user_cache_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join("modman"))
+}
+
+// Number of single character edits (insertions, deletions or substitutions) to turn `a` into `b`.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Up to `max` candidates close to `input`, closest first. Candidates which are equally close keep their order.
+pub fn closest_matches<'a>(input: &str, candidates: &[&'a str], max: usize) -> Vec<&'a str> {
+    let limit = (input.chars().count() / 3).max(2);
+    let mut matches: Vec<(usize, &str)> = candidates
+        .iter()
+        .map(|candidate| (levenshtein(input, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .collect();
+    matches.sort_by_key(|(distance, _)| *distance);
+    matches.into_iter().take(max).map(|(_, c)| c).collect()
+}
+
+// `installed` is the current lockfile, used to find older versions of the same mods which the downloads replace.
+pub fn convert_lock_mods_to_downloads(
+    dir: &Path,
     config: &Config,
     lock_mods: Vec<LockMod>,
-) -> Vec<(String, PathBuf, String, String)> {
+    installed: &[LockMod],
+) -> Vec<DownloadTask> {
     lock_mods
         .into_iter()
         .map(|lock_mod| {
-            let url = lock_mod.download_url;
-            let name = lock_mod.name;
-            let dest = config.mods_folder.join(&lock_mod.file_name);
-            let hash = lock_mod.sha512;
-            (url, dest, name, hash)
+            let replaces = installed
+                .iter()
+                .find(|installed_mod| installed_mod.id == lock_mod.id)
+                .map(|installed_mod| config.installed_path(dir, installed_mod));
+            let dest = config.installed_path(dir, &lock_mod);
+            DownloadTask {
+                name: lock_mod.name,
+                url: lock_mod.download_url,
+                dest,
+                sha512: lock_mod.sha512,
+                sha1: lock_mod.sha1,
+                size: lock_mod.size,
+                replaces,
+            }
         })
         .collect()
 }